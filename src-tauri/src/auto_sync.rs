@@ -0,0 +1,186 @@
+//! Background auto-sync
+//!
+//! Runs the GitHub two-way sync on a Rust-side timer, so it keeps going
+//! while the window is minimized or closed to the tray. The interval is
+//! saved in `auto_sync_config.json`; 0 disables auto-sync. Each run emits
+//! a `sync-completed` event with the outcome. Every tick also retries
+//! pushes queued while offline.
+
+use crate::github::commands::{check_pro_status, sync_github_bidirectional, GitHubBidirectionalSyncResult};
+use crate::paths;
+use crate::sync_error::SyncError;
+use crate::sync_lock::SyncLocks;
+use crate::sync_queue;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+use tokio::sync::watch;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const AUTO_SYNC_CONFIG_FILE: &str = "auto_sync_config.json";
+
+/// Event emitted after every background sync attempt
+pub const SYNC_COMPLETED_EVENT: &str = "sync-completed";
+
+/// Shortest accepted interval, so a typo can't hammer the GitHub API
+pub const MIN_AUTO_SYNC_INTERVAL_SECS: u64 = 60;
+
+/// Persisted auto-sync settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoSyncConfig {
+    /// Seconds between background syncs; 0 disables auto-sync
+    pub auto_sync_interval_secs: u64,
+}
+
+/// Payload of `sync-completed`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncCompletedEvent {
+    pub backend: String,
+    pub result: Option<GitHubBidirectionalSyncResult>,
+    pub error_code: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Handle to the running scheduler; changing the interval wakes it up
+pub struct AutoSyncScheduler {
+    interval_secs: watch::Sender<u64>,
+}
+
+/// Accept 0 (disabled) or an interval of at least `MIN_AUTO_SYNC_INTERVAL_SECS`
+pub fn validate_interval(interval_secs: u64) -> Result<(), String> {
+    if interval_secs != 0 && interval_secs < MIN_AUTO_SYNC_INTERVAL_SECS {
+        return Err(format!(
+            "Auto-sync interval must be 0 (off) or at least {} seconds",
+            MIN_AUTO_SYNC_INTERVAL_SECS
+        ));
+    }
+    Ok(())
+}
+
+/// Load the auto-sync settings, falling back to disabled
+pub fn load_auto_sync_config(store_dir: &Path) -> AutoSyncConfig {
+    fs::read_to_string(store_dir.join(AUTO_SYNC_CONFIG_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_auto_sync_config(store_dir: &Path, config: &AutoSyncConfig) -> Result<(), String> {
+    fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    fs::write(store_dir.join(AUTO_SYNC_CONFIG_FILE), content).map_err(|e| e.to_string())
+}
+
+fn get_store_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let base = paths::data_dir(app)?;
+    Ok(base.join(NEKOTICK_FOLDER).join(STORE_FOLDER))
+}
+
+/// Register the scheduler state and spawn the background loop
+pub fn start(app: &tauri::AppHandle) {
+    let interval_secs = get_store_dir(app)
+        .map(|dir| load_auto_sync_config(&dir).auto_sync_interval_secs)
+        .unwrap_or(0);
+    let (sender, receiver) = watch::channel(interval_secs);
+    app.manage(AutoSyncScheduler { interval_secs: sender });
+    tauri::async_runtime::spawn(run_scheduler(app.clone(), receiver));
+}
+
+async fn run_scheduler(app: tauri::AppHandle, mut interval: watch::Receiver<u64>) {
+    loop {
+        let secs = *interval.borrow_and_update();
+        if secs == 0 {
+            if interval.changed().await.is_err() {
+                return;
+            }
+            continue;
+        }
+
+        // A new interval restarts the wait instead of waiting out the old one
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(secs)) => auto_sync_once(&app).await,
+            changed = interval.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Retry queued pushes, then run one background sync if GitHub is connected and PRO is active
+async fn auto_sync_once(app: &tauri::AppHandle) {
+    sync_queue::flush_pending(app).await;
+
+    match check_pro_status(app.clone()).await {
+        Ok(status) if status.is_pro => {}
+        _ => return,
+    }
+
+    let result = sync_github_bidirectional(app.clone(), app.state::<SyncLocks>(), None, None).await;
+    // A manual sync is already running; it will report its own outcome
+    if matches!(result, Err(SyncError::AlreadyRunning)) {
+        return;
+    }
+
+    let event = match result {
+        Ok(result) => SyncCompletedEvent {
+            backend: "github".to_string(),
+            result: Some(result),
+            error_code: None,
+            error: None,
+        },
+        Err(e) => SyncCompletedEvent {
+            backend: "github".to_string(),
+            result: None,
+            error_code: Some(e.code().to_string()),
+            error: Some(e.to_string()),
+        },
+    };
+    let _ = app.emit(SYNC_COMPLETED_EVENT, event);
+}
+
+/// Set the background sync interval in seconds (0 disables it)
+#[tauri::command]
+pub async fn set_auto_sync_interval(
+    app: tauri::AppHandle,
+    scheduler: tauri::State<'_, AutoSyncScheduler>,
+    interval_secs: u64,
+) -> Result<(), String> {
+    validate_interval(interval_secs)?;
+    save_auto_sync_config(&get_store_dir(&app)?, &AutoSyncConfig { auto_sync_interval_secs: interval_secs })?;
+    scheduler.interval_secs.send_replace(interval_secs);
+    Ok(())
+}
+
+/// Get the background sync interval in seconds (0 means disabled)
+#[tauri::command]
+pub async fn get_auto_sync_interval(scheduler: tauri::State<'_, AutoSyncScheduler>) -> Result<u64, String> {
+    Ok(*scheduler.interval_secs.borrow())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_validation() {
+        assert!(validate_interval(0).is_ok());
+        assert!(validate_interval(MIN_AUTO_SYNC_INTERVAL_SECS).is_ok());
+        assert!(validate_interval(5).is_err());
+    }
+
+    #[test]
+    fn test_config_defaults_to_disabled_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load_auto_sync_config(dir.path()).auto_sync_interval_secs, 0);
+
+        save_auto_sync_config(dir.path(), &AutoSyncConfig { auto_sync_interval_secs: 900 }).unwrap();
+        assert_eq!(load_auto_sync_config(dir.path()).auto_sync_interval_secs, 900);
+    }
+}
@@ -0,0 +1,149 @@
+//! Rolling backups of local data files
+//!
+//! Before a restore or sync overwrites `data.json` (or `nekotick.md`) the
+//! current file is copied to `<name>.backup.<timestamp>` next to it, keeping
+//! the newest few so more than one recovery point survives.
+
+use crate::paths;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DATA_FILE_NAME: &str = "data.json";
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+
+/// Number of backups kept per file
+pub const DEFAULT_BACKUP_KEEP: usize = 5;
+
+/// Path of the backup of `file_name` taken at `timestamp` (Unix ms)
+fn backup_path(dir: &Path, file_name: &str, timestamp: i64) -> PathBuf {
+    dir.join(format!("{}.backup.{}", file_name, timestamp))
+}
+
+/// Timestamps of the existing backups of `file_name` in `dir`, newest first
+pub fn list_backup_timestamps(dir: &Path, file_name: &str) -> Result<Vec<i64>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let prefix = format!("{}.backup.", file_name);
+    let mut timestamps: Vec<i64> = fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            name.to_str()?.strip_prefix(&prefix)?.parse().ok()
+        })
+        .collect();
+    timestamps.sort_unstable_by(|a, b| b.cmp(a));
+    Ok(timestamps)
+}
+
+/// Back up `dir/file_name` and prune all but the newest `keep` backups
+///
+/// Returns the new backup's path, or `None` when there is no file to back up.
+pub fn rotate_backup(dir: &Path, file_name: &str, keep: usize) -> Result<Option<PathBuf>, String> {
+    let source = dir.join(file_name);
+    if !source.exists() {
+        return Ok(None);
+    }
+
+    write_backup(dir, file_name, keep, |backup| fs::copy(&source, backup).map(|_| ())).map(Some)
+}
+
+/// Keep `content` as the newest backup of `dir/file_name`, leaving the file itself alone
+///
+/// For a copy that is about to be lost somewhere else, such as remote notes
+/// a sync is going to overwrite.
+pub fn save_backup(dir: &Path, file_name: &str, content: &str, keep: usize) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    write_backup(dir, file_name, keep, |backup| fs::write(backup, content))
+}
+
+fn write_backup(
+    dir: &Path,
+    file_name: &str,
+    keep: usize,
+    write: impl FnOnce(&Path) -> std::io::Result<()>,
+) -> Result<PathBuf, String> {
+    let existing = list_backup_timestamps(dir, file_name)?;
+
+    // Stay strictly newer than the last backup even within the same millisecond
+    let now = chrono::Utc::now().timestamp_millis();
+    let timestamp = existing.first().map_or(now, |&newest| now.max(newest + 1));
+    let backup = backup_path(dir, file_name, timestamp);
+    write(&backup).map_err(|e| format!("Failed to create backup: {}", e))?;
+
+    for old in existing.into_iter().skip(keep.saturating_sub(1)) {
+        let _ = fs::remove_file(backup_path(dir, file_name, old));
+    }
+
+    Ok(backup)
+}
+
+/// List the timestamps of the available data.json backups, newest first
+#[tauri::command]
+pub async fn list_backups(app: tauri::AppHandle) -> Result<Vec<i64>, String> {
+    let base = paths::data_dir(&app)?;
+    let store_dir = base.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+    list_backup_timestamps(&store_dir, DATA_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_file_is_not_backed_up() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(rotate_backup(dir.path(), DATA_FILE_NAME, DEFAULT_BACKUP_KEEP).unwrap(), None);
+        assert!(list_backup_timestamps(dir.path(), DATA_FILE_NAME).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_keeps_newest_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_json_path = dir.path().join(DATA_FILE_NAME);
+
+        for i in 0..7 {
+            fs::write(&data_json_path, format!("version {}", i)).unwrap();
+            rotate_backup(dir.path(), DATA_FILE_NAME, 3).unwrap();
+        }
+
+        let timestamps = list_backup_timestamps(dir.path(), DATA_FILE_NAME).unwrap();
+        assert_eq!(timestamps.len(), 3);
+        assert!(timestamps.windows(2).all(|w| w[0] > w[1]));
+
+        let newest = backup_path(dir.path(), DATA_FILE_NAME, timestamps[0]);
+        let oldest = backup_path(dir.path(), DATA_FILE_NAME, timestamps[2]);
+        assert_eq!(fs::read_to_string(newest).unwrap(), "version 6");
+        assert_eq!(fs::read_to_string(oldest).unwrap(), "version 4");
+    }
+
+    #[test]
+    fn test_save_backup_keeps_content_without_touching_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("nekotick.md"), "# local").unwrap();
+
+        let backup = save_backup(dir.path(), "nekotick.md", "# remote", DEFAULT_BACKUP_KEEP).unwrap();
+
+        assert_eq!(fs::read_to_string(backup).unwrap(), "# remote");
+        assert_eq!(fs::read_to_string(dir.path().join("nekotick.md")).unwrap(), "# local");
+        assert_eq!(list_backup_timestamps(dir.path(), "nekotick.md").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_other_files_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(DATA_FILE_NAME), "{}").unwrap();
+        fs::write(dir.path().join("data.json.backup"), "legacy").unwrap();
+        fs::write(dir.path().join("nekotick.md.backup.5"), "notes").unwrap();
+        fs::write(dir.path().join("data.json.backup.tmp"), "junk").unwrap();
+
+        rotate_backup(dir.path(), DATA_FILE_NAME, 1).unwrap();
+
+        assert_eq!(list_backup_timestamps(dir.path(), DATA_FILE_NAME).unwrap().len(), 1);
+        assert!(dir.path().join("data.json.backup").exists());
+        assert!(dir.path().join("nekotick.md.backup.5").exists());
+    }
+}
@@ -0,0 +1,74 @@
+//! Sync diagnostics
+//!
+//! Probes every connected sync backend for a paste-able "Copy diagnostics"
+//! report. Only flags and error messages are reported; tokens and passwords
+//! are redacted from the messages.
+
+use serde::{Deserialize, Serialize};
+
+/// Diagnostic results for one sync backend
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendDiagnostics {
+    pub backend: String,
+    pub credentials_present: bool,
+    /// Stored credentials decrypt and parse on this device
+    pub credentials_valid: bool,
+    /// Sync metadata signature verifies; `None` when there is no signed metadata
+    pub metadata_valid: Option<bool>,
+    /// `None` when the credentials carry no expiry
+    pub token_expired: Option<bool>,
+    /// An authenticated API request succeeded
+    pub api_reachable: bool,
+    pub remote_data_exists: bool,
+    pub errors: Vec<String>,
+}
+
+/// Diagnostics for all connected backends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub generated_at: i64,
+    pub backends: Vec<BackendDiagnostics>,
+}
+
+/// Replace every occurrence of a secret in `message`
+pub fn redact(message: &str, secrets: &[&str]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(message.to_string(), |message, secret| message.replace(secret, "[redacted]"))
+}
+
+/// Check credentials, token expiry, API access and remote data for each connected backend
+#[tauri::command]
+pub async fn run_sync_diagnostics(app: tauri::AppHandle) -> Result<DiagnosticsReport, String> {
+    let backends = [
+        crate::github::commands::github_diagnostics(&app).await,
+        crate::webdav::commands::webdav_diagnostics(&app).await,
+    ];
+
+    Ok(DiagnosticsReport {
+        generated_at: chrono::Utc::now().timestamp(),
+        backends: backends.into_iter().flatten().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secrets() {
+        let message = "401 for token gho_abc123 (refresh ghr_def456)";
+        assert_eq!(
+            redact(message, &["gho_abc123", "ghr_def456"]),
+            "401 for token [redacted] (refresh [redacted])"
+        );
+    }
+
+    #[test]
+    fn test_empty_secret_is_ignored() {
+        assert_eq!(redact("Network error", &[""]), "Network error");
+    }
+}
@@ -3,18 +3,43 @@
 //! These commands are exposed to the frontend via Tauri's IPC.
 
 use crate::github::{
-    gist_api::GistClient,
-    oauth::GitHubOAuthClient,
+    compression,
+    credential_store,
+    gist_api::{Gist, GistApiError, GistClient, GistFetch, GistRevision, GitHubRateLimit},
+    meta_signing,
+    meta_writer::CoalescedWriter,
+    gist_api::{parse_gist_id, select_primary_email},
+    oauth::{GitHubOAuthClient, GitHubTokenResponse},
+    server::{self, GitHubServer},
 };
+use crate::backup;
+use crate::diagnostics::{self, BackendDiagnostics};
+use crate::paths;
+use crate::redact::{redact, redact_error};
+use crate::sync_crypto;
+use crate::sync_error::{LastSyncOutcome, SyncError};
+use crate::sync_lock::{check_cancelled, write_then_push, PushOutcome, SyncBackend, SyncLocks};
+use crate::sync_queue;
+use crate::tasks::{self, schema, ConflictStrategy};
+use crate::webdav;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
-use std::path::PathBuf;
-use tauri::Manager;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
 
 const DATA_FILE_NAME: &str = "data.json";
 const NEKOTICK_FOLDER: &str = ".nekotick";
 const STORE_FOLDER: &str = "store";
+/// Plaintext credentials written by older versions; migrated on load
 const GITHUB_CREDS_FILE: &str = "github_credentials.json";
+const GITHUB_CREDS_ENC_FILE: &str = "github_credentials.enc";
+const GITHUB_SYNC_META_FILE: &str = "github_sync_meta.json";
+/// Last successfully synced data.json, the base for three-way merges
+const SYNC_BASE_FILE: &str = "sync_base.json";
+const NOTES_FILE_NAME: &str = "nekotick.md";
+/// Last successfully synced nekotick.md
+const SYNC_BASE_NOTES_FILE: &str = "sync_base.md";
 
 /// GitHub OAuth config
 #[derive(Debug, Clone, Deserialize)]
@@ -53,6 +78,13 @@ pub struct GitHubSyncStatus {
     pub last_sync_time: Option<i64>,
     pub has_remote_data: bool,
     pub remote_modified_time: Option<String>,
+    /// Active sync profile; `None` is the default profile
+    pub profile: Option<String>,
+    /// False when the most recent sync attempt failed
+    pub last_sync_success: bool,
+    pub last_sync_error: Option<String>,
+    /// `SyncError` code of the most recent failure
+    pub last_sync_error_code: Option<String>,
 }
 
 /// GitHub auth result returned to frontend
@@ -70,6 +102,9 @@ pub struct GitHubAuthResult {
 pub struct GitHubSyncResult {
     pub success: bool,
     pub timestamp: Option<i64>,
+    /// Nothing was uploaded because the data matches the last upload
+    #[serde(default)]
+    pub unchanged: bool,
     pub error: Option<String>,
 }
 
@@ -81,6 +116,15 @@ pub struct GitHubBidirectionalSyncResult {
     pub timestamp: Option<i64>,
     pub pulled_from_cloud: bool,
     pub pushed_to_cloud: bool,
+    /// Task text edited on both devices; the local version was kept
+    pub conflicts: Vec<tasks::MergeConflict>,
+    /// nekotick.md was edited on both devices; the local version was kept
+    /// and the remote one saved as a nekotick.md backup
+    #[serde(default)]
+    pub notes_conflict: bool,
+    /// The gist changed on another device mid-sync; nothing was pushed
+    #[serde(default)]
+    pub remote_changed: bool,
     pub error: Option<String>,
 }
 
@@ -101,6 +145,25 @@ pub struct GitHubRemoteDataInfo {
     pub gist_id: Option<String>,
 }
 
+/// Remote change poll result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteChangeStatus {
+    pub remote_newer: bool,
+    pub remote_modified_time: Option<String>,
+}
+
+/// Per-provider outcome of `disconnect_all`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderDisconnectReport {
+    pub provider: String,
+    pub was_connected: bool,
+    pub local_cleared: bool,
+    pub remote_purged: bool,
+    pub error: Option<String>,
+}
+
 /// Stored GitHub credentials
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct GitHubCredentials {
@@ -111,20 +174,138 @@ struct GitHubCredentials {
     #[serde(default)]
     avatar_url: Option<String>,
     gist_id: Option<String>,
+    /// Cached primary verified email for commit attribution
+    #[serde(default)]
+    email: Option<String>,
+    /// Only issued to GitHub Apps with expiring user tokens
+    #[serde(default)]
+    refresh_token: Option<String>,
+    /// Unix seconds; `None` means the token does not expire
+    #[serde(default)]
+    token_expires_at: Option<i64>,
+    #[serde(default)]
+    refresh_token_expires_at: Option<i64>,
+    /// Connected with a personal access token instead of OAuth; never refreshed
+    #[serde(default)]
+    token_based: bool,
+    /// Web base of the server that issued the token; `None` (older files) is github.com
+    #[serde(default)]
+    server: Option<String>,
+}
+
+/// Refresh this long before the access token actually expires
+const TOKEN_REFRESH_MARGIN_SECS: i64 = 5 * 60;
+
+impl GitHubCredentials {
+    /// Whether the token was issued by `server` and may be sent to it
+    fn issued_by(&self, server: &GitHubServer) -> bool {
+        self.server.as_deref().unwrap_or(server::DOTCOM_WEB_BASE) == server.web_base
+    }
+
+    /// Whether the access token should be rotated before use
+    fn needs_refresh(&self, now: i64) -> bool {
+        if self.token_based {
+            return false;
+        }
+        match (&self.refresh_token, self.token_expires_at) {
+            (Some(_), Some(expires_at)) => {
+                let refresh_usable = self.refresh_token_expires_at.is_none_or(|at| at > now);
+                refresh_usable && expires_at - now <= TOKEN_REFRESH_MARGIN_SECS
+            }
+            _ => false,
+        }
+    }
+
+    /// Store a token response, keeping the old refresh token if none was returned
+    fn apply_token_response(&mut self, tokens: &GitHubTokenResponse, now: i64) {
+        self.access_token = tokens.access_token.clone();
+        if tokens.refresh_token.is_some() {
+            self.refresh_token = tokens.refresh_token.clone();
+        }
+        self.token_expires_at = tokens.expires_in.map(|secs| now + secs);
+        if let Some(secs) = tokens.refresh_token_expires_in {
+            self.refresh_token_expires_at = Some(now + secs);
+        }
+    }
+}
+
+/// A gist's ETag and the `updated_at` it was returned with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CachedGistEtag {
+    gist_id: String,
+    etag: String,
+    updated_at: String,
 }
 
 /// GitHub sync metadata
+///
+/// Signed with the per-install metadata key; see `meta_signing`.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 struct GitHubSyncMeta {
     last_sync_time: Option<i64>,
+    /// Gist `updated_at` as of the last sync, to detect pushes from other devices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote_updated_at: Option<String>,
+    /// `upload_hash` of the last uploaded data.json and notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_uploaded_hash: Option<String>,
+    /// Sync profile selecting which gist is used; `None` is the default profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    /// Result of the most recent sync attempt, successful or not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_outcome: Option<LastSyncOutcome>,
+    /// Saved conflict strategy for bidirectional sync; `None` means `Merge`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    conflict_strategy: Option<ConflictStrategy>,
+    /// Whether the sync gist is public; `None` means secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gist_public: Option<bool>,
+    /// ETag of the last `check_github_remote_data` fetch, to poll with `If-None-Match`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gist_etag: Option<CachedGistEtag>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+impl GitHubSyncMeta {
+    /// Bytes covered by the signature (the metadata without its signature)
+    fn signing_payload(&self) -> Result<Vec<u8>, String> {
+        let unsigned = GitHubSyncMeta {
+            signature: None,
+            ..self.clone()
+        };
+        serde_json::to_vec(&unsigned).map_err(|e| e.to_string())
+    }
+
+    /// Parse and verify metadata, treating unsigned or foreign content as absent
+    fn from_signed_json(content: &str, key: &[u8]) -> Option<GitHubSyncMeta> {
+        let meta: GitHubSyncMeta = serde_json::from_str(content).ok()?;
+        let signature = meta.signature.as_deref()?;
+        let payload = meta.signing_payload().ok()?;
+        meta_signing::verify(key, &payload, signature).then_some(meta)
+    }
+
+    /// Serialize metadata with a fresh signature
+    fn to_signed_json(&self, key: &[u8]) -> Result<String, String> {
+        let signed = GitHubSyncMeta {
+            signature: Some(meta_signing::sign(key, &self.signing_payload()?)),
+            ..self.clone()
+        };
+        serde_json::to_string_pretty(&signed).map_err(|e| e.to_string())
+    }
 }
 
 /// Get the data directory path
 fn get_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    app.path()
-        .app_data_dir()
-        .map_err(|e| e.to_string())
+    paths::data_dir(app)
+}
+
+/// Get the local store directory (.nekotick/store)
+fn get_store_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(get_data_dir(app)?.join(NEKOTICK_FOLDER).join(STORE_FOLDER))
 }
 
 /// Get GitHub credentials file path
@@ -132,7 +313,7 @@ fn get_github_creds_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     let mut path = get_data_dir(app)?;
     path.push(NEKOTICK_FOLDER);
     path.push(STORE_FOLDER);
-    path.push(GITHUB_CREDS_FILE);
+    path.push(GITHUB_CREDS_ENC_FILE);
     Ok(path)
 }
 
@@ -141,66 +322,146 @@ fn get_github_sync_meta_path(app: &tauri::AppHandle) -> Result<PathBuf, String>
     let mut path = get_data_dir(app)?;
     path.push(NEKOTICK_FOLDER);
     path.push(STORE_FOLDER);
-    path.push("github_sync_meta.json");
+    path.push(GITHUB_SYNC_META_FILE);
     Ok(path)
 }
 
 /// Load GitHub credentials
 fn load_github_credentials(app: &tauri::AppHandle) -> Option<GitHubCredentials> {
     let path = get_github_creds_path(app).ok()?;
-    let content = fs::read_to_string(&path).ok()?;
-    serde_json::from_str(&content).ok()
+    let store_dir = get_store_dir(app).ok()?;
+
+    if let Err(e) = credential_store::migrate_plaintext(&store_dir, &store_dir.join(GITHUB_CREDS_FILE), &path) {
+        log::warn!("Failed to encrypt stored GitHub credentials: {}", e);
+    }
+
+    let creds: GitHubCredentials = match credential_store::read_encrypted(&store_dir, &path) {
+        Ok(content) => serde_json::from_slice(&content?).ok()?,
+        Err(e) => {
+            log::warn!("Failed to read GitHub credentials: {}", e);
+            return None;
+        }
+    };
+
+    // Never send a token to a server other than the one that issued it
+    if !creds.issued_by(&server::current()) {
+        log::warn!("Stored GitHub credentials belong to another server; reconnect to continue");
+        return None;
+    }
+    Some(creds)
 }
 
 /// Save GitHub credentials
 fn save_github_credentials(app: &tauri::AppHandle, creds: &GitHubCredentials) -> Result<(), String> {
     let path = get_github_creds_path(app)?;
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    let content = serde_json::to_string_pretty(creds).map_err(|e| e.to_string())?;
-    fs::write(&path, content).map_err(|e| e.to_string())
+    let content = serde_json::to_vec(creds).map_err(|e| e.to_string())?;
+    credential_store::write_encrypted(&get_store_dir(app)?, &path, &content)
 }
 
 /// Delete GitHub credentials
 fn delete_github_credentials(app: &tauri::AppHandle) -> Result<(), String> {
-    let path = get_github_creds_path(app)?;
-    if path.exists() {
-        fs::remove_file(&path).map_err(|e| e.to_string())?;
+    let store_dir = get_store_dir(app)?;
+    for file_name in [GITHUB_CREDS_ENC_FILE, GITHUB_CREDS_FILE] {
+        let path = store_dir.join(file_name);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
     }
     Ok(())
 }
 
+/// Rotate the access token using the stored refresh token and persist it
+async fn refresh_github_credentials(app: &tauri::AppHandle, creds: &mut GitHubCredentials) -> Result<(), String> {
+    if creds.token_based {
+        return Err("Personal access tokens cannot be refreshed; connect with a new token".to_string());
+    }
+
+    let refresh_token = creds
+        .refresh_token
+        .clone()
+        .ok_or("No refresh token stored; reconnect GitHub")?;
+
+    let oauth_config = load_oauth_config()?;
+    let oauth_client = GitHubOAuthClient::new(oauth_config.client_id, oauth_config.client_secret);
+    let tokens = oauth_client
+        .refresh_token(&refresh_token)
+        .await
+        .map_err(redact_error)?;
+
+    creds.apply_token_response(&tokens, chrono::Utc::now().timestamp());
+    save_github_credentials(app, creds)
+}
+
+/// Load GitHub credentials, refreshing the access token first if it is about to expire
+///
+/// A failed refresh falls back to the stored token so the caller surfaces
+/// GitHub's own auth error rather than a confusing "not connected".
+async fn load_valid_github_credentials(app: &tauri::AppHandle) -> Option<GitHubCredentials> {
+    let mut creds = load_github_credentials(app)?;
+    if creds.needs_refresh(chrono::Utc::now().timestamp()) {
+        if let Err(e) = refresh_github_credentials(app, &mut creds).await {
+            log::warn!("GitHub token refresh failed: {}", e);
+        }
+    }
+    Some(creds)
+}
+
 /// Get stored GitHub access token (public for use by other modules)
 pub fn get_stored_github_token(app: &tauri::AppHandle) -> Option<String> {
     load_github_credentials(app).map(|c| c.access_token)
 }
 
+/// Get a GitHub access token, refreshing it first if it is about to expire
+pub async fn get_valid_github_token(app: &tauri::AppHandle) -> Option<String> {
+    load_valid_github_credentials(app).await.map(|c| c.access_token)
+}
+
 /// Get stored GitHub username (public for use by other modules)
 pub fn get_stored_github_username(app: &tauri::AppHandle) -> Option<String> {
     load_github_credentials(app).map(|c| c.username)
 }
 
 /// Load GitHub sync metadata
+///
+/// Reads a pending, not yet flushed write first so callers never see stale data.
+/// Metadata whose signature doesn't verify is ignored, forcing a full compare.
 fn load_github_sync_meta(app: &tauri::AppHandle) -> GitHubSyncMeta {
-    if let Ok(path) = get_github_sync_meta_path(app) {
-        if let Ok(content) = fs::read_to_string(&path) {
-            if let Ok(meta) = serde_json::from_str(&content) {
-                return meta;
-            }
-        }
+    let (Ok(path), Ok(store_dir)) = (get_github_sync_meta_path(app), get_store_dir(app)) else {
+        return GitHubSyncMeta::default();
+    };
+
+    let content = CoalescedWriter::global()
+        .pending(&path)
+        .map(Ok)
+        .unwrap_or_else(|| fs::read_to_string(&path));
+
+    // Read-only: with no key yet, nothing has been signed
+    match (content, meta_signing::load_key(&store_dir)) {
+        (Ok(content), Some(key)) => GitHubSyncMeta::from_signed_json(&content, &key).unwrap_or_default(),
+        _ => GitHubSyncMeta::default(),
     }
-    GitHubSyncMeta::default()
+}
+
+/// Whether stored sync metadata verifies, or `None` when there is none
+fn github_sync_meta_verifies(app: &tauri::AppHandle) -> Option<bool> {
+    let path = get_github_sync_meta_path(app).ok()?;
+    let content = CoalescedWriter::global()
+        .pending(&path)
+        .or_else(|| fs::read_to_string(&path).ok())?;
+    let Some(key) = meta_signing::load_key(&get_store_dir(app).ok()?) else {
+        return Some(false);
+    };
+    Some(GitHubSyncMeta::from_signed_json(&content, &key).is_some())
 }
 
 /// Save GitHub sync metadata
+///
+/// Writes are coalesced; the file is flushed after a short debounce or on app exit.
 fn save_github_sync_meta(app: &tauri::AppHandle, meta: &GitHubSyncMeta) -> Result<(), String> {
     let path = get_github_sync_meta_path(app)?;
-    if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    let content = serde_json::to_string_pretty(meta).map_err(|e| e.to_string())?;
-    fs::write(&path, content).map_err(|e| e.to_string())
+    let key = meta_signing::load_or_create_key(&get_store_dir(app)?)?;
+    let content = meta.to_signed_json(&key)?;
+    CoalescedWriter::global().write(&path, content)
 }
 
 /// Start GitHub OAuth2 authorization flow
@@ -297,7 +558,7 @@ pub async fn github_auth(app: tauri::AppHandle) -> Result<GitHubAuthResult, Stri
             return Ok(GitHubAuthResult {
                 success: false,
                 username: None,
-                error: Some(e.to_string()),
+                error: Some(redact_error(e)),
             });
         }
     };
@@ -310,22 +571,30 @@ pub async fn github_auth(app: tauri::AppHandle) -> Result<GitHubAuthResult, Stri
             return Ok(GitHubAuthResult {
                 success: false,
                 username: None,
-                error: Some(format!("Failed to get user info: {}", e)),
+                error: Some(redact(&format!("Failed to get user info: {}", e))),
             });
         }
     };
 
     // Check for existing gist
-    let existing_gist = gist_client.find_nekotick_gist().await.ok().flatten();
+    let profile = load_github_sync_meta(&app).profile;
+    let existing_gist = gist_client.find_nekotick_gist(profile.as_deref()).await.ok().flatten();
 
     // Store credentials
-    let creds = GitHubCredentials {
-        access_token: tokens.access_token.clone(),
+    let mut creds = GitHubCredentials {
+        access_token: String::new(),
         username: user_info.login.clone(),
         github_id: Some(user_info.id),
         avatar_url: user_info.avatar_url.clone(),
         gist_id: existing_gist.map(|g| g.id),
+        email: None,
+        refresh_token: None,
+        token_expires_at: None,
+        refresh_token_expires_at: None,
+        token_based: false,
+        server: Some(server::current().web_base),
     };
+    creds.apply_token_response(&tokens, chrono::Utc::now().timestamp());
 
     if let Err(e) = save_github_credentials(&app, &creds) {
         return Ok(GitHubAuthResult {
@@ -335,18 +604,91 @@ pub async fn github_auth(app: tauri::AppHandle) -> Result<GitHubAuthResult, Stri
         });
     }
 
-    // Register user with cloud API (fire and forget, don't block login)
-    let access_token_for_register = tokens.access_token.clone();
-    tokio::spawn(async move {
-        let client = reqwest::Client::new();
-        let _ = client
-            .post("https://api.nekotick.com/auth/register")
-            .json(&serde_json::json!({
-                "access_token": access_token_for_register
-            }))
-            .send()
-            .await;
-    });
+    // Register user with cloud API (fire and forget, don't block login).
+    // Enterprise tokens are corporate credentials and never leave their server.
+    if server::current().is_dotcom() {
+        let access_token_for_register = tokens.access_token.clone();
+        tokio::spawn(async move {
+            let client = crate::http::build_http_client();
+            let _ = client
+                .post("https://api.nekotick.com/auth/register")
+                .json(&serde_json::json!({
+                    "access_token": access_token_for_register
+                }))
+                .send()
+                .await;
+        });
+    }
+
+    Ok(GitHubAuthResult {
+        success: true,
+        username: Some(user_info.login),
+        error: None,
+    })
+}
+
+/// Connect GitHub with a fine-grained personal access token
+///
+/// For accounts that can't use the OAuth App flow. The token must be able to
+/// read the user and list gists; it is stored like an OAuth token but is
+/// never refreshed.
+#[tauri::command]
+pub async fn github_auth_with_token(app: tauri::AppHandle, token: String) -> Result<GitHubAuthResult, String> {
+    let token = token.trim().to_string();
+    if token.is_empty() {
+        return Ok(GitHubAuthResult {
+            success: false,
+            username: None,
+            error: Some("Enter a personal access token".to_string()),
+        });
+    }
+
+    let gist_client = GistClient::new(token.clone());
+    let user_info = match gist_client.get_user_info().await {
+        Ok(u) => u,
+        Err(e) => {
+            return Ok(GitHubAuthResult {
+                success: false,
+                username: None,
+                error: Some(redact(&format!("GitHub rejected the token: {}", e))),
+            });
+        }
+    };
+
+    // Looking for the sync gist lists gists, which fails without gist access
+    let profile = load_github_sync_meta(&app).profile;
+    let existing_gist = match gist_client.find_nekotick_gist(profile.as_deref()).await {
+        Ok(gist) => gist,
+        Err(e) => {
+            return Ok(GitHubAuthResult {
+                success: false,
+                username: None,
+                error: Some(redact(&format!("Token cannot access gists; grant it the Gists permission: {}", e))),
+            });
+        }
+    };
+
+    let creds = GitHubCredentials {
+        access_token: token,
+        username: user_info.login.clone(),
+        github_id: Some(user_info.id),
+        avatar_url: user_info.avatar_url.clone(),
+        gist_id: existing_gist.map(|g| g.id),
+        email: None,
+        refresh_token: None,
+        token_expires_at: None,
+        refresh_token_expires_at: None,
+        token_based: true,
+        server: Some(server::current().web_base),
+    };
+
+    if let Err(e) = save_github_credentials(&app, &creds) {
+        return Ok(GitHubAuthResult {
+            success: false,
+            username: None,
+            error: Some(e),
+        });
+    }
 
     Ok(GitHubAuthResult {
         success: true,
@@ -355,16 +697,181 @@ pub async fn github_auth(app: tauri::AppHandle) -> Result<GitHubAuthResult, Stri
     })
 }
 
+/// Rotate the GitHub access token using the stored refresh token
+///
+/// Returns `false` without contacting GitHub when the connection uses a
+/// non-expiring OAuth App token, which has nothing to rotate.
+#[tauri::command]
+pub async fn refresh_github_token(app: tauri::AppHandle) -> Result<bool, String> {
+    let mut creds = load_github_credentials(&app)
+        .ok_or("Not connected to GitHub")?;
+
+    if creds.token_based || creds.refresh_token.is_none() {
+        return Ok(false);
+    }
+
+    refresh_github_credentials(&app, &mut creds).await?;
+    Ok(true)
+}
+
+/// GitHub's noreply address, which always attributes commits to the account
+fn github_noreply_email(username: &str, github_id: Option<u64>) -> String {
+    match github_id {
+        Some(id) => format!("{}+{}@users.noreply.github.com", id, username),
+        None => format!("{}@users.noreply.github.com", username),
+    }
+}
+
+/// Get the user's primary verified GitHub email for commit attribution
+///
+/// The result of `/user/emails` is cached in credentials. Tokens without the
+/// `user:email` scope, or accounts without a verified primary, fall back to
+/// the noreply address; the fallback is not cached so a later re-auth with
+/// the scope picks up the real email.
+#[tauri::command]
+pub async fn get_github_primary_email(app: tauri::AppHandle) -> Result<String, String> {
+    let mut creds = load_valid_github_credentials(&app).await
+        .ok_or("Not connected to GitHub")?;
+
+    if let Some(email) = &creds.email {
+        return Ok(email.clone());
+    }
+
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let primary = match gist_client.list_user_emails().await {
+        Ok(emails) => select_primary_email(&emails),
+        Err(e) => {
+            log::info!("Could not fetch GitHub emails, using noreply address: {}", e);
+            None
+        }
+    };
+
+    match primary {
+        Some(email) => {
+            creds.email = Some(email.clone());
+            save_github_credentials(&app, &creds)?;
+            Ok(email)
+        }
+        None => Ok(github_noreply_email(&creds.username, creds.github_id)),
+    }
+}
+
+/// Remaining GitHub API quota, so the UI can warn before bulk operations
+#[tauri::command]
+pub async fn get_github_rate_limit(app: tauri::AppHandle) -> Result<GitHubRateLimit, String> {
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or("Not connected to GitHub")?;
+
+    GistClient::new(creds.access_token.clone())
+        .get_rate_limit()
+        .await
+        .map_err(redact_error)
+}
+
 /// Disconnect from GitHub
 #[tauri::command]
 pub async fn github_disconnect(app: tauri::AppHandle) -> Result<(), String> {
     delete_github_credentials(&app)
 }
 
+/// Remove local GitHub credentials and sync metadata from a store directory
+pub(crate) fn clear_github_local_state(store_dir: &Path) -> Result<(), String> {
+    for file_name in [GITHUB_CREDS_ENC_FILE, GITHUB_CREDS_FILE, GITHUB_SYNC_META_FILE] {
+        let path = store_dir.join(file_name);
+        CoalescedWriter::global().discard(&path);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", file_name, e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Forget the GitHub account, its sync metadata and merge base
+///
+/// Used when the GitHub server changes: tokens only work on the server that
+/// issued them, and the merge base belongs to the old server's gist.
+pub(crate) fn clear_github_server_state(data_dir: &Path) -> Result<(), String> {
+    let nekotick_dir = data_dir.join(NEKOTICK_FOLDER);
+    clear_github_local_state(&nekotick_dir.join(STORE_FOLDER))?;
+    for name in [SYNC_BASE_FILE, SYNC_BASE_NOTES_FILE] {
+        let path = nekotick_dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of deleting the remote gist; one that is already gone counts as purged
+fn remote_purge_outcome(result: Result<(), GistApiError>) -> Result<(), String> {
+    match result {
+        Ok(()) | Err(GistApiError::NotFound(_)) => Ok(()),
+        Err(e) => Err(format!("Failed to delete remote gist: {}", e)),
+    }
+}
+
+/// Disconnect GitHub, optionally deleting the remote gist first
+async fn disconnect_github_provider(app: &tauri::AppHandle, purge_remote: bool) -> ProviderDisconnectReport {
+    let mut report = ProviderDisconnectReport {
+        provider: "github".to_string(),
+        was_connected: false,
+        local_cleared: false,
+        remote_purged: false,
+        error: None,
+    };
+
+    let creds = load_valid_github_credentials(app).await;
+    report.was_connected = creds.is_some();
+
+    // Purge remote data first; keep local credentials on failure so the user can retry
+    if purge_remote {
+        if let Some(GitHubCredentials { access_token, gist_id: Some(gist_id), .. }) = &creds {
+            let gist_client = GistClient::new(access_token.clone());
+            if let Err(e) = remote_purge_outcome(gist_client.delete_gist(gist_id).await) {
+                report.error = Some(e);
+                return report;
+            }
+            report.remote_purged = true;
+        }
+    }
+
+    let nekotick_dir = match get_data_dir(app) {
+        Ok(dir) => dir.join(NEKOTICK_FOLDER),
+        Err(e) => {
+            report.error = Some(e);
+            return report;
+        }
+    };
+
+    let cleared = clear_github_local_state(&nekotick_dir.join(STORE_FOLDER))
+        .and_then(|()| sync_queue::forget_backend(&nekotick_dir, "github"));
+    match cleared {
+        Ok(()) => report.local_cleared = true,
+        Err(e) => report.error = Some(e),
+    }
+
+    report
+}
+
+/// Disconnect every sync provider and clear their local credentials
+///
+/// When `purge_remote` is set, remote sync data is deleted as well.
+#[tauri::command]
+pub async fn disconnect_all(app: tauri::AppHandle, purge_remote: bool) -> Result<Vec<ProviderDisconnectReport>, String> {
+    Ok(vec![
+        disconnect_github_provider(&app, purge_remote).await,
+        webdav::commands::disconnect_webdav_provider(&app, purge_remote).await,
+    ])
+}
+
 /// Get current GitHub sync status
 #[tauri::command]
 pub async fn get_github_sync_status(app: tauri::AppHandle) -> Result<GitHubSyncStatus, String> {
     let sync_meta = load_github_sync_meta(&app);
+    let last_outcome = sync_meta.last_outcome.clone();
+    let last_sync_success = last_outcome.as_ref().is_none_or(|o| o.success);
+    let last_sync_error = last_outcome.as_ref().and_then(|o| o.error.clone());
+    let last_sync_error_code = last_outcome.and_then(|o| o.error_code);
     
     match load_github_credentials(&app) {
         Some(creds) => {
@@ -377,6 +884,10 @@ pub async fn get_github_sync_status(app: tauri::AppHandle) -> Result<GitHubSyncS
                 last_sync_time: sync_meta.last_sync_time,
                 has_remote_data: has_remote,
                 remote_modified_time: None,
+                profile: sync_meta.profile,
+                last_sync_success,
+                last_sync_error,
+                last_sync_error_code,
             })
         }
         None => Ok(GitHubSyncStatus {
@@ -387,22 +898,92 @@ pub async fn get_github_sync_status(app: tauri::AppHandle) -> Result<GitHubSyncS
             last_sync_time: None,
             has_remote_data: false,
             remote_modified_time: None,
+            profile: sync_meta.profile,
+            last_sync_success,
+            last_sync_error,
+            last_sync_error_code,
         }),
     }
 }
 
+/// Probe the GitHub backend for `run_sync_diagnostics`, or `None` when not connected
+pub async fn github_diagnostics(app: &tauri::AppHandle) -> Option<BackendDiagnostics> {
+    let store_dir = get_store_dir(app).ok()?;
+    if ![GITHUB_CREDS_ENC_FILE, GITHUB_CREDS_FILE].iter().any(|f| store_dir.join(f).exists()) {
+        return None;
+    }
+
+    let mut report = BackendDiagnostics {
+        backend: "github".to_string(),
+        credentials_present: true,
+        metadata_valid: github_sync_meta_verifies(app),
+        ..Default::default()
+    };
+
+    let Some(creds) = load_github_credentials(app) else {
+        report.errors.push("Stored credentials could not be read on this device".to_string());
+        return Some(report);
+    };
+    report.credentials_valid = true;
+    report.token_expired = creds.token_expires_at.map(|at| at <= chrono::Utc::now().timestamp());
+
+    let secrets = [creds.access_token.as_str(), creds.refresh_token.as_deref().unwrap_or_default()];
+    let gist_client = GistClient::new(creds.access_token.clone());
+
+    if let Err(e) = gist_client.get_user_info().await {
+        report.errors.push(diagnostics::redact(&format!("GitHub API: {}", e), &secrets));
+        return Some(report);
+    }
+    report.api_reachable = true;
+
+    let remote = match &creds.gist_id {
+        Some(gist_id) => gist_client.get_gist(gist_id).await.map(Some),
+        None => {
+            let profile = load_github_sync_meta(app).profile;
+            gist_client.find_nekotick_gist(profile.as_deref()).await
+        }
+    };
+    match remote {
+        Ok(gist) => report.remote_data_exists = gist.is_some(),
+        Err(e) => report.errors.push(diagnostics::redact(&format!("Sync gist: {}", e), &secrets)),
+    }
+
+    Some(report)
+}
+
 /// Check if remote data exists on GitHub
+///
+/// The stored gist is fetched conditionally with the ETag of the previous
+/// check, so polling an unchanged gist costs no download.
 #[tauri::command]
 pub async fn check_github_remote_data(app: tauri::AppHandle) -> Result<GitHubRemoteDataInfo, String> {
-    let creds = load_github_credentials(&app)
+    let creds = load_valid_github_credentials(&app).await
         .ok_or("Not connected to GitHub")?;
 
     let gist_client = GistClient::new(creds.access_token);
+    let mut meta = load_github_sync_meta(&app);
 
     // If we have a stored gist_id, check if it still exists
     if let Some(gist_id) = &creds.gist_id {
-        match gist_client.get_gist(gist_id).await {
-            Ok(gist) => {
+        let cached = meta.gist_etag.clone().filter(|cached| &cached.gist_id == gist_id);
+        match gist_client.get_gist_if_changed(gist_id, cached.as_ref().map(|c| c.etag.as_str())).await {
+            Ok(GistFetch::NotModified) => {
+                return Ok(GitHubRemoteDataInfo {
+                    exists: true,
+                    modified_time: cached.map(|c| c.updated_at),
+                    gist_id: Some(gist_id.clone()),
+                });
+            }
+            Ok(GistFetch::Fetched { gist, etag }) => {
+                let fresh = etag.map(|etag| CachedGistEtag {
+                    gist_id: gist.id.clone(),
+                    etag,
+                    updated_at: gist.updated_at.clone(),
+                });
+                if fresh != meta.gist_etag {
+                    meta.gist_etag = fresh;
+                    save_github_sync_meta(&app, &meta)?;
+                }
                 return Ok(GitHubRemoteDataInfo {
                     exists: true,
                     modified_time: Some(gist.updated_at),
@@ -416,7 +997,8 @@ pub async fn check_github_remote_data(app: tauri::AppHandle) -> Result<GitHubRem
     }
 
     // Try to find existing gist
-    match gist_client.find_nekotick_gist().await {
+    let profile = meta.profile;
+    match gist_client.find_nekotick_gist(profile.as_deref()).await {
         Ok(Some(gist)) => Ok(GitHubRemoteDataInfo {
             exists: true,
             modified_time: Some(gist.updated_at),
@@ -431,114 +1013,768 @@ pub async fn check_github_remote_data(app: tauri::AppHandle) -> Result<GitHubRem
     }
 }
 
-/// Sync local data to GitHub Gist
+/// Switch to another sync profile (`None` or blank for the default profile)
+///
+/// Each profile syncs with its own gist. The profile's existing gist, if
+/// any, becomes the sync target; otherwise the next upload creates one.
+/// Merge state from the previous profile is dropped so it can't leak into
+/// the new gist.
 #[tauri::command]
-pub async fn sync_to_github(app: tauri::AppHandle) -> Result<GitHubSyncResult, String> {
-    let mut creds = load_github_credentials(&app)
+pub async fn set_github_sync_profile(
+    app: tauri::AppHandle,
+    profile: Option<String>,
+) -> Result<GitHubRemoteDataInfo, String> {
+    let mut creds = load_valid_github_credentials(&app).await
         .ok_or("Not connected to GitHub")?;
+    let profile = profile
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty());
 
-    let base_path = get_data_dir(&app)?;
-    let data_json_path = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER).join(DATA_FILE_NAME);
-
-    if !data_json_path.exists() {
-        return Ok(GitHubSyncResult {
-            success: false,
-            timestamp: None,
-            error: Some("No local data to sync".to_string()),
-        });
-    }
-
-    let content = fs::read_to_string(&data_json_path)
-        .map_err(|e| format!("Failed to read data.json: {}", e))?;
-
-    let gist_client = GistClient::new(creds.access_token.clone());
-    
-    // Upload to gist (create or update)
-    let gist = gist_client
-        .upload_data(creds.gist_id.as_deref(), &content)
+    let gist = GistClient::new(creds.access_token.clone())
+        .find_nekotick_gist(profile.as_deref())
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(redact_error)?;
 
-    // Update stored gist_id if it was newly created
-    if creds.gist_id.is_none() {
-        creds.gist_id = Some(gist.id);
-        save_github_credentials(&app, &creds)?;
-    }
+    creds.gist_id = gist.as_ref().map(|g| g.id.clone());
+    save_github_credentials(&app, &creds)?;
 
-    // Update sync metadata
-    let now = chrono::Utc::now().timestamp();
-    let meta = GitHubSyncMeta {
-        last_sync_time: Some(now),
-    };
+    let mut meta = load_github_sync_meta(&app);
+    meta.profile = profile;
+    meta.remote_updated_at = None;
+    meta.last_uploaded_hash = None;
     save_github_sync_meta(&app, &meta)?;
 
-    Ok(GitHubSyncResult {
-        success: true,
-        timestamp: Some(now),
-        error: None,
+    let nekotick_dir = get_data_dir(&app)?.join(NEKOTICK_FOLDER);
+    for name in [SYNC_BASE_FILE, SYNC_BASE_NOTES_FILE] {
+        let path = nekotick_dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(GitHubRemoteDataInfo {
+        exists: gist.is_some(),
+        modified_time: gist.as_ref().map(|g| g.updated_at.clone()),
+        gist_id: gist.map(|g| g.id),
     })
 }
 
-/// Restore data from GitHub Gist
-#[tauri::command]
-pub async fn restore_from_github(app: tauri::AppHandle) -> Result<GitHubSyncResult, String> {
-    let creds = load_github_credentials(&app)
-        .ok_or("Not connected to GitHub")?;
+/// Whether the remote copy changed after our last sync
+fn is_remote_newer(remote_modified: Option<i64>, last_sync_time: Option<i64>) -> bool {
+    match (remote_modified, last_sync_time) {
+        (Some(remote), Some(last_sync)) => remote > last_sync,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// Poll whether remote data changed since the last sync
+///
+/// Only compares the remote modification time against the stored
+/// `last_sync_time`; never downloads content or writes local state.
+#[tauri::command]
+pub async fn poll_remote_changes(app: tauri::AppHandle, provider: String) -> Result<RemoteChangeStatus, String> {
+    if provider != "github" {
+        return Err(format!("Unsupported sync provider: {}", provider));
+    }
+
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or("Not connected to GitHub")?;
+
+    let gist_id = match creds.gist_id {
+        Some(id) => id,
+        None => {
+            return Ok(RemoteChangeStatus {
+                remote_newer: false,
+                remote_modified_time: None,
+            });
+        }
+    };
+
+    let gist_client = GistClient::new(creds.access_token);
+    let remote_modified = gist_client
+        .get_gist_modified_time(&gist_id)
+        .await
+        .map_err(redact_error)?;
+
+    let sync_meta = load_github_sync_meta(&app);
+
+    Ok(RemoteChangeStatus {
+        remote_newer: is_remote_newer(remote_modified, sync_meta.last_sync_time),
+        remote_modified_time: remote_modified
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.to_rfc3339()),
+    })
+}
+
+/// Read nekotick.md if it exists
+fn read_local_notes(notes_path: &Path) -> Result<Option<String>, String> {
+    if !notes_path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(notes_path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read {}: {}", NOTES_FILE_NAME, e))
+}
+
+/// Overwrite nekotick.md, keeping the previous version as a backup
+fn write_local_notes(notes_path: &Path, notes: &str) -> Result<(), String> {
+    if let Some(dir) = notes_path.parent() {
+        backup::rotate_backup(dir, NOTES_FILE_NAME, backup::DEFAULT_BACKUP_KEEP)?;
+    }
+    fs::write(notes_path, notes)
+        .map_err(|e| format!("Failed to write {}: {}", NOTES_FILE_NAME, e))
+}
+
+/// What a bidirectional sync should do with nekotick.md
+#[derive(Debug, Default, PartialEq)]
+struct NotesSync {
+    /// Remote notes to write locally
+    write_local: Option<String>,
+    /// Local notes to upload
+    upload: Option<String>,
+    /// Remote notes the upload replaces although they changed since the last
+    /// sync; kept as a local backup
+    overwritten_remote: Option<String>,
+}
+
+/// Reconcile nekotick.md against the notes from the last sync
+///
+/// Markdown is not merged: the side that changed since `base` wins. When
+/// both changed, the local notes are uploaded and the remote ones are
+/// reported in `overwritten_remote` so they can be backed up.
+fn resolve_notes(local: Option<&str>, remote: Option<&str>, base: Option<&str>) -> NotesSync {
+    match (local, remote) {
+        (Some(local), Some(remote)) if local == remote => NotesSync::default(),
+        (Some(local), Some(remote)) if Some(local) == base => NotesSync {
+            write_local: Some(remote.to_string()),
+            ..Default::default()
+        },
+        (Some(local), remote) => NotesSync {
+            upload: Some(local.to_string()),
+            overwritten_remote: remote.filter(|&remote| Some(remote) != base).map(str::to_string),
+            ..Default::default()
+        },
+        (None, Some(remote)) => NotesSync {
+            write_local: Some(remote.to_string()),
+            ..Default::default()
+        },
+        (None, None) => NotesSync::default(),
+    }
+}
+
+/// SHA-256 (hex) of an upload's data.json and nekotick.md, and how it was sealed
+///
+/// `marker` is the `sync_crypto::upload_marker`, so setting or clearing the
+/// sync passphrase makes unchanged data count as not yet uploaded.
+fn upload_hash(content: &str, notes: Option<&str>, marker: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    if let Some(notes) = notes {
+        // Separator keeps ("ab", "c") and ("a", "bc") apart
+        hasher.update([0u8]);
+        hasher.update(notes.as_bytes());
+    }
+    if let Some(marker) = marker {
+        hasher.update([1u8]);
+        hasher.update(marker.as_bytes());
+    }
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// In-band failure reported by a sync or restore result
+fn sync_result_failure(result: &GitHubSyncResult) -> Option<SyncError> {
+    (!result.success).then(|| SyncError::Other(result.error.clone().unwrap_or_default()))
+}
+
+/// In-band failure reported by a bidirectional sync result
+fn bidirectional_result_failure(result: &GitHubBidirectionalSyncResult) -> Option<SyncError> {
+    let message = result.error.clone().unwrap_or_default();
+    match (result.success, result.remote_changed) {
+        (true, _) => None,
+        (false, true) => Some(SyncError::Conflict(message)),
+        (false, false) => Some(SyncError::Other(message)),
+    }
+}
+
+/// Record the outcome of a sync attempt in the sync metadata
+fn record_github_outcome<T>(
+    app: &tauri::AppHandle,
+    result: &Result<T, SyncError>,
+    reported_failure: impl FnOnce(&T) -> Option<SyncError>,
+) {
+    let Some(outcome) = LastSyncOutcome::from_result(result, reported_failure, chrono::Utc::now().timestamp()) else {
+        return;
+    };
+    let mut meta = load_github_sync_meta(app);
+    meta.last_outcome = Some(outcome);
+    if let Err(e) = save_github_sync_meta(app, &meta) {
+        log::warn!("Failed to record GitHub sync outcome: {}", e);
+    }
+}
+
+/// Sync local data to GitHub Gist
+///
+/// The upload is skipped when data.json and nekotick.md are unchanged since
+/// the last upload, unless `force` is set.
+#[tauri::command]
+pub async fn sync_to_github(
+    app: tauri::AppHandle,
+    locks: tauri::State<'_, SyncLocks>,
+    force: Option<bool>,
+) -> Result<GitHubSyncResult, SyncError> {
+    let _sync_guard = locks.try_acquire(SyncBackend::GitHub)?;
+
+    let result = run_sync_to_github(app.clone(), force).await;
+    record_github_outcome(&app, &result, sync_result_failure);
+    sync_queue::record_push_result(&app, "github", &result, |result| result.success);
+    result
+}
+
+async fn run_sync_to_github(
+    app: tauri::AppHandle,
+    force: Option<bool>,
+) -> Result<GitHubSyncResult, SyncError> {
+    let mut creds = load_valid_github_credentials(&app).await
+        .ok_or(SyncError::NotConnected("GitHub".to_string()))?;
+
+    let base_path = get_data_dir(&app)?;
+    let data_json_path = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER).join(DATA_FILE_NAME);
+
+    if !data_json_path.exists() {
+        return Ok(GitHubSyncResult {
+            success: false,
+            timestamp: None,
+            unchanged: false,
+            error: Some("No local data to sync".to_string()),
+        });
+    }
+
+    let content = fs::read_to_string(&data_json_path)
+        .map_err(|e| format!("Failed to read data.json: {}", e))?;
+    let notes = read_local_notes(&base_path.join(NOTES_FILE_NAME))?;
+
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let mut meta = load_github_sync_meta(&app);
+
+    // Nothing changed since the last upload
+    let store_dir = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+    let hash = upload_hash(&content, notes.as_deref(), sync_crypto::upload_marker(&store_dir).as_deref());
+    if !force.unwrap_or(false) && creds.gist_id.is_some() && meta.last_uploaded_hash.as_deref() == Some(hash.as_str()) {
+        return Ok(GitHubSyncResult {
+            success: true,
+            timestamp: meta.last_sync_time,
+            unchanged: true,
+            error: None,
+        });
+    }
+
+    // Don't overwrite edits another device pushed since our last sync
+    if let Some(gist_id) = &creds.gist_id {
+        let refusal = match &meta.remote_updated_at {
+            Some(expected) => match gist_client.ensure_unchanged(gist_id, expected).await {
+                Ok(()) => None,
+                Err(GistApiError::RemoteChanged { .. }) => {
+                    Some("Remote data changed on another device. Sync both ways to merge before uploading.")
+                }
+                Err(e) => return Err(e.into()),
+            },
+            // No recorded remote version (first sync here, reset or unverified
+            // metadata): only push over a gist that still holds the last synced data
+            None => {
+                let gist = gist_client.get_gist(gist_id).await?;
+                let remote = gist_client.download_gist_data(&gist).await?;
+                let remote = sync_crypto::open_download(&store_dir, remote)?;
+                let base = fs::read_to_string(base_path.join(NEKOTICK_FOLDER).join(SYNC_BASE_FILE)).ok();
+                (!same_json(base.as_deref(), &remote))
+                    .then_some("Remote data may hold changes from another device. Sync both ways to merge before uploading.")
+            }
+        };
+        if let Some(error) = refusal {
+            return Ok(GitHubSyncResult {
+                success: false,
+                timestamp: None,
+                unchanged: false,
+                error: Some(error.to_string()),
+            });
+        }
+    }
+
+    // Upload to gist (create or update), encrypted when a sync passphrase is set
+    let payload = sync_crypto::seal_for_upload(&store_dir, &content)?;
+    let gist = gist_client
+        .upload_data(
+            creds.gist_id.as_deref(),
+            &payload,
+            notes.as_deref(),
+            compression::should_compress(&payload),
+            meta.profile.as_deref(),
+            meta.gist_public.unwrap_or(false),
+        )
+        .await?;
+
+    // Update stored gist_id if it was newly created
+    if creds.gist_id.is_none() {
+        creds.gist_id = Some(gist.id.clone());
+        save_github_credentials(&app, &creds)?;
+    }
+
+    // Update sync metadata
+    let now = chrono::Utc::now().timestamp();
+    meta.last_sync_time = Some(now);
+    meta.remote_updated_at = Some(gist.updated_at);
+    // Sealing may have created the salt, which changes the marker
+    meta.last_uploaded_hash = Some(upload_hash(&content, notes.as_deref(), sync_crypto::payload_marker(&payload).as_deref()));
+    save_github_sync_meta(&app, &meta)?;
+    save_sync_base(&base_path, &content, notes.as_deref())?;
+
+    Ok(GitHubSyncResult {
+        success: true,
+        timestamp: Some(now),
+        unchanged: false,
+        error: None,
+    })
+}
+
+/// Restore data from GitHub Gist
+///
+/// Data written by a newer app version is refused unless `allow_newer_schema` is set.
+#[tauri::command]
+pub async fn restore_from_github(
+    app: tauri::AppHandle,
+    locks: tauri::State<'_, SyncLocks>,
+    allow_newer_schema: Option<bool>,
+) -> Result<GitHubSyncResult, SyncError> {
+    let _sync_guard = locks.try_acquire(SyncBackend::GitHub)?;
+
+    let result = run_restore_from_github(app.clone(), allow_newer_schema).await;
+    record_github_outcome(&app, &result, sync_result_failure);
+    result
+}
+
+async fn run_restore_from_github(
+    app: tauri::AppHandle,
+    allow_newer_schema: Option<bool>,
+) -> Result<GitHubSyncResult, SyncError> {
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or(SyncError::NotConnected("GitHub".to_string()))?;
+
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let gist = find_remote_gist(&app, &gist_client, &creds).await?;
+    restore_gist_files(&app, &gist_client, &gist, allow_newer_schema.unwrap_or(false), true).await
+}
+
+/// The sync gist to restore from, refusing one owned by another account
+///
+/// Without a stored gist, the active profile's gist is looked up.
+async fn find_remote_gist(
+    app: &tauri::AppHandle,
+    gist_client: &GistClient,
+    creds: &GitHubCredentials,
+) -> Result<Gist, SyncError> {
+    let gist = match &creds.gist_id {
+        Some(gist_id) => gist_client.get_gist(gist_id).await?,
+        None => {
+            let profile = load_github_sync_meta(app).profile;
+            gist_client
+                .find_nekotick_gist(profile.as_deref())
+                .await?
+                .ok_or("No remote gist found")?
+        }
+    };
+
+    gist.verify_owner(&creds.username)?;
+    Ok(gist)
+}
+
+/// Download and decrypt the gist's data.json for `preview_remote_data`
+pub async fn download_github_data(app: &tauri::AppHandle) -> Result<String, SyncError> {
+    let creds = load_valid_github_credentials(app).await
+        .ok_or(SyncError::NotConnected("GitHub".to_string()))?;
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let gist = find_remote_gist(app, &gist_client, &creds).await?;
+    let content = gist_client.download_gist_data(&gist).await?;
+    sync_crypto::open_download(&get_store_dir(app)?, content)
+}
+
+/// List the sync gist's revisions, newest first
+#[tauri::command]
+pub async fn get_github_revisions(app: tauri::AppHandle) -> Result<Vec<GistRevision>, String> {
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or("Not connected to GitHub")?;
 
     let gist_id = creds.gist_id.as_ref()
         .ok_or("No remote gist found")?;
 
-    let gist_client = GistClient::new(creds.access_token);
-    
+    GistClient::new(creds.access_token.clone())
+        .list_revisions(gist_id)
+        .await
+        .map_err(redact_error)
+}
+
+/// Roll local data back to a historical gist revision
+///
+/// The gist itself is untouched; the next sync pushes the restored data.
+/// Data written by a newer app version is refused unless `allow_newer_schema` is set.
+#[tauri::command]
+pub async fn restore_github_revision(
+    app: tauri::AppHandle,
+    locks: tauri::State<'_, SyncLocks>,
+    sha: String,
+    allow_newer_schema: Option<bool>,
+) -> Result<GitHubSyncResult, SyncError> {
+    let _sync_guard = locks.try_acquire(SyncBackend::GitHub)?;
+
+    let result = run_restore_github_revision(app.clone(), sha, allow_newer_schema).await;
+    record_github_outcome(&app, &result, sync_result_failure);
+    result
+}
+
+async fn run_restore_github_revision(
+    app: tauri::AppHandle,
+    sha: String,
+    allow_newer_schema: Option<bool>,
+) -> Result<GitHubSyncResult, SyncError> {
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or(SyncError::NotConnected("GitHub".to_string()))?;
+
+    let gist_id = creds.gist_id.as_ref()
+        .ok_or("No remote gist found")?;
+
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let revision = gist_client
+        .get_gist_revision(gist_id, &sha)
+        .await?;
+    revision.verify_owner(&creds.username)?;
+
+    restore_gist_files(&app, &gist_client, &revision, allow_newer_schema.unwrap_or(false), false).await
+}
+
+/// Write data.json (and nekotick.md, when present) from `gist` over the local copies
+///
+/// `is_head` marks the gist's current version, which then becomes the merge
+/// base; an older revision leaves the base alone.
+async fn restore_gist_files(
+    app: &tauri::AppHandle,
+    gist_client: &GistClient,
+    gist: &Gist,
+    allow_newer_schema: bool,
+    is_head: bool,
+) -> Result<GitHubSyncResult, SyncError> {
     // Download data from gist
     let content = gist_client
-        .download_data(gist_id)
-        .await
-        .map_err(|e| e.to_string())?;
+        .download_gist_data(gist)
+        .await?;
+    let content = sync_crypto::open_download(&get_store_dir(app)?, content)?;
+    let notes = gist_client
+        .download_gist_notes(gist)
+        .await?;
+
+    // Warn instead of silently restoring data this version may not understand
+    let compatibility = schema::check_schema_version(&content)?;
+    if compatibility.is_too_new() && !allow_newer_schema {
+        return Ok(GitHubSyncResult {
+            success: false,
+            timestamp: None,
+            unchanged: false,
+            error: Some("Remote data was written by a newer version of NekoTick. Update the app before restoring.".to_string()),
+        });
+    }
 
     // Ensure local directory exists
-    let base_path = get_data_dir(&app)?;
+    let base_path = get_data_dir(app)?;
     let store_dir = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
     fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
 
     let data_json_path = store_dir.join(DATA_FILE_NAME);
-    let backup_path = store_dir.join(format!("{}.backup", DATA_FILE_NAME));
 
     // Backup existing local data
-    if data_json_path.exists() {
-        fs::copy(&data_json_path, &backup_path)
-            .map_err(|e| format!("Failed to create backup: {}", e))?;
-    }
+    let backup_path = backup::rotate_backup(&store_dir, DATA_FILE_NAME, backup::DEFAULT_BACKUP_KEEP)?;
 
     // Write remote data to local
     if let Err(e) = fs::write(&data_json_path, &content) {
         // Restore from backup on failure
-        if backup_path.exists() {
-            let _ = fs::copy(&backup_path, &data_json_path);
+        if let Some(backup_path) = &backup_path {
+            let _ = fs::copy(backup_path, &data_json_path);
         }
-        return Err(format!("Failed to write data.json: {}", e));
+        return Err(format!("Failed to write data.json: {}", e).into());
+    }
+
+    // Older gists only hold data.json; leave local notes alone then
+    if let Some(notes) = &notes {
+        write_local_notes(&base_path.join(NOTES_FILE_NAME), notes)?;
     }
 
     // Update sync metadata
     let now = chrono::Utc::now().timestamp();
-    let meta = GitHubSyncMeta {
-        last_sync_time: Some(now),
-    };
-    save_github_sync_meta(&app, &meta)?;
+    let mut meta = load_github_sync_meta(app);
+    meta.last_sync_time = Some(now);
+    meta.remote_updated_at = Some(gist.updated_at.clone());
+    meta.last_uploaded_hash = None;
+    save_github_sync_meta(app, &meta)?;
+    if is_head {
+        save_sync_base(&base_path, &content, notes.as_deref())?;
+    }
 
     Ok(GitHubSyncResult {
         success: true,
         timestamp: Some(now),
+        unchanged: false,
         error: None,
     })
 }
 
-/// Bidirectional sync with GitHub
+/// Diff a historical data.json against the local data file
+///
+/// A missing local file is treated as an empty task list.
+fn diff_local_against_revision(revision_content: &str, data_json_path: &Path) -> Result<tasks::TaskDiff, String> {
+    let local_content = if data_json_path.exists() {
+        fs::read_to_string(data_json_path).map_err(|e| e.to_string())?
+    } else {
+        "{}".to_string()
+    };
+    tasks::diff_task_json(revision_content, &local_content).map_err(|e| e.to_string())
+}
+
+/// Show what changed locally since a historical gist revision
+///
+/// Downloads data.json at `sha` for comparison only; nothing is written.
 #[tauri::command]
-pub async fn sync_github_bidirectional(app: tauri::AppHandle) -> Result<GitHubBidirectionalSyncResult, String> {
-    let mut creds = load_github_credentials(&app)
+pub async fn diff_against_github_revision(app: tauri::AppHandle, sha: String) -> Result<tasks::TaskDiff, String> {
+    let creds = load_valid_github_credentials(&app).await
         .ok_or("Not connected to GitHub")?;
 
+    let gist_id = creds.gist_id.as_ref()
+        .ok_or("No remote gist found")?;
+
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let revision = gist_client
+        .get_gist_revision(gist_id, &sha)
+        .await
+        .map_err(redact_error)?;
+    revision.verify_owner(&creds.username).map_err(redact_error)?;
+
+    let revision_content = gist_client
+        .download_gist_data(&revision)
+        .await
+        .map_err(redact_error)?;
+    let store_dir = get_store_dir(&app)?;
+    let revision_content = sync_crypto::open_download(&store_dir, revision_content).map_err(|e| e.to_string())?;
+
+    let data_json_path = store_dir.join(DATA_FILE_NAME);
+    diff_local_against_revision(&revision_content, &data_json_path)
+}
+
+/// Look up the sync gist: with `get_stored` when a gist id is stored,
+/// otherwise by listing with `find`
+///
+/// Only a 404 for the stored id means there is no remote gist. Any other
+/// failure (timeouts, rate limits, 5xx, 401) is returned, so an unreachable
+/// gist is never mistaken for a missing one and overwritten, and a failed
+/// listing never leads to a duplicate gist.
+async fn lookup_remote_gist<GetFut, Find, FindFut>(
+    get_stored: Option<GetFut>,
+    find: Find,
+) -> Result<Option<Gist>, GistApiError>
+where
+    GetFut: std::future::Future<Output = Result<Gist, GistApiError>>,
+    Find: FnOnce() -> FindFut,
+    FindFut: std::future::Future<Output = Result<Option<Gist>, GistApiError>>,
+{
+    match get_stored {
+        Some(get) => match get.await {
+            Ok(gist) => Ok(Some(gist)),
+            Err(GistApiError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        },
+        None => find().await,
+    }
+}
+
+/// Whether `content` parses to the same JSON as `other` (formatting ignored)
+fn same_json(other: Option<&str>, content: &str) -> bool {
+    let parse = |s: &str| serde_json::from_str::<serde_json::Value>(s).ok();
+    match other {
+        Some(other) => other == content || (parse(other).is_some() && parse(other) == parse(content)),
+        None => false,
+    }
+}
+
+/// Write the merged data.json and any pulled notes under `base_path`
+///
+/// Returns whether local files changed. With `dry_run` nothing is written
+/// and the result says whether anything would have been.
+fn apply_local_sync(
+    base_path: &Path,
+    local_content: Option<&str>,
+    content: &str,
+    pulled_notes: Option<&str>,
+    dry_run: bool,
+) -> Result<bool, String> {
+    let data_changed = !same_json(local_content, content);
+    if dry_run {
+        return Ok(data_changed || pulled_notes.is_some());
+    }
+
+    if let Some(notes) = pulled_notes {
+        write_local_notes(&base_path.join(NOTES_FILE_NAME), notes)?;
+    }
+
+    if data_changed {
+        let store_dir = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+        fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
+
+        // Backup existing local data
+        let _ = backup::rotate_backup(&store_dir, DATA_FILE_NAME, backup::DEFAULT_BACKUP_KEEP);
+
+        let data_json_path = store_dir.join(DATA_FILE_NAME);
+        fs::write(&data_json_path, content)
+            .map_err(|e| format!("Failed to write local data: {}", e))?;
+    }
+
+    Ok(data_changed || pulled_notes.is_some())
+}
+
+/// Record the synced data.json and notes as the base for the next merge
+///
+/// Without a base, items deleted on one device can't be told apart from
+/// items added on the other, so one-way pushes and restores record it too.
+fn save_sync_base(base_path: &Path, content: &str, notes: Option<&str>) -> Result<(), String> {
+    let nekotick_dir = base_path.join(NEKOTICK_FOLDER);
+    fs::create_dir_all(&nekotick_dir).map_err(|e| e.to_string())?;
+    fs::write(nekotick_dir.join(SYNC_BASE_FILE), content)
+        .map_err(|e| format!("Failed to write sync base: {}", e))?;
+    if let Some(notes) = notes {
+        fs::write(nekotick_dir.join(SYNC_BASE_NOTES_FILE), notes)
+            .map_err(|e| format!("Failed to write sync base: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Save the conflict strategy used by bidirectional GitHub syncs (`None` resets to `Merge`)
+#[tauri::command]
+pub async fn set_github_conflict_strategy(
+    app: tauri::AppHandle,
+    strategy: Option<ConflictStrategy>,
+) -> Result<(), String> {
+    let mut meta = load_github_sync_meta(&app);
+    meta.conflict_strategy = strategy;
+    save_github_sync_meta(&app, &meta)
+}
+
+/// Import a NekoTick template shared as a gist, replacing local data
+///
+/// `gist_url` is the gist's URL or id. Its data.json must pass the same
+/// validation as `import_data_file`, and local data is backed up first.
+/// With `fork`, the gist is also forked into the connected account so the
+/// user keeps their own copy of the template.
+#[tauri::command]
+pub async fn import_shared_gist(
+    app: tauri::AppHandle,
+    gist_url: String,
+    fork: Option<bool>,
+) -> Result<tasks::ImportResult, SyncError> {
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or(SyncError::NotConnected("GitHub".to_string()))?;
+    let gist_id = parse_gist_id(&gist_url)
+        .ok_or_else(|| format!("Not a gist URL: {}", gist_url))?;
+
+    let gist_client = GistClient::new(creds.access_token.clone());
+    let gist = gist_client.get_gist(&gist_id).await?;
+    let content = gist_client.download_gist_data(&gist).await?;
+
+    // Don't fork something that won't import
+    tasks::check_importable(&content)?;
+    if fork.unwrap_or(false) {
+        gist_client.fork_gist(&gist_id).await?;
+    }
+    tasks::import_data_into(&get_store_dir(&app)?, &content)
+}
+
+/// Result of changing the sync gist's visibility
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GistVisibilityResult {
+    pub public: bool,
+    /// Set when a secret gist became public
+    pub warning: Option<String>,
+}
+
+/// Warning shown when a secret sync gist is made public
+fn visibility_warning(was_public: bool, public: bool) -> Option<String> {
+    (public && !was_public).then(|| {
+        "The sync gist is now public: anyone can read your tasks and notes, and it may appear in search results.".to_string()
+    })
+}
+
+/// Make the sync gist public or secret
+///
+/// The setting is saved and applies to gists created later; an existing
+/// gist is switched in place. Gists stay secret unless this is called.
+#[tauri::command]
+pub async fn set_gist_visibility(
+    app: tauri::AppHandle,
+    public: bool,
+) -> Result<GistVisibilityResult, String> {
+    let creds = load_valid_github_credentials(&app).await
+        .ok_or("Not connected to GitHub")?;
+    let mut meta = load_github_sync_meta(&app);
+    let mut was_public = meta.gist_public.unwrap_or(false);
+
+    if let Some(gist_id) = &creds.gist_id {
+        let client = GistClient::new(creds.access_token.clone());
+        was_public = client.get_gist(gist_id).await.map_err(redact_error)?.public;
+        if was_public != public {
+            client.set_gist_public(gist_id, public).await.map_err(redact_error)?;
+        }
+    }
+
+    meta.gist_public = Some(public);
+    save_github_sync_meta(&app, &meta)?;
+
+    Ok(GistVisibilityResult {
+        public,
+        warning: visibility_warning(was_public, public),
+    })
+}
+
+/// Bidirectional sync with GitHub
+///
+/// Local and remote data are merged task by task against the payload from
+/// the last sync; the result is written to both sides. nekotick.md travels
+/// in the same gist and is reconciled by `resolve_notes`.
+///
+/// With `dry_run` set, nothing is written locally or uploaded; the pulled
+/// and pushed flags report what a real sync would do.
+///
+/// `strategy` overrides the saved conflict strategy for this sync only.
+#[tauri::command]
+pub async fn sync_github_bidirectional(
+    app: tauri::AppHandle,
+    locks: tauri::State<'_, SyncLocks>,
+    dry_run: Option<bool>,
+    strategy: Option<ConflictStrategy>,
+) -> Result<GitHubBidirectionalSyncResult, SyncError> {
+    let sync_guard = locks.try_acquire(SyncBackend::GitHub)?;
+
+    let result = run_sync_github_bidirectional(app.clone(), dry_run, strategy, &sync_guard.cancel).await;
+    if !dry_run.unwrap_or(false) {
+        record_github_outcome(&app, &result, bidirectional_result_failure);
+        sync_queue::record_sync_result(&app, "github", &result, |result| result.success);
+    }
+    result
+}
+
+async fn run_sync_github_bidirectional(
+    app: tauri::AppHandle,
+    dry_run: Option<bool>,
+    strategy: Option<ConflictStrategy>,
+    cancel: &CancellationToken,
+) -> Result<GitHubBidirectionalSyncResult, SyncError> {
+    let dry_run = dry_run.unwrap_or(false);
+    let mut creds = load_valid_github_credentials(&app).await
+        .ok_or(SyncError::NotConnected("GitHub".to_string()))?;
+
     let base_path = get_data_dir(&app)?;
     let data_json_path = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER).join(DATA_FILE_NAME);
 
@@ -546,87 +1782,193 @@ pub async fn sync_github_bidirectional(app: tauri::AppHandle) -> Result<GitHubBi
 
     let mut pulled_from_cloud = false;
     let mut pushed_to_cloud = false;
+    let mut conflicts = Vec::new();
+    let mut notes_conflict = false;
 
-    // Get local modification time
-    let local_modified = if data_json_path.exists() {
-        fs::metadata(&data_json_path)
-            .ok()
-            .and_then(|m| m.modified().ok())
-            .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs() as i64)
+    let sync_base_path = base_path.join(NEKOTICK_FOLDER).join(SYNC_BASE_FILE);
+    let notes_path = base_path.join(NOTES_FILE_NAME);
+    let notes_base_path = base_path.join(NEKOTICK_FOLDER).join(SYNC_BASE_NOTES_FILE);
+    let local_notes = read_local_notes(&notes_path)?;
+
+    let local_content = if data_json_path.exists() {
+        Some(fs::read_to_string(&data_json_path)
+            .map_err(|e| format!("Failed to read data.json: {}", e))?)
     } else {
         None
     };
 
     // Check remote
-    let remote_gist = if let Some(gist_id) = &creds.gist_id {
-        gist_client.get_gist(gist_id).await.ok()
-    } else {
-        gist_client.find_nekotick_gist().await.ok().flatten()
-    };
-
-    // Pull from cloud if remote is newer
-    if let Some(gist) = &remote_gist {
-        let should_pull = match local_modified {
-            Some(local_time) => {
-                // Parse remote time (ISO 8601 format)
-                if let Ok(remote_dt) = chrono::DateTime::parse_from_rfc3339(&gist.updated_at) {
-                    remote_dt.timestamp() > local_time
-                } else {
-                    false
-                }
-            }
-            None => true, // Remote exists, local doesn't
-        };
+    let sync_meta = load_github_sync_meta(&app);
+    let profile = sync_meta.profile;
+    let gist_public = sync_meta.gist_public.unwrap_or(false);
+    let strategy = strategy.or(sync_meta.conflict_strategy).unwrap_or_default();
+    let remote_gist = lookup_remote_gist(
+        creds.gist_id.as_deref().map(|id| gist_client.get_gist(id)),
+        || gist_client.find_nekotick_gist(profile.as_deref()),
+    )
+    .await?;
+
+    let mut remote_updated_at = remote_gist.as_ref().map(|g| g.updated_at.clone());
+    let mut synced_hash = None;
+    let mut remote_notes = None;
+    let mut remote_marker = None;
+    let remote_content = match &remote_gist {
+        Some(gist) => {
+            // Never pull data from a gist owned by another account
+            gist.verify_owner(&creds.username)?;
 
-        if should_pull {
-            // Download remote data
             let content = gist_client
-                .download_data(&gist.id)
-                .await
-                .map_err(|e| e.to_string())?;
-
-            // Ensure local directory exists
-            let store_dir = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
-            fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
-
-            // Backup existing local data
-            if data_json_path.exists() {
-                let backup_path = store_dir.join(format!("{}.backup", DATA_FILE_NAME));
-                let _ = fs::copy(&data_json_path, &backup_path);
+                .download_gist_data(gist)
+                .await?;
+            remote_marker = sync_crypto::payload_marker(&content);
+            let content = sync_crypto::open_download(&get_store_dir(&app)?, content)?;
+
+            // Stop before pulling (and then pushing back) data from a newer app version
+            if schema::check_schema_version(&content)?.is_too_new() {
+                return Err(SyncError::SchemaTooNew);
             }
 
-            // Write remote data to local
-            fs::write(&data_json_path, &content)
-                .map_err(|e| format!("Failed to write local data: {}", e))?;
+            remote_notes = gist_client
+                .download_gist_notes(gist)
+                .await?;
+            Some(content)
+        }
+        None => None,
+    };
 
-            pulled_from_cloud = true;
+    // By default merge both sides against the last synced payload instead of overwriting the older one
+    let synced_content = match (&local_content, &remote_content) {
+        (Some(local), Some(remote)) => {
+            let base = fs::read_to_string(&sync_base_path).ok();
+            let outcome = tasks::resolve_task_json(strategy, local, remote, base.as_deref(), chrono::Utc::now().timestamp_millis())?;
+            conflicts = outcome.conflicts;
+            Some(outcome.merged)
         }
-    }
+        (Some(local), None) => Some(local.clone()),
+        (None, Some(remote)) => Some(remote.clone()),
+        (None, None) => None,
+    };
+
+    if let Some(content) = &synced_content {
+        let notes_base = fs::read_to_string(&notes_base_path).ok();
+        let notes_sync = resolve_notes(local_notes.as_deref(), remote_notes.as_deref(), notes_base.as_deref());
+        notes_conflict = notes_sync.overwritten_remote.is_some();
+
+        // Push when the remote is missing, behind the merge, or sealed differently
+        // than uploads are now (the sync passphrase was set or cleared)
+        let store_dir = get_store_dir(&app)?;
+        let resealed = remote_gist.is_some() && remote_marker != sync_crypto::upload_marker(&store_dir);
+        let needs_push = !same_json(remote_content.as_deref(), content) || notes_sync.upload.is_some() || resealed;
+
+        let (pulled, pushed) = write_then_push(
+            cancel,
+            || {
+                // The remote notes are about to be replaced; keep them recoverable
+                if let (Some(remote), false) = (&notes_sync.overwritten_remote, dry_run) {
+                    backup::save_backup(&base_path, NOTES_FILE_NAME, remote, backup::DEFAULT_BACKUP_KEEP)?;
+                }
 
-    // Push local data to cloud
-    if data_json_path.exists() {
-        let content = fs::read_to_string(&data_json_path)
-            .map_err(|e| format!("Failed to read data.json: {}", e))?;
+                // Write locally when the merge or the remote notes changed anything
+                Ok(apply_local_sync(
+                    &base_path,
+                    local_content.as_deref(),
+                    content,
+                    notes_sync.write_local.as_deref(),
+                    dry_run,
+                )?)
+            },
+            needs_push,
+            || async {
+                if dry_run {
+                    return Ok(PushOutcome::Pushed(None));
+                }
 
-        let gist = gist_client
-            .upload_data(creds.gist_id.as_deref(), &content)
-            .await
-            .map_err(|e| e.to_string())?;
+                // Another device may have pushed while we merged; don't overwrite it
+                if let Some(remote) = &remote_gist {
+                    match gist_client.ensure_unchanged(&remote.id, &remote.updated_at).await {
+                        Ok(()) => {}
+                        Err(e @ GistApiError::RemoteChanged { .. }) => {
+                            return Ok(PushOutcome::RemoteChanged(redact_error(e)));
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
 
-        // Update stored gist_id if it was newly created
-        if creds.gist_id.is_none() {
-            creds.gist_id = Some(gist.id);
-            save_github_credentials(&app, &creds)?;
+                let payload = sync_crypto::seal_for_upload(&store_dir, content)?;
+                let gist = gist_client
+                    .upload_data(
+                        creds.gist_id.as_deref(),
+                        &payload,
+                        notes_sync.upload.as_deref(),
+                        compression::should_compress(&payload),
+                        profile.as_deref(),
+                        gist_public,
+                    )
+                    .await?;
+                Ok(PushOutcome::Pushed(Some((gist, sync_crypto::payload_marker(&payload)))))
+            },
+        )
+        .await?;
+        pulled_from_cloud = pulled;
+
+        let mut synced_marker = remote_marker.clone();
+        match pushed {
+            None => {}
+            Some(PushOutcome::RemoteChanged(error)) => {
+                return Ok(GitHubBidirectionalSyncResult {
+                    success: false,
+                    timestamp: None,
+                    pulled_from_cloud,
+                    pushed_to_cloud: false,
+                    conflicts,
+                    notes_conflict,
+                    remote_changed: true,
+                    error: Some(error),
+                });
+            }
+            Some(PushOutcome::Pushed(uploaded)) => {
+                if let Some((gist, marker)) = uploaded {
+                    // Update stored gist_id if it was newly created
+                    if creds.gist_id.is_none() {
+                        creds.gist_id = Some(gist.id.clone());
+                        save_github_credentials(&app, &creds)?;
+                    }
+                    remote_updated_at = Some(gist.updated_at);
+                    synced_marker = marker;
+                }
+                pushed_to_cloud = true;
+            }
         }
 
-        pushed_to_cloud = true;
+        // Both sides now hold this payload; it is the base for the next merge
+        if !dry_run {
+            let synced_notes = notes_sync.write_local.as_deref()
+                .or(notes_sync.upload.as_deref())
+                .or(local_notes.as_deref());
+            save_sync_base(&base_path, content, synced_notes)?;
+            synced_hash = Some(upload_hash(content, synced_notes, synced_marker.as_deref()));
+        }
+    }
+
+    if dry_run {
+        return Ok(GitHubBidirectionalSyncResult {
+            success: true,
+            timestamp: None,
+            pulled_from_cloud,
+            pushed_to_cloud,
+            conflicts,
+            notes_conflict,
+            remote_changed: false,
+            error: None,
+        });
     }
 
     // Update sync metadata
     let now = chrono::Utc::now().timestamp();
-    let meta = GitHubSyncMeta {
-        last_sync_time: Some(now),
-    };
+    let mut meta = load_github_sync_meta(&app);
+    meta.last_sync_time = Some(now);
+    meta.remote_updated_at = remote_updated_at;
+    meta.last_uploaded_hash = synced_hash;
     save_github_sync_meta(&app, &meta)?;
 
     Ok(GitHubBidirectionalSyncResult {
@@ -634,22 +1976,35 @@ pub async fn sync_github_bidirectional(app: tauri::AppHandle) -> Result<GitHubBi
         timestamp: Some(now),
         pulled_from_cloud,
         pushed_to_cloud,
+        conflicts,
+        notes_conflict,
+        remote_changed: false,
         error: None,
     })
 }
 
 
 /// Check PRO status from cloud API
+///
+/// Enterprise Server accounts are never looked up: their ids mean nothing
+/// to the cloud API, so they are reported as not PRO.
 #[tauri::command]
 pub async fn check_pro_status(app: tauri::AppHandle) -> Result<ProStatusResult, String> {
-    let creds = load_github_credentials(&app)
+    let creds = load_valid_github_credentials(&app).await
         .ok_or("Not connected to GitHub")?;
 
+    if !server::current().is_dotcom() {
+        return Ok(ProStatusResult {
+            is_pro: false,
+            expires_at: None,
+        });
+    }
+
     let github_id = creds.github_id
         .ok_or("GitHub ID not available. Please reconnect to GitHub.")?;
 
     // Call cloud API to check PRO status
-    let client = reqwest::Client::new();
+    let client = crate::http::build_http_client();
     let response = client
         .post("https://api.nekotick.com/check_pro")
         .json(&serde_json::json!({
@@ -679,3 +2034,410 @@ pub async fn check_pro_status(app: tauri::AppHandle) -> Result<ProStatusResult,
         expires_at,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creds_with_expiry(refresh_token: Option<&str>, token_expires_at: Option<i64>) -> GitHubCredentials {
+        GitHubCredentials {
+            access_token: "ghu_old".to_string(),
+            username: "neko".to_string(),
+            github_id: None,
+            avatar_url: None,
+            gist_id: None,
+            email: None,
+            refresh_token: refresh_token.map(|s| s.to_string()),
+            token_expires_at,
+            refresh_token_expires_at: None,
+            token_based: false,
+            server: None,
+        }
+    }
+
+    #[test]
+    fn test_remote_purge_treats_missing_gist_as_purged() {
+        assert!(remote_purge_outcome(Ok(())).is_ok());
+        assert!(remote_purge_outcome(Err(GistApiError::NotFound("gone".to_string()))).is_ok());
+
+        let err = remote_purge_outcome(Err(GistApiError::Unauthorized)).unwrap_err();
+        assert!(err.starts_with("Failed to delete remote gist"));
+    }
+
+    fn gist_updated_at(updated_at: &str) -> Gist {
+        Gist {
+            id: "abc123".to_string(),
+            owner: None,
+            description: None,
+            public: false,
+            files: std::collections::HashMap::new(),
+            created_at: updated_at.to_string(),
+            updated_at: updated_at.to_string(),
+            html_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_gist_fetch_is_not_treated_as_missing() {
+        for error in [
+            GistApiError::Timeout,
+            GistApiError::Unauthorized,
+            GistApiError::RateLimited { reset_at: 0 },
+            GistApiError::ApiError("502 Bad Gateway".to_string()),
+        ] {
+            let mut listed = false;
+            let result = lookup_remote_gist(Some(async { Err(error) }), || {
+                listed = true;
+                async { Ok(Some(gist_updated_at("2024-01-01T00:00:00Z"))) }
+            })
+            .await;
+
+            // An error stops the sync before the merge, so nothing is uploaded
+            // over the gist and no replacement gist is created from a listing
+            assert!(result.is_err());
+            assert!(!listed);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_gist_lookup() {
+        let deleted = lookup_remote_gist(
+            Some(async { Err(GistApiError::NotFound("gone".to_string())) }),
+            || async { Ok(None) },
+        )
+        .await;
+        assert!(deleted.unwrap().is_none());
+
+        let stored = lookup_remote_gist(Some(async { Ok(gist_updated_at("2024-01-01T00:00:00Z")) }), || async { Ok(None) }).await;
+        assert_eq!(stored.unwrap().unwrap().id, "abc123");
+
+        let failed_listing = lookup_remote_gist(
+            None::<std::future::Ready<Result<Gist, GistApiError>>>,
+            || async { Err(GistApiError::Timeout) },
+        )
+        .await;
+        assert!(failed_listing.is_err());
+    }
+
+    #[test]
+    fn test_diff_local_against_fixture_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_json_path = dir.path().join(DATA_FILE_NAME);
+        fs::write(
+            &data_json_path,
+            r#"{"version":2,"lastModified":2000,"data":{"tasks":[
+                {"id":"a","content":"water plants","completed":true,"updatedAt":2000},
+                {"id":"c","content":"new since backup","updatedAt":2000}
+            ]}}"#,
+        )
+        .unwrap();
+        let revision = r#"{"version":2,"lastModified":1000,"data":{"tasks":[
+            {"id":"a","content":"water plants","completed":false,"updatedAt":1000},
+            {"id":"b","content":"only in backup","updatedAt":1000}
+        ]}}"#;
+
+        let diff = diff_local_against_revision(revision, &data_json_path).unwrap();
+        assert_eq!(diff.added.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), vec!["c"]);
+        assert_eq!(diff.removed.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(), vec!["b"]);
+        assert_eq!(diff.modified[0].changed_fields, vec!["completed"]);
+
+        // Comparing never touches the local file
+        assert!(fs::read_to_string(&data_json_path).unwrap().contains("new since backup"));
+    }
+
+    #[test]
+    fn test_diff_against_revision_without_local_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let revision = r#"{"data":{"tasks":[{"id":"a","content":"x"}]}}"#;
+        let diff = diff_local_against_revision(revision, &dir.path().join(DATA_FILE_NAME)).unwrap();
+        assert_eq!(diff.removed.len(), 1);
+    }
+
+    #[test]
+    fn test_same_json_ignores_formatting() {
+        assert!(same_json(Some(r#"{"a":1}"#), "{\n  \"a\": 1\n}"));
+        assert!(!same_json(Some(r#"{"a":1}"#), r#"{"a":2}"#));
+        assert!(!same_json(None, r#"{"a":1}"#));
+    }
+
+    #[test]
+    fn test_noreply_email_prefers_id_form() {
+        assert_eq!(github_noreply_email("neko", Some(42)), "42+neko@users.noreply.github.com");
+        assert_eq!(github_noreply_email("neko", None), "neko@users.noreply.github.com");
+    }
+
+    #[test]
+    fn test_oauth_app_token_never_needs_refresh() {
+        let creds = creds_with_expiry(None, None);
+        assert!(!creds.needs_refresh(i64::MAX));
+    }
+
+    #[test]
+    fn test_personal_access_token_never_refreshed() {
+        let mut creds = creds_with_expiry(Some("ghr_refresh"), Some(1_000));
+        creds.token_based = true;
+        assert!(!creds.needs_refresh(999));
+    }
+
+    #[test]
+    fn test_token_refreshed_near_expiry() {
+        let creds = creds_with_expiry(Some("ghr_1"), Some(10_000));
+        assert!(!creds.needs_refresh(10_000 - TOKEN_REFRESH_MARGIN_SECS - 1));
+        assert!(creds.needs_refresh(10_000 - TOKEN_REFRESH_MARGIN_SECS));
+    }
+
+    #[test]
+    fn test_expired_refresh_token_not_used() {
+        let mut creds = creds_with_expiry(Some("ghr_1"), Some(10_000));
+        creds.refresh_token_expires_at = Some(9_000);
+        assert!(!creds.needs_refresh(9_500));
+    }
+
+    #[test]
+    fn test_apply_token_response_rotates_refresh_token() {
+        let mut creds = creds_with_expiry(Some("ghr_1"), Some(10_000));
+        let tokens = GitHubTokenResponse {
+            access_token: "ghu_new".to_string(),
+            token_type: "bearer".to_string(),
+            scope: None,
+            refresh_token: Some("ghr_2".to_string()),
+            expires_in: Some(28_800),
+            refresh_token_expires_in: Some(15_897_600),
+        };
+        creds.apply_token_response(&tokens, 1_000);
+        assert_eq!(creds.access_token, "ghu_new");
+        assert_eq!(creds.refresh_token.as_deref(), Some("ghr_2"));
+        assert_eq!(creds.token_expires_at, Some(29_800));
+        assert_eq!(creds.refresh_token_expires_at, Some(15_898_600));
+    }
+
+    #[test]
+    fn test_clear_github_local_state_removes_creds_and_meta() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(GITHUB_CREDS_FILE), "{}").unwrap();
+        fs::write(dir.path().join(GITHUB_SYNC_META_FILE), "{}").unwrap();
+        fs::write(dir.path().join(DATA_FILE_NAME), "{}").unwrap();
+
+        clear_github_local_state(dir.path()).unwrap();
+
+        assert!(!dir.path().join(GITHUB_CREDS_FILE).exists());
+        assert!(!dir.path().join(GITHUB_SYNC_META_FILE).exists());
+        // User data is never touched by a disconnect
+        assert!(dir.path().join(DATA_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_clear_github_server_state_drops_sync_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_dir = dir.path().join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+        fs::create_dir_all(&store_dir).unwrap();
+        fs::write(store_dir.join(GITHUB_CREDS_ENC_FILE), "x").unwrap();
+        fs::write(dir.path().join(NEKOTICK_FOLDER).join(SYNC_BASE_FILE), "{}").unwrap();
+
+        clear_github_server_state(dir.path()).unwrap();
+
+        assert!(!store_dir.join(GITHUB_CREDS_ENC_FILE).exists());
+        assert!(!dir.path().join(NEKOTICK_FOLDER).join(SYNC_BASE_FILE).exists());
+    }
+
+    #[test]
+    fn test_credentials_only_used_with_issuing_server() {
+        let enterprise = GitHubServer::from_base_url("https://github.example.com").unwrap();
+        let mut creds = creds_with_expiry(None, None);
+        // Files written before servers were recorded are github.com tokens
+        assert!(creds.issued_by(&GitHubServer::default()));
+        assert!(!creds.issued_by(&enterprise));
+
+        creds.server = Some(enterprise.web_base.clone());
+        assert!(creds.issued_by(&enterprise));
+        assert!(!creds.issued_by(&GitHubServer::default()));
+    }
+
+    #[test]
+    fn test_gist_etag_round_trips_and_is_optional() {
+        let meta: GitHubSyncMeta = serde_json::from_str("{}").unwrap();
+        assert!(meta.gist_etag.is_none());
+        assert!(!serde_json::to_string(&meta).unwrap().contains("gistEtag"));
+
+        let meta = GitHubSyncMeta {
+            gist_etag: Some(CachedGistEtag {
+                gist_id: "abc".to_string(),
+                etag: "W/\"123\"".to_string(),
+                updated_at: "2024-01-01T00:00:00Z".to_string(),
+            }),
+            ..Default::default()
+        };
+        let parsed: GitHubSyncMeta = serde_json::from_str(&serde_json::to_string(&meta).unwrap()).unwrap();
+        assert_eq!(parsed.gist_etag, meta.gist_etag);
+    }
+
+    #[test]
+    fn test_clear_github_local_state_when_nothing_stored() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(clear_github_local_state(dir.path()).is_ok());
+    }
+
+    #[test]
+    fn test_signed_meta_round_trip() {
+        let key = [1u8; 32];
+        let meta = GitHubSyncMeta {
+            last_sync_time: Some(1_700_000_000),
+            ..Default::default()
+        };
+        let json = meta.to_signed_json(&key).unwrap();
+        let loaded = GitHubSyncMeta::from_signed_json(&json, &key).unwrap();
+        assert_eq!(loaded.last_sync_time, Some(1_700_000_000));
+    }
+
+    #[test]
+    fn test_edited_meta_forces_conservative_sync() {
+        let key = [1u8; 32];
+        let meta = GitHubSyncMeta {
+            last_sync_time: Some(100),
+            ..Default::default()
+        };
+        let json = meta.to_signed_json(&key).unwrap();
+
+        // Pushing last_sync_time forward would hide a newer remote
+        let edited = json.replace("100", "999999");
+        let loaded = GitHubSyncMeta::from_signed_json(&edited, &key).unwrap_or_default();
+        assert_eq!(loaded.last_sync_time, None);
+        assert!(is_remote_newer(Some(200), loaded.last_sync_time));
+
+        // Unsigned legacy metadata is not trusted either
+        assert!(GitHubSyncMeta::from_signed_json(r#"{"lastSyncTime":100}"#, &key).is_none());
+    }
+
+    #[test]
+    fn test_remote_newer_than_last_sync() {
+        assert!(is_remote_newer(Some(200), Some(100)));
+    }
+
+    #[test]
+    fn test_remote_older_than_last_sync() {
+        assert!(!is_remote_newer(Some(100), Some(200)));
+    }
+
+    #[test]
+    fn test_remote_equal_to_last_sync() {
+        assert!(!is_remote_newer(Some(100), Some(100)));
+    }
+
+    #[test]
+    fn test_notes_missing_on_both_sides() {
+        assert_eq!(resolve_notes(None, None, None), NotesSync::default());
+    }
+
+    #[test]
+    fn test_notes_pushed_to_single_file_gist() {
+        let sync = resolve_notes(Some("# local"), None, None);
+        assert_eq!(sync.upload.as_deref(), Some("# local"));
+        assert_eq!(sync.write_local, None);
+    }
+
+    #[test]
+    fn test_notes_pulled_when_missing_locally() {
+        let sync = resolve_notes(None, Some("# remote"), None);
+        assert_eq!(sync.write_local.as_deref(), Some("# remote"));
+        assert_eq!(sync.upload, None);
+    }
+
+    #[test]
+    fn test_notes_follow_the_side_that_changed() {
+        let pulled = resolve_notes(Some("# base"), Some("# remote"), Some("# base"));
+        assert_eq!(pulled.write_local.as_deref(), Some("# remote"));
+        assert_eq!(pulled.upload, None);
+
+        let pushed = resolve_notes(Some("# local"), Some("# base"), Some("# base"));
+        assert_eq!(pushed.upload.as_deref(), Some("# local"));
+        assert_eq!(pushed.write_local, None);
+    }
+
+    #[test]
+    fn test_notes_changed_on_both_sides_are_reported() {
+        let sync = resolve_notes(Some("# local"), Some("# remote"), Some("# base"));
+        assert_eq!(sync.upload.as_deref(), Some("# local"));
+        assert_eq!(sync.write_local, None);
+        assert_eq!(sync.overwritten_remote.as_deref(), Some("# remote"));
+
+        // Without a base there is no telling which side changed
+        let unknown = resolve_notes(Some("# local"), Some("# remote"), None);
+        assert_eq!(unknown.overwritten_remote.as_deref(), Some("# remote"));
+
+        // Only the local side changed: nothing on the remote is lost
+        let pushed = resolve_notes(Some("# local"), Some("# base"), Some("# base"));
+        assert_eq!(pushed.overwritten_remote, None);
+        assert_eq!(resolve_notes(Some("# same"), Some("# same"), None), NotesSync::default());
+    }
+
+    #[test]
+    fn test_dry_run_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let merged = r#"{"version":2,"data":{"tasks":[]}}"#;
+
+        let would_pull = apply_local_sync(dir.path(), None, merged, Some("# remote"), true).unwrap();
+        assert!(would_pull);
+        assert!(!dir.path().join(NEKOTICK_FOLDER).exists());
+        assert!(!dir.path().join(NOTES_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn test_apply_local_sync_writes_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let merged = r#"{"version":2,"data":{"tasks":[]}}"#;
+
+        assert!(apply_local_sync(dir.path(), None, merged, Some("# remote"), false).unwrap());
+        let data_json_path = dir.path().join(NEKOTICK_FOLDER).join(STORE_FOLDER).join(DATA_FILE_NAME);
+        assert_eq!(fs::read_to_string(&data_json_path).unwrap(), merged);
+        assert_eq!(fs::read_to_string(dir.path().join(NOTES_FILE_NAME)).unwrap(), "# remote");
+
+        // Already in sync: nothing to pull
+        assert!(!apply_local_sync(dir.path(), Some(merged), merged, None, false).unwrap());
+    }
+
+    #[test]
+    fn test_upload_hash_tracks_data_and_notes() {
+        let hash = upload_hash("{}", Some("# notes"), None);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, upload_hash("{}", Some("# notes"), None));
+        assert_ne!(hash, upload_hash("{ }", Some("# notes"), None));
+        assert_ne!(hash, upload_hash("{}", Some("# notes!"), None));
+        assert_ne!(upload_hash("{}", None, None), upload_hash("{}", Some(""), None));
+    }
+
+    #[test]
+    fn test_setting_passphrase_forces_encrypted_upload_of_unchanged_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "{\"version\":2}";
+
+        // Hash recorded by the last (plaintext) upload
+        let plaintext_payload = sync_crypto::seal_for_upload(dir.path(), content).unwrap();
+        let last_uploaded = upload_hash(content, None, sync_crypto::payload_marker(&plaintext_payload).as_deref());
+        assert_eq!(last_uploaded, upload_hash(content, None, sync_crypto::upload_marker(dir.path()).as_deref()));
+
+        sync_crypto::save_passphrase(dir.path(), "correct horse").unwrap();
+
+        // The same data no longer counts as uploaded, and the next upload is encrypted
+        let marker = sync_crypto::upload_marker(dir.path());
+        assert_ne!(last_uploaded, upload_hash(content, None, marker.as_deref()));
+        let payload = sync_crypto::seal_for_upload(dir.path(), content).unwrap();
+        assert!(sync_crypto::is_encrypted(&payload));
+        assert_eq!(sync_crypto::payload_marker(&payload), marker);
+        // A plaintext remote is out of date while the passphrase is set
+        assert_ne!(sync_crypto::payload_marker(&plaintext_payload), marker);
+    }
+
+    #[test]
+    fn test_remote_without_prior_sync() {
+        assert!(is_remote_newer(Some(100), None));
+        assert!(!is_remote_newer(None, Some(100)));
+    }
+
+    #[test]
+    fn test_warns_only_when_gist_becomes_public() {
+        assert!(visibility_warning(false, true).is_some());
+        assert!(visibility_warning(true, true).is_none());
+        assert!(visibility_warning(true, false).is_none());
+        assert!(visibility_warning(false, false).is_none());
+    }
+}
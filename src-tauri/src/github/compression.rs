@@ -0,0 +1,97 @@
+//! Optional gzip compression of sync payloads
+//!
+//! Gist files must be text, so compressed payloads are stored as a marker
+//! line followed by base64 of the gzip stream. Anything without the marker
+//! is treated as plain JSON, which keeps previously uploaded data readable.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Prefix identifying a compressed payload (never valid JSON)
+pub const COMPRESSED_MARKER: &str = "nekotick-gzip-v1\n";
+
+/// Payloads larger than this are compressed before upload
+pub const COMPRESS_THRESHOLD_BYTES: usize = 256 * 1024;
+
+/// Whether an upload of this size should be compressed
+pub fn should_compress(content: &str) -> bool {
+    content.len() > COMPRESS_THRESHOLD_BYTES
+}
+
+/// Gzip and base64-encode `content` behind the marker
+pub fn encode_payload(content: &str) -> Result<String, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+    let compressed = encoder.finish().map_err(|e| e.to_string())?;
+    Ok(format!("{}{}", COMPRESSED_MARKER, STANDARD.encode(compressed)))
+}
+
+/// Inflate a payload written by `encode_payload`; plain content is returned as is
+pub fn decode_payload(payload: &str) -> Result<String, String> {
+    let Some(encoded) = payload.strip_prefix(COMPRESSED_MARKER) else {
+        return Ok(payload.to_string());
+    };
+
+    let compressed = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Invalid compressed payload: {}", e))?;
+    let mut content = String::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Invalid compressed payload: {}", e))?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A data.json of roughly 500KB with realistic task entries
+    fn large_data_json() -> String {
+        let tasks: Vec<serde_json::Value> = (0..4000)
+            .map(|i| {
+                serde_json::json!({
+                    "id": format!("task-{:05}", i),
+                    "content": format!("Follow up on item {} from the weekly planning notes", i),
+                    "groupId": format!("group-{}", i % 12),
+                    "completed": i % 3 == 0,
+                    "createdAt": 1_700_000_000_000i64 + i as i64 * 60_000,
+                })
+            })
+            .collect();
+        serde_json::to_string_pretty(&serde_json::json!({
+            "version": 2,
+            "lastModified": 1_700_000_000_000i64,
+            "data": { "tasks": tasks }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_large_payload() {
+        let content = large_data_json();
+        assert!(content.len() > 450 * 1024);
+        assert!(should_compress(&content));
+
+        let encoded = encode_payload(&content).unwrap();
+        assert!(encoded.starts_with(COMPRESSED_MARKER));
+        assert!(encoded.len() < content.len() / 4);
+        assert_eq!(decode_payload(&encoded).unwrap(), content);
+    }
+
+    #[test]
+    fn test_plain_payload_passes_through() {
+        let content = r#"{"version":2,"data":{"tasks":[]}}"#;
+        assert!(!should_compress(content));
+        assert_eq!(decode_payload(content).unwrap(), content);
+    }
+
+    #[test]
+    fn test_corrupt_compressed_payload_is_error() {
+        let corrupt = format!("{}not base64!", COMPRESSED_MARKER);
+        assert!(decode_payload(&corrupt).is_err());
+    }
+}
@@ -0,0 +1,359 @@
+//! Encrypted storage for sync credentials (GitHub, WebDAV)
+//!
+//! Credentials are sealed with AES-256-GCM under a key derived from the
+//! per-install secret in `.credential_key` and the machine id, so the file
+//! is useless on its own or copied to another device. A plaintext file from
+//! older versions is migrated on first load.
+//!
+//! The secret is never replaced once written: a key file that can't be
+//! decoded is an error rather than a fresh start, since every stored
+//! credential depends on it. Older versions sealed credentials under the
+//! metadata signing key; those are re-sealed under `.credential_key` when
+//! first read.
+//!
+//! Where the platform doesn't expose a machine id, a random fallback id is
+//! saved next to the credentials and used from then on, even if the machine
+//! id becomes readable later. Otherwise an OS or library update that fixes
+//! the machine id would make every stored credential undecryptable.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD, engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::Sha256;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::github::meta_signing;
+use crate::paths;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const FORMAT_PREFIX: &str = "nekotick-enc-v1:";
+const NONCE_LENGTH: usize = 12;
+const KEY_CONTEXT: &[u8] = b"nekotick github credentials v1";
+const DEVICE_ID_FILE: &str = ".device_id";
+const KEY_FILE: &str = ".credential_key";
+const KEY_LENGTH: usize = 32;
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+
+/// Serializes key creation within the process, so no caller reads a key file mid-write
+static CREATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Where the device id bound into the credential key comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceIdSource {
+    Hardware,
+    Fallback,
+}
+
+/// Id of this machine, if the platform exposes one
+fn hardware_id() -> Option<String> {
+    machine_uid::get().ok().filter(|id| !id.trim().is_empty())
+}
+
+/// Device id for the credential key, and where it came from
+///
+/// A saved fallback id always wins over `hardware_id`; one is created when
+/// neither exists.
+fn stable_device_id(store_dir: &Path, hardware_id: Option<String>) -> Result<(String, DeviceIdSource), String> {
+    let path = store_dir.join(DEVICE_ID_FILE);
+
+    if let Ok(id) = fs::read_to_string(&path) {
+        if !id.trim().is_empty() {
+            return Ok((id.trim().to_string(), DeviceIdSource::Fallback));
+        }
+    }
+
+    if let Some(id) = hardware_id {
+        return Ok((id, DeviceIdSource::Hardware));
+    }
+
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let id: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    fs::write(&path, &id).map_err(|e| e.to_string())?;
+
+    Ok((id, DeviceIdSource::Fallback))
+}
+
+/// Derive the credential key from the install secret and device id
+fn derive_key(install_key: &[u8], device_id: &str) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(install_key).expect("HMAC accepts keys of any length");
+    mac.update(KEY_CONTEXT);
+    mac.update(device_id.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Stable id of this device, as bound into the credential key
+pub fn device_id(store_dir: &Path) -> Result<String, String> {
+    stable_device_id(store_dir, hardware_id()).map(|(id, _)| id)
+}
+
+/// Read the credential secret, or `None` if none was created yet
+fn read_install_key(store_dir: &Path) -> Result<Option<Vec<u8>>, String> {
+    let encoded = match fs::read_to_string(store_dir.join(KEY_FILE)) {
+        Ok(encoded) => encoded,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read the credential key: {}", e)),
+    };
+    URL_SAFE_NO_PAD
+        .decode(encoded.trim())
+        .ok()
+        .filter(|key| key.len() == KEY_LENGTH)
+        .map(Some)
+        .ok_or_else(|| "Credential key is unreadable; stored credentials can't be decrypted".to_string())
+}
+
+/// Load the credential secret, creating it on first use
+///
+/// The file is created with `create_new`, so when another process wins the
+/// race its key is re-read instead of overwritten.
+fn load_or_create_install_key(store_dir: &Path) -> Result<Vec<u8>, String> {
+    let _guard = CREATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(key) = read_install_key(store_dir)? {
+        return Ok(key);
+    }
+
+    let mut key = vec![0u8; KEY_LENGTH];
+    rand::thread_rng().fill_bytes(&mut key);
+
+    fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    match fs::OpenOptions::new().write(true).create_new(true).open(store_dir.join(KEY_FILE)) {
+        Ok(mut file) => {
+            file.write_all(URL_SAFE_NO_PAD.encode(&key).as_bytes()).map_err(|e| e.to_string())?;
+            file.sync_all().map_err(|e| e.to_string())?;
+            Ok(key)
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            read_install_key(store_dir)?.ok_or_else(|| "Credential key is missing".to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn load_key(store_dir: &Path) -> Result<[u8; 32], String> {
+    let install_key = load_or_create_install_key(store_dir)?;
+    let (device_id, _) = stable_device_id(store_dir, hardware_id())?;
+    Ok(derive_key(&install_key, &device_id))
+}
+
+/// Encrypt `plaintext` as `nekotick-enc-v1:<base64(nonce || ciphertext)>`
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Result<String, String> {
+    let cipher = Aes256Gcm::new(key.into());
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| "Failed to encrypt credentials".to_string())?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", FORMAT_PREFIX, STANDARD.encode(sealed)))
+}
+
+/// Decrypt a value produced by `seal`
+fn open(key: &[u8; 32], sealed: &str) -> Result<Vec<u8>, String> {
+    let encoded = sealed
+        .trim()
+        .strip_prefix(FORMAT_PREFIX)
+        .ok_or("Unrecognized credentials format")?;
+    let bytes = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+    if bytes.len() < NONCE_LENGTH {
+        return Err("Encrypted credentials are truncated".to_string());
+    }
+
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+    Aes256Gcm::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Credentials could not be decrypted on this device".to_string())
+}
+
+/// Encrypt `plaintext` into `path`
+pub fn write_encrypted(store_dir: &Path, path: &Path, plaintext: &[u8]) -> Result<(), String> {
+    let sealed = seal(&load_key(store_dir)?, plaintext)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, sealed).map_err(|e| e.to_string())
+}
+
+/// Read and decrypt `path`, or `None` if it doesn't exist
+pub fn read_encrypted(store_dir: &Path, path: &Path) -> Result<Option<Vec<u8>>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let sealed = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let own_key = read_install_key(store_dir)?;
+    let (device_id, source) = stable_device_id(store_dir, hardware_id())?;
+
+    // Sealed by an older version under the metadata signing key
+    let legacy_key = meta_signing::load_key(store_dir);
+    let install_keys: Vec<&[u8]> = own_key.iter().chain(legacy_key.iter()).map(Vec::as_slice).collect();
+    if install_keys.is_empty() {
+        return Err("Credential key is missing".to_string());
+    }
+    let mut device_ids = vec![device_id.as_str()];
+    // Sealed before the fallback id existed, when a missing machine id was keyed as empty
+    if source == DeviceIdSource::Fallback {
+        device_ids.push("");
+    }
+
+    let candidates = install_keys.iter().flat_map(|key| device_ids.iter().map(move |id| (*key, *id)));
+    let mut first_error = None;
+    for (i, (install_key, id)) in candidates.enumerate() {
+        match open(&derive_key(install_key, id), &sealed) {
+            Ok(plaintext) => {
+                if own_key.is_none() || i > 0 {
+                    write_encrypted(store_dir, path, &plaintext)?;
+                }
+                return Ok(Some(plaintext));
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| "Credential key is missing".to_string()))
+}
+
+/// Encrypt a plaintext file from an older version into `encrypted_path`,
+/// then delete the plaintext
+///
+/// Returns whether anything was migrated. An existing encrypted file wins;
+/// the stale plaintext is still deleted.
+pub fn migrate_plaintext(store_dir: &Path, plaintext_path: &Path, encrypted_path: &Path) -> Result<bool, String> {
+    if !plaintext_path.exists() {
+        return Ok(false);
+    }
+
+    let migrated = if encrypted_path.exists() {
+        false
+    } else {
+        let plaintext = fs::read(plaintext_path).map_err(|e| e.to_string())?;
+        write_encrypted(store_dir, encrypted_path, &plaintext)?;
+        true
+    };
+
+    fs::remove_file(plaintext_path).map_err(|e| e.to_string())?;
+    Ok(migrated)
+}
+
+/// Whether stored credentials are bound to the machine id or a saved fallback id
+///
+/// With a fallback id, credentials only survive a move to another machine
+/// or a reinstall if `.nekotick/store` is copied along with them.
+#[tauri::command]
+pub async fn get_device_id_source(app: tauri::AppHandle) -> Result<DeviceIdSource, String> {
+    let store_dir = paths::data_dir(&app)?.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+    stable_device_id(&store_dir, hardware_id()).map(|(_, source)| source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_round_trip() {
+        let key = derive_key(&[7u8; 32], "machine-a");
+        let sealed = seal(&key, b"{\"access_token\":\"gho_secret\"}").unwrap();
+
+        assert!(sealed.starts_with(FORMAT_PREFIX));
+        assert!(!sealed.contains("gho_secret"));
+        assert_eq!(open(&key, &sealed).unwrap(), b"{\"access_token\":\"gho_secret\"}");
+    }
+
+    #[test]
+    fn test_other_device_cannot_decrypt() {
+        let sealed = seal(&derive_key(&[7u8; 32], "machine-a"), b"secret").unwrap();
+        assert!(open(&derive_key(&[7u8; 32], "machine-b"), &sealed).is_err());
+        assert!(open(&derive_key(&[8u8; 32], "machine-a"), &sealed).is_err());
+    }
+
+    #[test]
+    fn test_saved_fallback_id_wins_over_hardware_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let (id, source) = stable_device_id(dir.path(), Some("machine-a".to_string())).unwrap();
+        assert_eq!((id.as_str(), source), ("machine-a", DeviceIdSource::Hardware));
+
+        let (fallback, source) = stable_device_id(dir.path(), None).unwrap();
+        assert_eq!(source, DeviceIdSource::Fallback);
+        assert_eq!(fallback.len(), 32);
+
+        // The machine id starting to work later must not change the key
+        let (id, source) = stable_device_id(dir.path(), Some("machine-a".to_string())).unwrap();
+        assert_eq!((id, source), (fallback, DeviceIdSource::Fallback));
+    }
+
+    #[test]
+    fn test_migrates_plaintext_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let plaintext_path = dir.path().join("github_credentials.json");
+        let encrypted_path = dir.path().join("github_credentials.enc");
+        fs::write(&plaintext_path, "{\"access_token\":\"gho_secret\"}").unwrap();
+
+        assert!(migrate_plaintext(dir.path(), &plaintext_path, &encrypted_path).unwrap());
+        assert!(!plaintext_path.exists());
+        assert!(!fs::read_to_string(&encrypted_path).unwrap().contains("gho_secret"));
+        assert_eq!(
+            read_encrypted(dir.path(), &encrypted_path).unwrap().unwrap(),
+            b"{\"access_token\":\"gho_secret\"}"
+        );
+
+        // Nothing left to migrate
+        assert!(!migrate_plaintext(dir.path(), &plaintext_path, &encrypted_path).unwrap());
+    }
+
+    #[test]
+    fn test_credentials_survive_metadata_key_replacement() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("github_credentials.enc");
+        meta_signing::load_or_create_key(dir.path()).unwrap();
+        write_encrypted(dir.path(), &path, b"secret").unwrap();
+
+        fs::write(dir.path().join(".sync_meta_key"), "not a key").unwrap();
+        meta_signing::load_or_create_key(dir.path()).unwrap();
+        assert_eq!(read_encrypted(dir.path(), &path).unwrap().unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_unreadable_credential_key_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("github_credentials.enc");
+        write_encrypted(dir.path(), &path, b"secret").unwrap();
+
+        fs::write(dir.path().join(KEY_FILE), "not a key").unwrap();
+        assert!(read_encrypted(dir.path(), &path).is_err());
+        assert!(write_encrypted(dir.path(), &path, b"other").is_err());
+        assert_eq!(fs::read_to_string(dir.path().join(KEY_FILE)).unwrap(), "not a key");
+    }
+
+    #[test]
+    fn test_reseals_credentials_from_metadata_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let github = dir.path().join("github_credentials.enc");
+        let webdav = dir.path().join("webdav_credentials.enc");
+        let (device_id, _) = stable_device_id(dir.path(), hardware_id()).unwrap();
+        let legacy_key = derive_key(&meta_signing::load_or_create_key(dir.path()).unwrap(), &device_id);
+        fs::write(&github, seal(&legacy_key, b"github").unwrap()).unwrap();
+        fs::write(&webdav, seal(&legacy_key, b"webdav").unwrap()).unwrap();
+
+        assert_eq!(read_encrypted(dir.path(), &github).unwrap().unwrap(), b"github");
+        assert!(dir.path().join(KEY_FILE).exists());
+        // The second file is still readable after the first created `.credential_key`
+        assert_eq!(read_encrypted(dir.path(), &webdav).unwrap().unwrap(), b"webdav");
+
+        fs::remove_file(dir.path().join(".sync_meta_key")).unwrap();
+        assert_eq!(read_encrypted(dir.path(), &github).unwrap().unwrap(), b"github");
+        assert_eq!(read_encrypted(dir.path(), &webdav).unwrap().unwrap(), b"webdav");
+    }
+}
@@ -2,12 +2,18 @@
 //!
 //! Provides methods to interact with GitHub Gist API for sync operations.
 
+use crate::github::compression;
+use crate::github::server;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-const GITHUB_API_BASE: &str = "https://api.github.com";
 const NEKOTICK_GIST_DESCRIPTION: &str = "NekoTick Sync Data";
 const DATA_FILE_NAME: &str = "data.json";
+const NOTES_FILE_NAME: &str = "nekotick.md";
+/// Safety cap on pages fetched when listing gists (100 gists per page)
+const MAX_GIST_PAGES: u32 = 10;
+/// Default cap on an upload; GitHub rejects larger gists with an unhelpful error
+pub const DEFAULT_GIST_SIZE_LIMIT: usize = 10 * 1024 * 1024;
 
 /// GitHub user info
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,6 +25,43 @@ pub struct GitHubUser {
     pub email: Option<String>,
 }
 
+/// One bucket of GitHub API quota from `/rate_limit`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubRateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    pub used: u32,
+    /// Unix timestamp (seconds) when the quota resets
+    pub reset: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResources {
+    core: GitHubRateLimit,
+}
+
+#[derive(Debug, Deserialize)]
+struct RateLimitResponse {
+    resources: RateLimitResources,
+}
+
+/// Email address entry from `/user/emails`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubEmail {
+    pub email: String,
+    pub primary: bool,
+    pub verified: bool,
+    pub visibility: Option<String>,
+}
+
+/// Pick the primary email, but only if GitHub has verified it
+pub fn select_primary_email(emails: &[GitHubEmail]) -> Option<String> {
+    emails
+        .iter()
+        .find(|e| e.primary && e.verified)
+        .map(|e| e.email.clone())
+}
+
 /// Gist file content
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GistFile {
@@ -28,10 +71,19 @@ pub struct GistFile {
     pub size: Option<u64>,
 }
 
+/// Gist owner info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GistOwner {
+    pub login: String,
+    pub id: u64,
+}
+
 /// Gist response from GitHub API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Gist {
     pub id: String,
+    #[serde(default)]
+    pub owner: Option<GistOwner>,
     pub description: Option<String>,
     pub public: bool,
     pub files: HashMap<String, GistFile>,
@@ -40,6 +92,60 @@ pub struct Gist {
     pub html_url: Option<String>,
 }
 
+/// Line change counts for one gist revision
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GistChangeStatus {
+    #[serde(default)]
+    pub total: u64,
+    #[serde(default)]
+    pub additions: u64,
+    #[serde(default)]
+    pub deletions: u64,
+}
+
+/// Gist history entry from GitHub API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GistHistoryEntry {
+    pub version: String,
+    pub committed_at: String,
+    #[serde(default)]
+    pub change_status: GistChangeStatus,
+}
+
+/// One revision of the sync gist, as shown to the user
+///
+/// GitHub only reports line change counts per revision; byte sizes would
+/// need a download per revision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GistRevision {
+    pub sha: String,
+    pub committed_at: String,
+    pub changes: u64,
+    pub additions: u64,
+    pub deletions: u64,
+}
+
+impl From<GistHistoryEntry> for GistRevision {
+    fn from(entry: GistHistoryEntry) -> Self {
+        Self {
+            sha: entry.version,
+            committed_at: entry.committed_at,
+            changes: entry.change_status.total,
+            additions: entry.change_status.additions,
+            deletions: entry.change_status.deletions,
+        }
+    }
+}
+
+/// Result of a conditional gist fetch
+#[derive(Debug, Clone)]
+pub enum GistFetch {
+    /// The gist still matches the ETag that was sent
+    NotModified,
+    Fetched { gist: Box<Gist>, etag: Option<String> },
+}
+
 /// Gist creation/update request
 #[derive(Debug, Clone, Serialize)]
 pub struct GistRequest {
@@ -54,6 +160,67 @@ pub struct GistFileContent {
     pub content: String,
 }
 
+/// Gist description for a sync profile
+///
+/// The default profile (`None` or blank) keeps the original description so
+/// existing gists are still found; named profiles get their own gist.
+pub fn gist_description(profile: Option<&str>) -> String {
+    match profile.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(profile) => format!("{} ({})", NEKOTICK_GIST_DESCRIPTION, profile),
+        None => NEKOTICK_GIST_DESCRIPTION.to_string(),
+    }
+}
+
+/// Whether `gist` is the sync gist with `description` (and a data.json file)
+fn is_nekotick_gist(gist: &Gist, description: &str) -> bool {
+    gist.description.as_deref() == Some(description)
+        && gist.files.contains_key(DATA_FILE_NAME)
+}
+
+/// Feed gist pages to `on_page` until it returns a gist, a page comes back
+/// empty, or `MAX_GIST_PAGES` is reached
+async fn scan_gist_pages<F, Fut>(
+    mut fetch_page: F,
+    mut on_page: impl FnMut(Vec<Gist>) -> Option<Gist>,
+) -> Result<Option<Gist>, GistApiError>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<Gist>, GistApiError>>,
+{
+    for page in 1..=MAX_GIST_PAGES {
+        let gists = fetch_page(page).await?;
+        if gists.is_empty() {
+            break;
+        }
+        if let Some(found) = on_page(gists) {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+/// Build the gist file map for data.json plus nekotick.md when there is one
+///
+/// Leaving nekotick.md out of an update keeps whatever the gist already holds.
+fn gist_files(content: &str, notes: Option<&str>) -> HashMap<String, GistFileContent> {
+    let mut files = HashMap::new();
+    files.insert(
+        DATA_FILE_NAME.to_string(),
+        GistFileContent {
+            content: content.to_string(),
+        },
+    );
+    if let Some(notes) = notes {
+        files.insert(
+            NOTES_FILE_NAME.to_string(),
+            GistFileContent {
+                content: notes.to_string(),
+            },
+        );
+    }
+    files
+}
+
 /// Error types for Gist API operations
 #[derive(Debug, thiserror::Error)]
 pub enum GistApiError {
@@ -67,42 +234,147 @@ pub enum GistApiError {
     NotFound(String),
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("Gist belongs to {actual}, not the connected account {expected}")]
+    OwnershipMismatch { expected: String, actual: String },
+    #[error("Missing OAuth scope: {0}")]
+    MissingScope(String),
+    #[error("Remote gist changed since it was last synced (updated {actual}, expected {expected})")]
+    RemoteChanged { expected: String, actual: String },
+    #[error("GitHub API rate limit exceeded (resets at {reset_at})")]
+    RateLimited { reset_at: i64 },
+    #[error("GitHub timed out")]
+    Timeout,
+    #[error("Data is too large for a gist ({size} bytes, limit {limit}). Switch to repository sync, which has no such limit.")]
+    TooLarge { size: usize, limit: usize },
+}
+
+/// Reset time of an exhausted GitHub rate limit, if `status`/`headers` report one
+///
+/// GitHub answers 403 (or 429) with `x-ratelimit-remaining: 0` and the reset
+/// time as a Unix timestamp in `x-ratelimit-reset`.
+pub fn rate_limit_reset(status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap) -> Option<i64> {
+    if status != reqwest::StatusCode::FORBIDDEN && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if header("x-ratelimit-remaining")? != "0" {
+        return None;
+    }
+    Some(header("x-ratelimit-reset").and_then(|v| v.parse().ok()).unwrap_or_default())
+}
+
+/// Turn an unsuccessful response into an error, recognising rate limiting
+async fn response_error(response: reqwest::Response) -> GistApiError {
+    if let Some(reset_at) = rate_limit_reset(response.status(), response.headers()) {
+        return GistApiError::RateLimited { reset_at };
+    }
+    GistApiError::ApiError(response.text().await.unwrap_or_default())
+}
+
+impl GistApiError {
+    /// Classify a failed request, keeping timeouts distinct from other network errors
+    fn from_request(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            GistApiError::Timeout
+        } else {
+            GistApiError::NetworkError(e.to_string())
+        }
+    }
+}
+
+/// Gist id from a gist URL (`https://gist.github.com/<user>/<id>`, with or
+/// without the user, `.git` or a trailing `#file` anchor) or a bare id
+pub fn parse_gist_id(url_or_id: &str) -> Option<String> {
+    let trimmed = url_or_id.trim();
+    let path = match url::Url::parse(trimmed) {
+        Ok(url) if matches!(url.scheme(), "https" | "http") => url.path().to_string(),
+        Ok(_) => return None,
+        Err(_) => trimmed.to_string(),
+    };
+    let id = path.split('/').rfind(|segment| !segment.is_empty())?;
+    let id = id.strip_suffix(".git").unwrap_or(id);
+    (!id.is_empty() && id.chars().all(|c| c.is_ascii_hexdigit())).then(|| id.to_string())
+}
+
+/// Ensure an upload of `size` bytes fits under `limit`
+pub fn check_gist_size(size: usize, limit: usize) -> Result<(), GistApiError> {
+    if size > limit {
+        Err(GistApiError::TooLarge { size, limit })
+    } else {
+        Ok(())
+    }
+}
+
+/// Ensure a gist's `updated_at` still matches the value seen at the last sync
+pub fn check_remote_unchanged(expected_updated_at: &str, actual_updated_at: &str) -> Result<(), GistApiError> {
+    if expected_updated_at == actual_updated_at {
+        Ok(())
+    } else {
+        Err(GistApiError::RemoteChanged {
+            expected: expected_updated_at.to_string(),
+            actual: actual_updated_at.to_string(),
+        })
+    }
+}
+
+impl Gist {
+    /// Ensure the gist is owned by `username` (GitHub logins are case-insensitive)
+    pub fn verify_owner(&self, username: &str) -> Result<(), GistApiError> {
+        let actual = self.owner.as_ref().map(|o| o.login.as_str()).unwrap_or("an anonymous owner");
+        if actual.eq_ignore_ascii_case(username) {
+            Ok(())
+        } else {
+            Err(GistApiError::OwnershipMismatch {
+                expected: username.to_string(),
+                actual: actual.to_string(),
+            })
+        }
+    }
 }
 
 /// GitHub Gist API client
 pub struct GistClient {
     access_token: String,
     client: reqwest::Client,
+    api_base: String,
+    size_limit: usize,
 }
 
 impl GistClient {
-    /// Create a new Gist client
+    /// Create a new Gist client for the configured GitHub server
     pub fn new(access_token: String) -> Self {
         Self {
             access_token,
-            client: reqwest::Client::new(),
+            client: crate::http::build_http_client(),
+            api_base: server::current().api_base,
+            size_limit: DEFAULT_GIST_SIZE_LIMIT,
         }
     }
 
+    /// Reject uploads larger than `limit` bytes instead of `DEFAULT_GIST_SIZE_LIMIT`
+    pub fn with_size_limit(mut self, limit: usize) -> Self {
+        self.size_limit = limit;
+        self
+    }
+
     /// Get authenticated user info
     pub async fn get_user_info(&self) -> Result<GitHubUser, GistApiError> {
         let response = self.client
-            .get(format!("{}/user", GITHUB_API_BASE))
+            .get(format!("{}/user", self.api_base))
             .header("Authorization", format!("Bearer {}", self.access_token))
             .header("Accept", "application/vnd.github+json")
             .header("User-Agent", "NekoTick")
             .header("X-GitHub-Api-Version", "2022-11-28")
             .send()
             .await
-            .map_err(|e| GistApiError::NetworkError(e.to_string()))?;
+            .map_err(GistApiError::from_request)?;
 
         if response.status() == 401 {
             return Err(GistApiError::Unauthorized);
         }
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GistApiError::ApiError(error_text));
+            return Err(response_error(response).await);
         }
 
         response
@@ -111,25 +383,95 @@ impl GistClient {
             .map_err(|e| GistApiError::ParseError(e.to_string()))
     }
 
+    /// Current core API quota; `/rate_limit` itself doesn't count against it
+    pub async fn get_rate_limit(&self) -> Result<GitHubRateLimit, GistApiError> {
+        let response = self.client
+            .get(format!("{}/rate_limit", self.api_base))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        response
+            .json::<RateLimitResponse>()
+            .await
+            .map(|r| r.resources.core)
+            .map_err(|e| GistApiError::ParseError(e.to_string()))
+    }
+
+    /// List the user's email addresses (needs the `user:email` scope)
+    pub async fn list_user_emails(&self) -> Result<Vec<GitHubEmail>, GistApiError> {
+        let response = self.client
+            .get(format!("{}/user/emails", self.api_base))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        // Tokens granted before `user:email` was requested get 403/404 here
+        let rate_limited = rate_limit_reset(response.status(), response.headers()).is_some();
+        if (response.status() == 403 && !rate_limited) || response.status() == 404 {
+            return Err(GistApiError::MissingScope("user:email".to_string()));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        response
+            .json::<Vec<GitHubEmail>>()
+            .await
+            .map_err(|e| GistApiError::ParseError(e.to_string()))
+    }
+
     /// List user's gists
     pub async fn list_gists(&self) -> Result<Vec<Gist>, GistApiError> {
+        let mut all_gists = Vec::new();
+        scan_gist_pages(|page| self.list_gists_page(page), |gists| {
+            all_gists.extend(gists);
+            None
+        })
+        .await?;
+        Ok(all_gists)
+    }
+
+    /// Fetch one page (1-based) of the user's gists
+    async fn list_gists_page(&self, page: u32) -> Result<Vec<Gist>, GistApiError> {
         let response = self.client
-            .get(format!("{}/gists", GITHUB_API_BASE))
+            .get(format!("{}/gists", self.api_base))
             .header("Authorization", format!("Bearer {}", self.access_token))
             .header("Accept", "application/vnd.github+json")
             .header("User-Agent", "NekoTick")
             .header("X-GitHub-Api-Version", "2022-11-28")
+            .query(&[("per_page", "100"), ("page", &page.to_string())])
             .send()
             .await
-            .map_err(|e| GistApiError::NetworkError(e.to_string()))?;
+            .map_err(GistApiError::from_request)?;
 
         if response.status() == 401 {
             return Err(GistApiError::Unauthorized);
         }
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GistApiError::ApiError(error_text));
+            return Err(response_error(response).await);
         }
 
         response
@@ -138,28 +480,28 @@ impl GistClient {
             .map_err(|e| GistApiError::ParseError(e.to_string()))
     }
 
-    /// Find existing NekoTick sync gist
-    pub async fn find_nekotick_gist(&self) -> Result<Option<Gist>, GistApiError> {
-        let gists = self.list_gists().await?;
-        
-        // Find gist with our description and data.json file
-        Ok(gists.into_iter().find(|g| {
-            g.description.as_deref() == Some(NEKOTICK_GIST_DESCRIPTION) 
-                && g.files.contains_key(DATA_FILE_NAME)
-        }))
+    /// Find the sync gist for `profile`
+    pub async fn find_nekotick_gist(&self, profile: Option<&str>) -> Result<Option<Gist>, GistApiError> {
+        let description = gist_description(profile);
+
+        // Stop paging as soon as the sync gist turns up
+        scan_gist_pages(|page| self.list_gists_page(page), |gists| {
+            gists.into_iter().find(|g| is_nekotick_gist(g, &description))
+        })
+        .await
     }
 
     /// Get a specific gist by ID
     pub async fn get_gist(&self, gist_id: &str) -> Result<Gist, GistApiError> {
         let response = self.client
-            .get(format!("{}/gists/{}", GITHUB_API_BASE, gist_id))
+            .get(format!("{}/gists/{}", self.api_base, gist_id))
             .header("Authorization", format!("Bearer {}", self.access_token))
             .header("Accept", "application/vnd.github+json")
             .header("User-Agent", "NekoTick")
             .header("X-GitHub-Api-Version", "2022-11-28")
             .send()
             .await
-            .map_err(|e| GistApiError::NetworkError(e.to_string()))?;
+            .map_err(GistApiError::from_request)?;
 
         if response.status() == 401 {
             return Err(GistApiError::Unauthorized);
@@ -170,8 +512,7 @@ impl GistClient {
         }
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GistApiError::ApiError(error_text));
+            return Err(response_error(response).await);
         }
 
         response
@@ -180,24 +521,171 @@ impl GistClient {
             .map_err(|e| GistApiError::ParseError(e.to_string()))
     }
 
-    /// Create a new private gist
-    pub async fn create_gist(&self, content: &str) -> Result<Gist, GistApiError> {
-        let mut files = HashMap::new();
-        files.insert(
-            DATA_FILE_NAME.to_string(),
-            GistFileContent {
-                content: content.to_string(),
-            },
-        );
+    /// Get a gist unless it still matches `etag` from an earlier fetch
+    ///
+    /// Sends `If-None-Match`; GitHub answers 304 without a body (and without
+    /// charging the rate limit) when nothing changed.
+    pub async fn get_gist_if_changed(&self, gist_id: &str, etag: Option<&str>) -> Result<GistFetch, GistApiError> {
+        let mut request = self.client
+            .get(format!("{}/gists/{}", self.api_base, gist_id))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(etag) = etag {
+            request = request.header("If-None-Match", etag);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(GistFetch::NotModified);
+        }
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let gist = response
+            .json::<Gist>()
+            .await
+            .map_err(|e| GistApiError::ParseError(e.to_string()))?;
+        Ok(GistFetch::Fetched { gist: Box::new(gist), etag })
+    }
+
+    /// Re-fetch a gist and fail with `RemoteChanged` if another device updated it
+    /// after `expected_updated_at`
+    pub async fn ensure_unchanged(&self, gist_id: &str, expected_updated_at: &str) -> Result<(), GistApiError> {
+        let gist = self.get_gist(gist_id).await?;
+        check_remote_unchanged(expected_updated_at, &gist.updated_at)
+    }
+
+    /// Get a gist as it was at a historical revision
+    pub async fn get_gist_revision(&self, gist_id: &str, sha: &str) -> Result<Gist, GistApiError> {
+        let response = self.client
+            .get(format!("{}/gists/{}/{}", self.api_base, gist_id, sha))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Revision {} of gist {} not found", sha, gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        response
+            .json::<Gist>()
+            .await
+            .map_err(|e| GistApiError::ParseError(e.to_string()))
+    }
+
+    /// List a gist's revisions, newest first
+    pub async fn list_revisions(&self, gist_id: &str) -> Result<Vec<GistRevision>, GistApiError> {
+        let response = self.client
+            .get(format!("{}/gists/{}/commits?per_page=100", self.api_base, gist_id))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        let history = response
+            .json::<Vec<GistHistoryEntry>>()
+            .await
+            .map_err(|e| GistApiError::ParseError(e.to_string()))?;
+
+        Ok(history.into_iter().map(GistRevision::from).collect())
+    }
+
+    /// Get a gist's last modification time without downloading its content
+    ///
+    /// Issues a HEAD request and reads the `Last-Modified` header, returning
+    /// the Unix timestamp if the header is present and parseable.
+    pub async fn get_gist_modified_time(&self, gist_id: &str) -> Result<Option<i64>, GistApiError> {
+        let response = self.client
+            .head(format!("{}/gists/{}", self.api_base, gist_id))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
 
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if let Some(reset_at) = rate_limit_reset(response.status(), response.headers()) {
+            return Err(GistApiError::RateLimited { reset_at });
+        }
+
+        if !response.status().is_success() {
+            return Err(GistApiError::ApiError(format!("HEAD request failed: {}", response.status())));
+        }
+
+        Ok(response
+            .headers()
+            .get("last-modified")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+            .map(|dt| dt.timestamp()))
+    }
+
+    /// Create a new gist, secret unless `public` is set
+    pub async fn create_gist(&self, content: &str, notes: Option<&str>, profile: Option<&str>, public: bool) -> Result<Gist, GistApiError> {
         let request = GistRequest {
-            description: NEKOTICK_GIST_DESCRIPTION.to_string(),
-            public: false,
-            files,
+            description: gist_description(profile),
+            public,
+            files: gist_files(content, notes),
         };
 
         let response = self.client
-            .post(format!("{}/gists", GITHUB_API_BASE))
+            .post(format!("{}/gists", self.api_base))
             .header("Authorization", format!("Bearer {}", self.access_token))
             .header("Accept", "application/vnd.github+json")
             .header("User-Agent", "NekoTick")
@@ -205,15 +693,14 @@ impl GistClient {
             .json(&request)
             .send()
             .await
-            .map_err(|e| GistApiError::NetworkError(e.to_string()))?;
+            .map_err(GistApiError::from_request)?;
 
         if response.status() == 401 {
             return Err(GistApiError::Unauthorized);
         }
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GistApiError::ApiError(error_text));
+            return Err(response_error(response).await);
         }
 
         response
@@ -223,23 +710,15 @@ impl GistClient {
     }
 
     /// Update an existing gist
-    pub async fn update_gist(&self, gist_id: &str, content: &str) -> Result<Gist, GistApiError> {
-        let mut files = HashMap::new();
-        files.insert(
-            DATA_FILE_NAME.to_string(),
-            GistFileContent {
-                content: content.to_string(),
-            },
-        );
-
+    pub async fn update_gist(&self, gist_id: &str, content: &str, notes: Option<&str>, profile: Option<&str>, public: bool) -> Result<Gist, GistApiError> {
         let request = GistRequest {
-            description: NEKOTICK_GIST_DESCRIPTION.to_string(),
-            public: false,
-            files,
+            description: gist_description(profile),
+            public,
+            files: gist_files(content, notes),
         };
 
         let response = self.client
-            .patch(format!("{}/gists/{}", GITHUB_API_BASE, gist_id))
+            .patch(format!("{}/gists/{}", self.api_base, gist_id))
             .header("Authorization", format!("Bearer {}", self.access_token))
             .header("Accept", "application/vnd.github+json")
             .header("User-Agent", "NekoTick")
@@ -247,7 +726,80 @@ impl GistClient {
             .json(&request)
             .send()
             .await
-            .map_err(|e| GistApiError::NetworkError(e.to_string()))?;
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        response
+            .json::<Gist>()
+            .await
+            .map_err(|e| GistApiError::ParseError(e.to_string()))
+    }
+
+    /// Change an existing gist's visibility
+    ///
+    /// Fails with `ApiError` if GitHub accepts the request but leaves the
+    /// visibility unchanged, so the caller never reports a switch that
+    /// didn't happen.
+    pub async fn set_gist_public(&self, gist_id: &str, public: bool) -> Result<Gist, GistApiError> {
+        let response = self.client
+            .patch(format!("{}/gists/{}", self.api_base, gist_id))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .json(&serde_json::json!({ "public": public }))
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        let gist = response
+            .json::<Gist>()
+            .await
+            .map_err(|e| GistApiError::ParseError(e.to_string()))?;
+        if gist.public != public {
+            return Err(GistApiError::ApiError(format!(
+                "GitHub kept gist {} {}",
+                gist_id,
+                if gist.public { "public" } else { "secret" }
+            )));
+        }
+        Ok(gist)
+    }
+
+    /// Delete a gist
+    pub async fn delete_gist(&self, gist_id: &str) -> Result<(), GistApiError> {
+        let response = self.client
+            .delete(format!("{}/gists/{}", self.api_base, gist_id))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
 
         if response.status() == 401 {
             return Err(GistApiError::Unauthorized);
@@ -258,8 +810,34 @@ impl GistClient {
         }
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GistApiError::ApiError(error_text));
+            return Err(response_error(response).await);
+        }
+
+        Ok(())
+    }
+
+    /// Fork someone else's gist into the authenticated account
+    pub async fn fork_gist(&self, gist_id: &str) -> Result<Gist, GistApiError> {
+        let response = self.client
+            .post(format!("{}/gists/{}/forks", self.api_base, gist_id))
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
         }
 
         response
@@ -268,13 +846,83 @@ impl GistClient {
             .map_err(|e| GistApiError::ParseError(e.to_string()))
     }
 
+    /// Star a gist
+    pub async fn star_gist(&self, gist_id: &str) -> Result<(), GistApiError> {
+        self.set_starred(gist_id, true).await
+    }
+
+    /// Remove the star from a gist
+    pub async fn unstar_gist(&self, gist_id: &str) -> Result<(), GistApiError> {
+        self.set_starred(gist_id, false).await
+    }
+
+    async fn set_starred(&self, gist_id: &str, starred: bool) -> Result<(), GistApiError> {
+        let url = format!("{}/gists/{}/star", self.api_base, gist_id);
+        let request = if starred {
+            // GitHub wants an explicit empty body on this PUT
+            self.client.put(url).header("Content-Length", "0")
+        } else {
+            self.client.delete(url)
+        };
+        let response = request
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "NekoTick")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .send()
+            .await
+            .map_err(GistApiError::from_request)?;
+
+        if response.status() == 401 {
+            return Err(GistApiError::Unauthorized);
+        }
+
+        if response.status() == 404 {
+            return Err(GistApiError::NotFound(format!("Gist {} not found", gist_id)));
+        }
+
+        if !response.status().is_success() {
+            return Err(response_error(response).await);
+        }
+
+        Ok(())
+    }
+
     /// Download gist content (data.json)
     pub async fn download_data(&self, gist_id: &str) -> Result<String, GistApiError> {
         let gist = self.get_gist(gist_id).await?;
-        
+        self.download_gist_data(&gist).await
+    }
+
+    /// Download data.json as it was at a historical revision
+    pub async fn download_revision(&self, gist_id: &str, sha: &str) -> Result<String, GistApiError> {
+        let gist = self.get_gist_revision(gist_id, sha).await?;
+        self.download_gist_data(&gist).await
+    }
+
+    /// Download data.json from an already fetched gist
+    ///
+    /// Compressed payloads (see `compression`) are inflated transparently.
+    pub async fn download_gist_data(&self, gist: &Gist) -> Result<String, GistApiError> {
         let file = gist.files.get(DATA_FILE_NAME)
             .ok_or_else(|| GistApiError::NotFound("data.json not found in gist".to_string()))?;
+        let payload = self.download_gist_file(DATA_FILE_NAME, file).await?;
+        compression::decode_payload(&payload).map_err(GistApiError::ParseError)
+    }
+
+    /// Download nekotick.md from an already fetched gist
+    ///
+    /// Gists created before notes were synced only hold data.json, so a
+    /// missing file is `None` rather than an error.
+    pub async fn download_gist_notes(&self, gist: &Gist) -> Result<Option<String>, GistApiError> {
+        match gist.files.get(NOTES_FILE_NAME) {
+            Some(file) => self.download_gist_file(NOTES_FILE_NAME, file).await.map(Some),
+            None => Ok(None),
+        }
+    }
 
+    /// Download a gist file exactly as stored
+    async fn download_gist_file(&self, name: &str, file: &GistFile) -> Result<String, GistApiError> {
         // If content is included in response, use it
         if let Some(content) = &file.content {
             return Ok(content.clone());
@@ -282,7 +930,7 @@ impl GistClient {
 
         // Otherwise, fetch from raw_url
         let raw_url = file.raw_url.as_ref()
-            .ok_or_else(|| GistApiError::NotFound("No raw_url for data.json".to_string()))?;
+            .ok_or_else(|| GistApiError::NotFound(format!("No raw_url for {}", name)))?;
 
         let response = self.client
             .get(raw_url)
@@ -290,11 +938,10 @@ impl GistClient {
             .header("User-Agent", "NekoTick")
             .send()
             .await
-            .map_err(|e| GistApiError::NetworkError(e.to_string()))?;
+            .map_err(GistApiError::from_request)?;
 
         if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(GistApiError::ApiError(error_text));
+            return Err(response_error(response).await);
         }
 
         response
@@ -303,11 +950,274 @@ impl GistClient {
             .map_err(|e| GistApiError::ParseError(e.to_string()))
     }
 
-    /// Upload data to gist (create or update)
-    pub async fn upload_data(&self, gist_id: Option<&str>, content: &str) -> Result<Gist, GistApiError> {
+    /// Upload data to gist (create or update), gzip-compressing it when `compress` is set
+    ///
+    /// `notes` (nekotick.md) is stored uncompressed next to data.json. A new
+    /// gist is described after `profile`; see `gist_description`, and is
+    /// public only when `public` is set. Fails with `TooLarge`, before any
+    /// request is made, when the files exceed the client's size limit.
+    pub async fn upload_data(
+        &self,
+        gist_id: Option<&str>,
+        content: &str,
+        notes: Option<&str>,
+        compress: bool,
+        profile: Option<&str>,
+        public: bool,
+    ) -> Result<Gist, GistApiError> {
+        let payload = if compress {
+            compression::encode_payload(content).map_err(GistApiError::ParseError)?
+        } else {
+            content.to_string()
+        };
+        check_gist_size(payload.len() + notes.map_or(0, str::len), self.size_limit)?;
+
         match gist_id {
-            Some(id) => self.update_gist(id, content).await,
-            None => self.create_gist(content).await,
+            Some(id) => self.update_gist(id, &payload, notes, profile, public).await,
+            None => self.create_gist(&payload, notes, profile, public).await,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gist_owned_by(login: Option<&str>) -> Gist {
+        Gist {
+            id: "abc123".to_string(),
+            owner: login.map(|l| GistOwner { login: l.to_string(), id: 1 }),
+            description: Some(NEKOTICK_GIST_DESCRIPTION.to_string()),
+            public: false,
+            files: HashMap::new(),
+            created_at: "2024-01-01T00:00:00Z".to_string(),
+            updated_at: "2024-01-01T00:00:00Z".to_string(),
+            html_url: None,
+        }
+    }
+
+    fn email(address: &str, primary: bool, verified: bool) -> GitHubEmail {
+        GitHubEmail {
+            email: address.to_string(),
+            primary,
+            verified,
+            visibility: None,
+        }
+    }
+
+    #[test]
+    fn test_select_primary_email() {
+        let emails = vec![
+            email("other@example.com", false, true),
+            email("main@example.com", true, true),
+        ];
+        assert_eq!(select_primary_email(&emails).as_deref(), Some("main@example.com"));
+    }
+
+    #[test]
+    fn test_unverified_primary_email_ignored() {
+        let emails = vec![
+            email("main@example.com", true, false),
+            email("other@example.com", false, true),
+        ];
+        assert_eq!(select_primary_email(&emails), None);
+        assert_eq!(select_primary_email(&[]), None);
+    }
+
+    #[test]
+    fn test_verify_owner_matches() {
+        assert!(gist_owned_by(Some("Neko")).verify_owner("neko").is_ok());
+    }
+
+    #[test]
+    fn test_verify_owner_mismatch() {
+        match gist_owned_by(Some("stranger")).verify_owner("neko") {
+            Err(GistApiError::OwnershipMismatch { expected, actual }) => {
+                assert_eq!(expected, "neko");
+                assert_eq!(actual, "stranger");
+            }
+            other => panic!("expected ownership mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_owner_anonymous_gist() {
+        assert!(gist_owned_by(None).verify_owner("neko").is_err());
+    }
+
+    #[test]
+    fn test_gist_files_include_notes_when_present() {
+        let files = gist_files("{}", Some("# notes"));
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[NOTES_FILE_NAME].content, "# notes");
+
+        let files = gist_files("{}", None);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[DATA_FILE_NAME].content, "{}");
+    }
+
+    #[test]
+    fn test_revisions_from_commits_response() {
+        let body = r#"[
+            {"version": "57a7f021a713b1c5a6a199b54cc514735d2d462f", "committed_at": "2024-03-02T10:00:00Z",
+             "change_status": {"total": 5, "additions": 3, "deletions": 2}, "url": "https://api.github.com/gists/abc/57a7"},
+            {"version": "0b3f4d", "committed_at": "2024-03-01T09:00:00Z"}
+        ]"#;
+        let history: Vec<GistHistoryEntry> = serde_json::from_str(body).unwrap();
+        let revisions: Vec<GistRevision> = history.into_iter().map(GistRevision::from).collect();
+
+        assert_eq!(revisions.len(), 2);
+        assert_eq!(revisions[0].sha, "57a7f021a713b1c5a6a199b54cc514735d2d462f");
+        assert_eq!((revisions[0].changes, revisions[0].additions, revisions[0].deletions), (5, 3, 2));
+        assert_eq!(revisions[1].committed_at, "2024-03-01T09:00:00Z");
+        assert_eq!(revisions[1].changes, 0);
+    }
+
+    fn rate_limit_headers(remaining: &str, reset: &str) -> reqwest::header::HeaderMap {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", remaining.parse().unwrap());
+        headers.insert("x-ratelimit-reset", reset.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_rate_limit_reset_parsed() {
+        let headers = rate_limit_headers("0", "1700000000");
+        assert_eq!(rate_limit_reset(reqwest::StatusCode::FORBIDDEN, &headers), Some(1700000000));
+        assert_eq!(rate_limit_reset(reqwest::StatusCode::TOO_MANY_REQUESTS, &headers), Some(1700000000));
+    }
+
+    #[test]
+    fn test_forbidden_with_quota_left_is_not_rate_limited() {
+        let headers = rate_limit_headers("12", "1700000000");
+        assert_eq!(rate_limit_reset(reqwest::StatusCode::FORBIDDEN, &headers), None);
+        let exhausted = rate_limit_headers("0", "1700000000");
+        assert_eq!(rate_limit_reset(reqwest::StatusCode::NOT_FOUND, &exhausted), None);
+    }
+
+    #[test]
+    fn test_parse_gist_id() {
+        let id = "aa5a315d61ae9438b18d";
+        assert_eq!(parse_gist_id(&format!("https://gist.github.com/octocat/{}", id)).as_deref(), Some(id));
+        assert_eq!(parse_gist_id(&format!("https://gist.github.com/{}#file-data-json", id)).as_deref(), Some(id));
+        assert_eq!(parse_gist_id(&format!("https://gist.github.com/{}.git", id)).as_deref(), Some(id));
+        assert_eq!(parse_gist_id(&format!(" {} ", id)).as_deref(), Some(id));
+        assert_eq!(parse_gist_id("https://gist.github.com/octocat"), None);
+        assert_eq!(parse_gist_id("file:///etc/aa5a315d"), None);
+    }
+
+    #[test]
+    fn test_gist_size_limit() {
+        assert!(check_gist_size(DEFAULT_GIST_SIZE_LIMIT, DEFAULT_GIST_SIZE_LIMIT).is_ok());
+        let err = check_gist_size(11, 10).unwrap_err();
+        assert!(matches!(err, GistApiError::TooLarge { size: 11, limit: 10 }));
+        assert!(err.to_string().contains("repository sync"));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upload_fails_before_request() {
+        let client = GistClient::new("token".to_string()).with_size_limit(4);
+        let err = client.upload_data(None, "{\"tasks\":[]}", None, false, None, false).await.unwrap_err();
+        assert!(matches!(err, GistApiError::TooLarge { limit: 4, .. }));
+    }
+
+    #[test]
+    fn test_remote_unchanged() {
+        assert!(check_remote_unchanged("2024-01-01T00:00:00Z", "2024-01-01T00:00:00Z").is_ok());
+    }
+
+    #[test]
+    fn test_remote_changed_since_last_sync() {
+        let err = check_remote_unchanged("2024-01-01T00:00:00Z", "2024-01-02T08:30:00Z").unwrap_err();
+        assert!(matches!(
+            err,
+            GistApiError::RemoteChanged { ref expected, ref actual }
+                if expected == "2024-01-01T00:00:00Z" && actual == "2024-01-02T08:30:00Z"
+        ));
+    }
+
+    fn listed_gist(id: &str, description: &str) -> Gist {
+        let mut gist = gist_owned_by(Some("neko"));
+        gist.id = id.to_string();
+        gist.description = Some(description.to_string());
+        gist.files.insert(
+            DATA_FILE_NAME.to_string(),
+            GistFile { filename: Some(DATA_FILE_NAME.to_string()), content: None, raw_url: None, size: None },
+        );
+        gist
+    }
+
+    /// Two full pages followed by an empty one, recording which pages were requested
+    fn two_pages(requested: &std::cell::RefCell<Vec<u32>>, page: u32) -> std::future::Ready<Result<Vec<Gist>, GistApiError>> {
+        requested.borrow_mut().push(page);
+        let gists = match page {
+            1 => vec![listed_gist("a", "notes"), listed_gist("b", "dotfiles")],
+            2 => vec![listed_gist("c", "scratch"), listed_gist("sync", NEKOTICK_GIST_DESCRIPTION)],
+            _ => Vec::new(),
+        };
+        std::future::ready(Ok(gists))
+    }
+
+    #[test]
+    fn test_gist_description_per_profile() {
+        assert_eq!(gist_description(None), "NekoTick Sync Data");
+        assert_eq!(gist_description(Some("  ")), "NekoTick Sync Data");
+        assert_eq!(gist_description(Some(" work ")), "NekoTick Sync Data (work)");
+    }
+
+    #[test]
+    fn test_profiles_do_not_match_each_other() {
+        let default = listed_gist("default", NEKOTICK_GIST_DESCRIPTION);
+        let work = listed_gist("work", &gist_description(Some("work")));
+
+        assert!(is_nekotick_gist(&default, &gist_description(None)));
+        assert!(!is_nekotick_gist(&work, &gist_description(None)));
+        assert!(is_nekotick_gist(&work, &gist_description(Some("work"))));
+        assert!(!is_nekotick_gist(&default, &gist_description(Some("work"))));
+    }
+
+    #[tokio::test]
+    async fn test_find_gist_on_second_page() {
+        let requested = std::cell::RefCell::new(Vec::new());
+        let found = scan_gist_pages(|page| two_pages(&requested, page), |gists| gists.into_iter().find(|g| is_nekotick_gist(g, NEKOTICK_GIST_DESCRIPTION)))
+            .await
+            .unwrap();
+
+        assert_eq!(found.map(|g| g.id), Some("sync".to_string()));
+        // Short-circuits without requesting the empty third page
+        assert_eq!(*requested.borrow(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_list_collects_pages_until_empty() {
+        let requested = std::cell::RefCell::new(Vec::new());
+        let mut all = Vec::new();
+        let found = scan_gist_pages(|page| two_pages(&requested, page), |gists| {
+            all.extend(gists);
+            None
+        })
+        .await
+        .unwrap();
+
+        assert!(found.is_none());
+        assert_eq!(all.len(), 4);
+        assert_eq!(*requested.borrow(), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_page_scan_stops_at_cap() {
+        let requested = std::cell::RefCell::new(0);
+        let found = scan_gist_pages(
+            |_| {
+                *requested.borrow_mut() += 1;
+                std::future::ready(Ok(vec![listed_gist("other", "notes")]))
+            },
+            |gists| gists.into_iter().find(|g| is_nekotick_gist(g, NEKOTICK_GIST_DESCRIPTION)),
+        )
+        .await
+        .unwrap();
+
+        assert!(found.is_none());
+        assert_eq!(*requested.borrow(), MAX_GIST_PAGES);
+    }
+}
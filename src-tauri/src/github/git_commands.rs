@@ -1,25 +1,95 @@
 //! Tauri commands for git operations
 
-use super::git_ops::{self, CommitInfo, FileStatus};
-use super::commands::{get_stored_github_token, get_stored_github_username};
-use tauri::command;
+use super::git_ops::{self, AheadBehind, BranchInfo, CommitInfo, FileStatus, GitError, GitProgress, ProgressThrottle, SearchHit};
+use super::commands::{get_github_primary_email, get_stored_github_username, get_valid_github_token};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{command, Emitter};
+use tokio::sync::Semaphore;
+
+/// Minimum time between progress events for one operation
+const PROGRESS_EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Progress event payload for `git-clone-progress` / `git-pull-progress`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitProgressEvent {
+    /// `owner/repo`
+    pub repo: String,
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+}
+
+/// Build a throttled progress callback that emits `event` for `owner/repo`
+fn progress_emitter(app: tauri::AppHandle, event: &'static str, owner: &str, repo: &str) -> impl FnMut(GitProgress) {
+    let key = format!("{}/{}", owner, repo);
+    let mut throttle = ProgressThrottle::new(PROGRESS_EVENT_INTERVAL);
+    move |progress: GitProgress| {
+        if throttle.should_emit(&progress, Instant::now()) {
+            let _ = app.emit(event, GitProgressEvent {
+                repo: key.clone(),
+                received_objects: progress.received_objects,
+                total_objects: progress.total_objects,
+                received_bytes: progress.received_bytes,
+            });
+        }
+    }
+}
+
+/// Maximum number of repositories pulled at once by `pull_stale_repos`
+const MAX_CONCURRENT_PULLS: usize = 4;
+
+/// Result of an automatic pull for one repository
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoPullOutcome {
+    pub owner: String,
+    pub repo: String,
+    pub status: String, // "pulled", "fresh", "skipped", "failed"
+    pub reason: Option<String>,
+}
+
+/// Run a blocking git network operation, giving up after `timeout_secs`
+///
+/// libgit2 calls cannot be cancelled mid-flight, so on timeout the blocking
+/// thread may linger until the transport itself gives up; its result is
+/// discarded.
+pub async fn run_with_timeout<T, F>(timeout_secs: Option<u64>, op: F) -> Result<T, GitError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, GitError> + Send + 'static,
+{
+    let secs = timeout_secs.unwrap_or(git_ops::DEFAULT_NETWORK_TIMEOUT_SECS);
+    match tokio::time::timeout(Duration::from_secs(secs), tokio::task::spawn_blocking(op)).await {
+        Ok(joined) => joined.map_err(|e| GitError::Io(std::io::Error::other(e.to_string())))?,
+        Err(_) => Err(GitError::Timeout(secs)),
+    }
+}
 
 /// Clone a repository to local storage
+///
+/// Emits throttled `git-clone-progress` events keyed by `owner/repo`. With
+/// `depth`, a shallow clone of that many commits is made; its log only
+/// reaches back that far.
 #[command]
 pub async fn clone_github_repo(
     app: tauri::AppHandle,
     owner: String,
     repo: String,
+    timeout_secs: Option<u64>,
+    depth: Option<u32>,
 ) -> Result<String, String> {
-    let token = get_stored_github_token(&app).ok_or("Not authenticated with GitHub")?;
+    let token = get_valid_github_token(&app).await.ok_or("Not authenticated with GitHub")?;
     
-    tokio::task::spawn_blocking(move || {
-        git_ops::clone_repo(&owner, &repo, &token)
+    let mut on_progress = progress_emitter(app.clone(), "git-clone-progress", &owner, &repo);
+    run_with_timeout(timeout_secs, move || {
+        git_ops::clone_repo_with_progress(&owner, &repo, &token, depth, Some(&mut on_progress))
             .map(|path| path.display().to_string())
-            .map_err(|e| e.to_string())
     })
     .await
-    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
 }
 
 /// Check if a repository is cloned locally
@@ -45,51 +115,155 @@ pub async fn get_repo_local_path(owner: String, repo: String) -> Result<String,
 }
 
 /// Pull latest changes from remote
+///
+/// Emits throttled `git-pull-progress` events keyed by `owner/repo`. With
+/// `autostash`, uncommitted changes are stashed around the pull.
 #[command]
 pub async fn pull_github_repo(
     app: tauri::AppHandle,
     owner: String,
     repo: String,
+    timeout_secs: Option<u64>,
+    autostash: Option<bool>,
 ) -> Result<(), String> {
-    let token = get_stored_github_token(&app).ok_or("Not authenticated with GitHub")?;
+    let token = get_valid_github_token(&app).await.ok_or("Not authenticated with GitHub")?;
+    let autostash = autostash.unwrap_or(false);
+    let (username, email) = commit_author(&app).await;
     
+    let mut on_progress = progress_emitter(app.clone(), "git-pull-progress", &owner, &repo);
+    run_with_timeout(timeout_secs, move || {
+        git_ops::pull_repo_with_progress(&owner, &repo, &token, autostash, Some((&username, &email)), Some(&mut on_progress))
+    })
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Fetch from remote without merging
+///
+/// Only origin's remote-tracking branches change, so this is safe to run in
+/// the background; the result says how far the current branch is ahead of
+/// and behind its upstream.
+#[command]
+pub async fn fetch_github_repo(
+    app: tauri::AppHandle,
+    owner: String,
+    repo: String,
+    timeout_secs: Option<u64>,
+) -> Result<AheadBehind, String> {
+    let token = get_valid_github_token(&app).await.ok_or("Not authenticated with GitHub")?;
+
+    run_with_timeout(timeout_secs, move || git_ops::fetch_repo(&owner, &repo, &token))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Commits ahead of and behind the upstream as of the last fetch (no network access)
+#[command]
+pub async fn get_repo_ahead_behind(owner: String, repo: String) -> Result<AheadBehind, String> {
     tokio::task::spawn_blocking(move || {
-        git_ops::pull_repo(&owner, &repo, &token).map_err(|e| e.to_string())
+        git_ops::get_ahead_behind(&owner, &repo).map_err(|e| e.to_string())
     })
     .await
     .map_err(|e| e.to_string())?
 }
 
+/// Author name and email for commits made on the user's behalf, from the
+/// stored GitHub credentials
+async fn commit_author(app: &tauri::AppHandle) -> (String, String) {
+    let username = get_stored_github_username(app).unwrap_or_else(|| "NekoTick User".to_string());
+    let email = match get_github_primary_email(app.clone()).await {
+        Ok(email) => email,
+        Err(_) => format!("{}@users.noreply.github.com", username),
+    };
+    (username, email)
+}
+
 /// Push local changes to remote
 #[command]
 pub async fn push_github_repo(
     app: tauri::AppHandle,
     owner: String,
     repo: String,
+    timeout_secs: Option<u64>,
 ) -> Result<(), String> {
-    let token = get_stored_github_token(&app).ok_or("Not authenticated with GitHub")?;
+    let token = get_valid_github_token(&app).await.ok_or("Not authenticated with GitHub")?;
     
-    tokio::task::spawn_blocking(move || {
-        git_ops::push_repo(&owner, &repo, &token).map_err(|e| e.to_string())
-    })
-    .await
-    .map_err(|e| e.to_string())?
+    run_with_timeout(timeout_secs, move || git_ops::push_repo(&owner, &repo, &token))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Pull every cloned repository not pulled within `max_age_secs`
+///
+/// Repositories with uncommitted changes are skipped rather than pulled.
+#[command]
+pub async fn pull_stale_repos(
+    app: tauri::AppHandle,
+    max_age_secs: u64,
+    timeout_secs: Option<u64>,
+) -> Result<Vec<RepoPullOutcome>, String> {
+    let token = get_valid_github_token(&app).await.ok_or("Not authenticated with GitHub")?;
+    let repos = tokio::task::spawn_blocking(git_ops::list_local_repos)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_PULLS));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (index, (owner, repo)) in repos.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let token = token.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let (o, r) = (owner.clone(), repo.clone());
+            let result = run_with_timeout(timeout_secs, move || {
+                let repository = git_ops::open_repo(&o, &r)?;
+                let now = chrono::Utc::now().timestamp();
+                match git_ops::auto_pull_decision(&repository, now, max_age_secs)? {
+                    git_ops::AutoPullDecision::Pull => {
+                        git_ops::pull_repo(&o, &r, &token)?;
+                        Ok(git_ops::AutoPullDecision::Pull)
+                    }
+                    decision => Ok(decision),
+                }
+            })
+            .await;
+
+            let (status, reason) = match result {
+                Ok(git_ops::AutoPullDecision::Pull) => ("pulled", None),
+                Ok(git_ops::AutoPullDecision::Fresh) => ("fresh", None),
+                Ok(git_ops::AutoPullDecision::Dirty) => ("skipped", Some("Uncommitted local changes".to_string())),
+                Err(e) => ("failed", Some(e.to_string())),
+            };
+            (index, RepoPullOutcome { owner, repo, status: status.to_string(), reason })
+        });
+    }
+
+    let mut outcomes = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        outcomes.push(joined.map_err(|e| e.to_string())?);
+    }
+    outcomes.sort_by_key(|(index, _)| *index);
+    Ok(outcomes.into_iter().map(|(_, outcome)| outcome).collect())
 }
 
 /// Commit all changes
+///
+/// `add_tool_trailer` marks the commit as app-made (NekoTick committer and
+/// a `NekoTick-Version` trailer) while keeping the user as author.
 #[command]
 pub async fn commit_repo_changes(
     app: tauri::AppHandle,
     owner: String,
     repo: String,
     message: String,
+    add_tool_trailer: Option<bool>,
 ) -> Result<String, String> {
-    // Get author info from stored credentials
-    let username = get_stored_github_username(&app).unwrap_or_else(|| "NekoTick User".to_string());
-    let email = format!("{}@users.noreply.github.com", username);
+    let add_tool_trailer = add_tool_trailer.unwrap_or(false);
+    let (username, email) = commit_author(&app).await;
     
     tokio::task::spawn_blocking(move || {
-        git_ops::commit_all(&owner, &repo, &message, &username, &email)
+        git_ops::commit_all(&owner, &repo, &message, &username, &email, add_tool_trailer)
             .map_err(|e| e.to_string())
     })
     .await
@@ -97,10 +271,32 @@ pub async fn commit_repo_changes(
 }
 
 /// Get repository status (changed files)
+///
+/// Ignored files are only listed when `show_ignored` is set.
 #[command]
-pub async fn get_repo_status(owner: String, repo: String) -> Result<Vec<FileStatus>, String> {
+pub async fn get_repo_status(owner: String, repo: String, show_ignored: Option<bool>) -> Result<Vec<FileStatus>, String> {
     tokio::task::spawn_blocking(move || {
-        git_ops::get_status(&owner, &repo).map_err(|e| e.to_string())
+        git_ops::get_status(&owner, &repo, show_ignored.unwrap_or(false)).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// List local and remote-tracking branches
+#[command]
+pub async fn get_repo_branches(owner: String, repo: String) -> Result<Vec<BranchInfo>, String> {
+    tokio::task::spawn_blocking(move || {
+        git_ops::list_branches(&owner, &repo).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Check out a branch; fails if the worktree has uncommitted changes
+#[command]
+pub async fn checkout_repo_branch(owner: String, repo: String, branch: String) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        git_ops::checkout_branch(&owner, &repo, &branch).map_err(|e| e.to_string())
     })
     .await
     .map_err(|e| e.to_string())?
@@ -136,6 +332,39 @@ pub async fn get_file_diff(
     .map_err(|e| e.to_string())?
 }
 
+/// Get the patch between two commits, optionally for a single file
+///
+/// Pass an empty `from_sha` to diff a root commit against the empty tree.
+#[command]
+pub async fn get_repo_commit_diff(
+    owner: String,
+    repo: String,
+    from_sha: String,
+    to_sha: String,
+    file_path: Option<String>,
+) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        git_ops::get_commit_diff(&owner, &repo, &from_sha, &to_sha, file_path.as_deref())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Get the most recent commit that changed a file
+#[command]
+pub async fn get_repo_file_last_commit(
+    owner: String,
+    repo: String,
+    path: String,
+) -> Result<Option<CommitInfo>, String> {
+    tokio::task::spawn_blocking(move || {
+        git_ops::get_file_last_commit(&owner, &repo, &path).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 /// Delete a local repository
 #[command]
 pub async fn delete_local_repo(owner: String, repo: String) -> Result<(), String> {
@@ -155,3 +384,40 @@ pub async fn list_local_repos() -> Result<Vec<(String, String)>, String> {
     .await
     .map_err(|e| e.to_string())?
 }
+
+/// Search the tracked text files of all locally cloned repositories
+#[command]
+pub async fn search_local_repos(query: String, case_sensitive: bool) -> Result<Vec<SearchHit>, String> {
+    tokio::task::spawn_blocking(move || {
+        git_ops::search_local_repos(&query, case_sensitive).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slow_operation_times_out() {
+        let result = run_with_timeout(Some(0), || {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(GitError::Timeout(0))));
+    }
+
+    #[tokio::test]
+    async fn test_fast_operation_returns_result() {
+        let result = run_with_timeout(Some(5), || Ok(42)).await;
+        assert_eq!(result.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn test_operation_error_passes_through() {
+        let result: Result<(), _> = run_with_timeout(Some(5), || Err(GitError::NoToken)).await;
+        assert!(matches!(result, Err(GitError::NoToken)));
+    }
+}
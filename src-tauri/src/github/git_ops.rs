@@ -4,13 +4,22 @@
 //! for local repository management.
 
 use git2::{
-    Cred, CredentialType, FetchOptions, PushOptions, RemoteCallbacks,
-    Repository, Signature, StatusOptions, DiffOptions,
+    BranchType, Cred, CredentialType, ErrorCode, FetchOptions, PushOptions, RemoteCallbacks,
+    Repository, Signature, StashFlags, StatusOptions, DiffOptions,
     build::RepoBuilder,
 };
+use crate::github::server;
 use std::path::PathBuf;
+use std::sync::Once;
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Default limit for a clone/pull/push before it is reported as timed out
+pub const DEFAULT_NETWORK_TIMEOUT_SECS: u64 = 60;
+
+/// libgit2 connect timeout; stalled handshakes fail well before the overall limit
+const TRANSPORT_CONNECT_TIMEOUT_MS: i32 = 15_000;
+
 #[derive(Error, Debug)]
 pub enum GitError {
     #[error("Git error: {0}")]
@@ -23,10 +32,48 @@ pub enum GitError {
     NoToken,
     #[error("Invalid repository URL")]
     InvalidUrl,
+    #[error("Git operation timed out after {0}s")]
+    Timeout(u64),
+    #[error("HEAD is detached; check out a branch first")]
+    DetachedHead,
+    #[error("Working tree has uncommitted changes; commit or discard them before switching branches")]
+    DirtyWorktree,
+    #[error("Branch not found: {0}")]
+    BranchNotFound(String),
+    #[error("Your local changes conflict with the pulled changes; they were kept in the stash")]
+    StashConflict,
+    #[error("Pulled changes conflict with local commits in: {}", .0.join(", "))]
+    MergeConflict(Vec<String>),
+}
+
+/// Configure libgit2's transport connect and read/write timeouts
+///
+/// These are process-wide libgit2 options, so they are set once before the
+/// first network operation rather than per call.
+pub fn configure_transport_timeouts() {
+    static CONFIGURE: Once = Once::new();
+    CONFIGURE.call_once(|| {
+        let io_timeout_ms = (DEFAULT_NETWORK_TIMEOUT_SECS * 1000) as i32;
+        // SAFETY: runs once, before any transport is created by this module
+        unsafe {
+            if let Err(e) = git2::opts::set_server_connect_timeout_in_milliseconds(TRANSPORT_CONNECT_TIMEOUT_MS) {
+                log::warn!("Failed to set git connect timeout: {}", e);
+            }
+            if let Err(e) = git2::opts::set_server_timeout_in_milliseconds(io_timeout_ms) {
+                log::warn!("Failed to set git transport timeout: {}", e);
+            }
+        }
+    });
 }
 
 /// Get the base directory for cloned repositories
+///
+/// In portable mode repositories live under the portable data directory.
 pub fn get_repos_base_dir() -> Result<PathBuf, GitError> {
+    if let Some(dir) = crate::paths::portable_repos_dir() {
+        return Ok(dir);
+    }
+
     let base = dirs::data_local_dir()
         .or_else(|| dirs::home_dir())
         .ok_or_else(|| GitError::Io(std::io::Error::new(
@@ -61,7 +108,7 @@ pub fn is_repo_cloned(owner: &str, repo: &str) -> Result<bool, GitError> {
         // Migrate to new format
         if let Err(e) = std::fs::rename(&old_path, &new_path) {
             // If rename fails, still return true since the repo exists
-            eprintln!("Failed to migrate repo directory: {}", e);
+            log::warn!("Failed to migrate repo directory: {}", e);
             return Ok(true);
         }
         return Ok(true);
@@ -70,8 +117,53 @@ pub fn is_repo_cloned(owner: &str, repo: &str) -> Result<bool, GitError> {
     Ok(false)
 }
 
-/// Create git credentials callback using GitHub token
-fn create_callbacks(token: &str) -> RemoteCallbacks<'_> {
+/// Transfer progress of a clone or fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GitProgress {
+    pub received_objects: usize,
+    pub total_objects: usize,
+    pub received_bytes: usize,
+}
+
+impl GitProgress {
+    pub fn is_complete(&self) -> bool {
+        self.total_objects > 0 && self.received_objects >= self.total_objects
+    }
+}
+
+/// Receives transfer progress from a network operation
+pub type ProgressCallback<'a> = &'a mut dyn FnMut(GitProgress);
+
+/// Limits how often progress is reported; the first and final updates always pass
+pub struct ProgressThrottle {
+    interval: Duration,
+    last_emit: Option<Instant>,
+}
+
+impl ProgressThrottle {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last_emit: None }
+    }
+
+    /// Whether an update at `now` should be reported
+    pub fn should_emit(&mut self, progress: &GitProgress, now: Instant) -> bool {
+        let due = match self.last_emit {
+            Some(last) => now.duration_since(last) >= self.interval,
+            None => true,
+        };
+        if due || progress.is_complete() {
+            self.last_emit = Some(now);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Create git credentials callback using GitHub token, optionally reporting transfer progress
+fn create_callbacks<'a>(token: &str, on_progress: Option<ProgressCallback<'a>>) -> RemoteCallbacks<'a> {
+    configure_transport_timeouts();
+
     let mut callbacks = RemoteCallbacks::new();
     let token = token.to_string();
     
@@ -86,6 +178,17 @@ fn create_callbacks(token: &str) -> RemoteCallbacks<'_> {
             Err(git2::Error::from_str("Unsupported credential type"))
         }
     });
+
+    if let Some(on_progress) = on_progress {
+        callbacks.transfer_progress(move |stats| {
+            on_progress(GitProgress {
+                received_objects: stats.received_objects(),
+                total_objects: stats.total_objects(),
+                received_bytes: stats.received_bytes(),
+            });
+            true
+        });
+    }
     
     callbacks
 }
@@ -95,8 +198,23 @@ pub fn clone_repo(
     owner: &str,
     repo: &str,
     token: &str,
+    depth: Option<u32>,
 ) -> Result<PathBuf, GitError> {
-    let url = format!("https://github.com/{}/{}.git", owner, repo);
+    clone_repo_with_progress(owner, repo, token, depth, None)
+}
+
+/// Clone a repository from GitHub, reporting transfer progress
+///
+/// With `depth`, only that many commits of history are fetched. `get_log`
+/// on a shallow clone returns just the fetched commits.
+pub fn clone_repo_with_progress(
+    owner: &str,
+    repo: &str,
+    token: &str,
+    depth: Option<u32>,
+    on_progress: Option<ProgressCallback<'_>>,
+) -> Result<PathBuf, GitError> {
+    let url = server::current().clone_url(owner, repo);
     let local_path = get_repo_local_path(owner, repo)?;
     
     // Create parent directories if needed
@@ -114,9 +232,12 @@ pub fn clone_repo(
         }
     }
     
-    let callbacks = create_callbacks(token);
+    let callbacks = create_callbacks(token, on_progress);
     let mut fetch_options = FetchOptions::new();
     fetch_options.remote_callbacks(callbacks);
+    if let Some(depth) = depth.filter(|d| *d > 0) {
+        fetch_options.depth(i32::try_from(depth).unwrap_or(i32::MAX));
+    }
     
     RepoBuilder::new()
         .fetch_options(fetch_options)
@@ -135,17 +256,221 @@ pub fn open_repo(owner: &str, repo: &str) -> Result<Repository, GitError> {
     Ok(Repository::open(&path)?)
 }
 
+/// Per-repo metadata kept in `.git/` so it never shows up as a worktree change
+const REPO_META_FILE: &str = "nekotick_meta.json";
+
+/// NekoTick bookkeeping for a cloned repository
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoMeta {
+    /// Unix seconds of the last successful pull
+    pub last_pull_time: Option<i64>,
+}
+
+/// Load a repository's metadata, defaulting when missing or unreadable
+pub fn load_repo_meta(repo: &Repository) -> RepoMeta {
+    std::fs::read_to_string(repo.path().join(REPO_META_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn record_pull_time(repo: &Repository, now: i64) -> Result<(), GitError> {
+    let mut meta = load_repo_meta(repo);
+    meta.last_pull_time = Some(now);
+    let content = serde_json::to_string_pretty(&meta)
+        .map_err(|e| GitError::Io(std::io::Error::other(e)))?;
+    std::fs::write(repo.path().join(REPO_META_FILE), content)?;
+    Ok(())
+}
+
+/// Whether a repo last pulled at `last_pull_time` is older than `max_age_secs`
+///
+/// Repos never pulled by NekoTick count as stale.
+pub fn is_stale(last_pull_time: Option<i64>, now: i64, max_age_secs: u64) -> bool {
+    match last_pull_time {
+        Some(pulled_at) => now.saturating_sub(pulled_at) > max_age_secs as i64,
+        None => true,
+    }
+}
+
+/// Whether the worktree has no changes, including untracked files
+pub fn is_worktree_clean(repo: &Repository) -> Result<bool, GitError> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true);
+    Ok(repo.statuses(Some(&mut options))?.is_empty())
+}
+
+/// What an automatic pull should do with a repository
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoPullDecision {
+    Pull,
+    Fresh,
+    Dirty,
+}
+
+/// Decide whether a repository should be auto-pulled
+///
+/// Dirty worktrees are never pulled so local edits are not overwritten.
+pub fn auto_pull_decision(repo: &Repository, now: i64, max_age_secs: u64) -> Result<AutoPullDecision, GitError> {
+    if !is_stale(load_repo_meta(repo).last_pull_time, now, max_age_secs) {
+        return Ok(AutoPullDecision::Fresh);
+    }
+    if !is_worktree_clean(repo)? {
+        return Ok(AutoPullDecision::Dirty);
+    }
+    Ok(AutoPullDecision::Pull)
+}
+
 /// Pull latest changes from remote
 pub fn pull_repo(owner: &str, repo: &str, token: &str) -> Result<(), GitError> {
-    let repo = open_repo(owner, repo)?;
-    
-    // Fetch from origin
+    pull_repo_with_progress(owner, repo, token, false, None, None)
+}
+
+/// Pull latest changes from remote, reporting fetch progress
+///
+/// With `autostash`, uncommitted changes are stashed for the pull and
+/// reapplied afterwards. Diverged histories get a merge commit authored by
+/// `merge_author` (name, email), or the repository's configured identity.
+pub fn pull_repo_with_progress(
+    owner: &str,
+    repo: &str,
+    token: &str,
+    autostash: bool,
+    merge_author: Option<(&str, &str)>,
+    on_progress: Option<ProgressCallback<'_>>,
+) -> Result<(), GitError> {
+    let mut repo = open_repo(owner, repo)?;
+    if autostash {
+        with_autostash(&mut repo, |repo| merge_from_origin(repo, token, merge_author, on_progress))?;
+    } else {
+        merge_from_origin(&repo, token, merge_author, on_progress)?;
+    }
+    record_pull_time(&repo, chrono::Utc::now().timestamp())
+}
+
+const AUTOSTASH_MESSAGE: &str = "NekoTick autostash before pull";
+
+/// Run `op` with uncommitted changes (including untracked files) stashed
+///
+/// The stash is reapplied even if `op` fails. If reapplying conflicts, the
+/// stash is left in place and `StashConflict` is returned.
+fn with_autostash<T>(
+    repo: &mut Repository,
+    op: impl FnOnce(&Repository) -> Result<T, GitError>,
+) -> Result<T, GitError> {
+    if is_worktree_clean(repo)? {
+        return op(repo);
+    }
+
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now(NEKOTICK_COMMITTER_NAME, NEKOTICK_COMMITTER_EMAIL))?;
+    repo.stash_save(&signature, AUTOSTASH_MESSAGE, Some(StashFlags::INCLUDE_UNTRACKED))?;
+
+    let result = op(repo);
+    restore_autostash(repo)?;
+    result
+}
+
+/// Apply the newest stash and drop it only if it applied cleanly
+fn restore_autostash(repo: &mut Repository) -> Result<(), GitError> {
+    match repo.stash_apply(0, None) {
+        Ok(()) if repo.index()?.has_conflicts() => Err(GitError::StashConflict),
+        Ok(()) => {
+            repo.stash_drop(0)?;
+            Ok(())
+        }
+        Err(e) if matches!(e.code(), ErrorCode::Conflict | ErrorCode::MergeConflict) => {
+            Err(GitError::StashConflict)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Fetch `refspecs` from origin (its configured refspecs when empty)
+fn fetch_origin(
+    repo: &Repository,
+    token: &str,
+    refspecs: &[String],
+    on_progress: Option<ProgressCallback<'_>>,
+) -> Result<(), GitError> {
     let mut remote = repo.find_remote("origin")?;
-    let callbacks = create_callbacks(token);
+    let callbacks = create_callbacks(token, on_progress);
     let mut fetch_options = FetchOptions::new();
     fetch_options.remote_callbacks(callbacks);
     
-    remote.fetch(&["main", "master"], Some(&mut fetch_options), None)?;
+    remote.fetch(refspecs, Some(&mut fetch_options), None)?;
+    Ok(())
+}
+
+/// How the checked-out branch compares to its upstream
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AheadBehind {
+    pub branch: String,
+    /// Upstream branch, e.g. `origin/main`; `None` when there is none
+    pub upstream: Option<String>,
+    /// Local commits not on the upstream
+    pub ahead: usize,
+    /// Upstream commits not yet pulled
+    pub behind: usize,
+}
+
+/// Update origin's remote-tracking branches without touching HEAD, the
+/// index or the worktree, then compare the current branch to its upstream
+///
+/// Unlike `pull_repo` this never changes local files, so it is safe to poll.
+pub fn fetch_repo(owner: &str, repo: &str, token: &str) -> Result<AheadBehind, GitError> {
+    let repo = open_repo(owner, repo)?;
+    fetch_origin(&repo, token, &[], None)?;
+    ahead_behind_in(&repo)
+}
+
+/// Compare the current branch to its upstream as of the last fetch
+pub fn get_ahead_behind(owner: &str, repo: &str) -> Result<AheadBehind, GitError> {
+    let repo = open_repo(owner, repo)?;
+    ahead_behind_in(&repo)
+}
+
+fn ahead_behind_in(repo: &Repository) -> Result<AheadBehind, GitError> {
+    let branch = current_branch(repo)?;
+    let local = repo.head()?.peel_to_commit()?.id();
+
+    // The configured upstream, or origin's branch of the same name
+    let upstream = repo
+        .find_branch(&branch, BranchType::Local)?
+        .upstream()
+        .or_else(|_| repo.find_branch(&format!("origin/{}", branch), BranchType::Remote));
+    let Ok(upstream) = upstream else {
+        return Ok(AheadBehind { branch, upstream: None, ahead: 0, behind: 0 });
+    };
+
+    let upstream_id = upstream.get().peel_to_commit()?.id();
+    let (ahead, behind) = repo.graph_ahead_behind(local, upstream_id)?;
+    Ok(AheadBehind {
+        branch,
+        upstream: upstream.name()?.map(str::to_string),
+        ahead,
+        behind,
+    })
+}
+
+/// Fetch origin and fast-forward or merge it into the local branch
+fn merge_from_origin(
+    repo: &Repository,
+    token: &str,
+    merge_author: Option<(&str, &str)>,
+    on_progress: Option<ProgressCallback<'_>>,
+) -> Result<(), GitError> {
+    // Pull the checked-out branch; an unborn HEAD falls back to main/master
+    let branches = match current_branch(repo) {
+        Ok(branch) => vec![branch],
+        Err(GitError::DetachedHead) => return Err(GitError::DetachedHead),
+        Err(_) => vec!["main".to_string(), "master".to_string()],
+    };
+
+    fetch_origin(repo, token, &branches, on_progress)?;
     
     // Get the fetch head
     let fetch_head = repo.find_reference("FETCH_HEAD")?;
@@ -160,48 +485,204 @@ pub fn pull_repo(owner: &str, repo: &str, token: &str) -> Result<(), GitError> {
     
     if analysis.is_fast_forward() {
         // Fast-forward merge
-        let refname = "refs/heads/main";
-        let mut reference = match repo.find_reference(refname) {
+        let refname = format!("refs/heads/{}", branches[0]);
+        let mut reference = match repo.find_reference(&refname) {
             Ok(r) => r,
             Err(_) => repo.find_reference("refs/heads/master")?,
         };
         reference.set_target(fetch_commit.id(), "Fast-forward")?;
         repo.set_head(reference.name().unwrap())?;
         repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+    } else if analysis.is_normal() {
+        let theirs = repo.find_commit(fetch_commit.id())?;
+        let message = format!("Merge remote-tracking branch 'origin/{}'", branches[0]);
+        merge_into_head(repo, &theirs, &message, merge_author)?;
     }
     
     Ok(())
 }
 
+/// Merge `theirs` into HEAD with a merge commit
+///
+/// The merge is computed in memory first, so a conflicting pull leaves HEAD,
+/// the index and the worktree untouched and reports the conflicted paths.
+fn merge_into_head(
+    repo: &Repository,
+    theirs: &git2::Commit,
+    message: &str,
+    author: Option<(&str, &str)>,
+) -> Result<git2::Oid, GitError> {
+    let head = repo.head()?.peel_to_commit()?;
+    let mut index = repo.merge_commits(&head, theirs, None)?;
+
+    if index.has_conflicts() {
+        let mut paths: Vec<String> = index
+            .conflicts()?
+            .filter_map(|conflict| conflict.ok())
+            .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+            .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+            .collect();
+        paths.sort();
+        paths.dedup();
+        return Err(GitError::MergeConflict(paths));
+    }
+
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+    let signature = match author {
+        Some((name, email)) => Signature::now(name, email)?,
+        None => repo
+            .signature()
+            .or_else(|_| Signature::now(NEKOTICK_COMMITTER_NAME, NEKOTICK_COMMITTER_EMAIL))?,
+    };
+
+    // Update the worktree before moving HEAD so the safe checkout compares
+    // against the pre-merge tree
+    repo.checkout_tree(tree.as_object(), Some(git2::build::CheckoutBuilder::new().safe()))?;
+    let id = repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[&head, theirs])?;
+    Ok(id)
+}
+
 /// Push local changes to remote
 pub fn push_repo(owner: &str, repo: &str, token: &str) -> Result<(), GitError> {
     let repo = open_repo(owner, repo)?;
     let mut remote = repo.find_remote("origin")?;
     
-    let callbacks = create_callbacks(token);
+    let callbacks = create_callbacks(token, None);
     let mut push_options = PushOptions::new();
     push_options.remote_callbacks(callbacks);
     
-    // Try main first, then master
-    let refspec = match repo.find_reference("refs/heads/main") {
-        Ok(_) => "refs/heads/main:refs/heads/main",
-        Err(_) => "refs/heads/master:refs/heads/master",
-    };
+    // Push the checked-out branch
+    let branch = current_branch(&repo)?;
+    let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
     
-    remote.push(&[refspec], Some(&mut push_options))?;
+    remote.push(&[refspec.as_str()], Some(&mut push_options))?;
     
     Ok(())
 }
 
+/// Local or remote-tracking branch
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchInfo {
+    /// Short name, e.g. `notes` or `origin/notes`
+    pub name: String,
+    pub is_remote: bool,
+    /// Checked out in the worktree
+    pub is_current: bool,
+}
+
+/// Name of the checked-out local branch
+///
+/// Fails with `DetachedHead` when HEAD points straight at a commit.
+pub fn current_branch(repo: &Repository) -> Result<String, GitError> {
+    if repo.head_detached()? {
+        return Err(GitError::DetachedHead);
+    }
+    let head = repo.head()?;
+    head.shorthand().map(str::to_string).ok_or(GitError::DetachedHead)
+}
+
+/// List local and remote-tracking branches
+pub fn list_branches(owner: &str, repo_name: &str) -> Result<Vec<BranchInfo>, GitError> {
+    let repo = open_repo(owner, repo_name)?;
+    list_branches_in(&repo)
+}
+
+fn list_branches_in(repo: &Repository) -> Result<Vec<BranchInfo>, GitError> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(None)? {
+        let (branch, branch_type) = branch?;
+        let Some(name) = branch.name()? else {
+            continue;
+        };
+        // origin/HEAD is a pointer to the default branch, not a branch
+        if branch_type == BranchType::Remote && name.ends_with("/HEAD") {
+            continue;
+        }
+        branches.push(BranchInfo {
+            name: name.to_string(),
+            is_remote: branch_type == BranchType::Remote,
+            is_current: branch.is_head(),
+        });
+    }
+    branches.sort_by(|a, b| (a.is_remote, &a.name).cmp(&(b.is_remote, &b.name)));
+    Ok(branches)
+}
+
+/// Check out `branch`, creating a local tracking branch for `origin/<branch>`
+/// when there is no local one
+///
+/// Refuses to switch with uncommitted changes rather than overwriting them.
+pub fn checkout_branch(owner: &str, repo_name: &str, branch: &str) -> Result<(), GitError> {
+    let repo = open_repo(owner, repo_name)?;
+    checkout_branch_in(&repo, branch)
+}
+
+fn checkout_branch_in(repo: &Repository, branch: &str) -> Result<(), GitError> {
+    if !is_worktree_clean(repo)? {
+        return Err(GitError::DirtyWorktree);
+    }
+
+    let local_name = branch.strip_prefix("origin/").unwrap_or(branch);
+    let local = match repo.find_branch(local_name, BranchType::Local) {
+        Ok(local) => local,
+        Err(_) => {
+            let remote_name = format!("origin/{}", local_name);
+            let remote = repo
+                .find_branch(&remote_name, BranchType::Remote)
+                .map_err(|_| GitError::BranchNotFound(branch.to_string()))?;
+            let commit = remote.get().peel_to_commit()?;
+            let mut local = repo.branch(local_name, &commit, false)?;
+            local.set_upstream(Some(&remote_name))?;
+            local
+        }
+    };
+
+    let refname = local.get().name().ok_or_else(|| GitError::BranchNotFound(branch.to_string()))?.to_string();
+    let target = local.get().peel_to_commit()?;
+    repo.checkout_tree(target.as_object(), Some(git2::build::CheckoutBuilder::new().safe()))?;
+    repo.set_head(&refname)?;
+    Ok(())
+}
+
+/// Committer identity used when commits are marked as app-made
+const NEKOTICK_COMMITTER_NAME: &str = "NekoTick";
+const NEKOTICK_COMMITTER_EMAIL: &str = "noreply@nekotick.com";
+
+/// Append the `NekoTick-Version` trailer to a commit message
+pub fn append_tool_trailer(message: &str) -> String {
+    format!(
+        "{}\n\nNekoTick-Version: {}\n",
+        message.trim_end(),
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
 /// Commit all changes in the repository
+///
+/// When `add_tool_trailer` is set, the message gets a `NekoTick-Version`
+/// trailer and the committer is recorded as NekoTick while the author stays
+/// the user, so app-made commits are identifiable in history.
 pub fn commit_all(
     owner: &str,
     repo_name: &str,
     message: &str,
     author_name: &str,
     author_email: &str,
+    add_tool_trailer: bool,
 ) -> Result<String, GitError> {
     let repo = open_repo(owner, repo_name)?;
+    commit_all_in(&repo, message, author_name, author_email, add_tool_trailer)
+}
+
+/// Commit all changes in an already opened repository
+fn commit_all_in(
+    repo: &Repository,
+    message: &str,
+    author_name: &str,
+    author_email: &str,
+    add_tool_trailer: bool,
+) -> Result<String, GitError> {
     let mut index = repo.index()?;
     
     // Add all changes
@@ -211,7 +692,15 @@ pub fn commit_all(
     let tree_id = index.write_tree()?;
     let tree = repo.find_tree(tree_id)?;
     
-    let signature = Signature::now(author_name, author_email)?;
+    let author = Signature::now(author_name, author_email)?;
+    let (committer, message) = if add_tool_trailer {
+        (
+            Signature::now(NEKOTICK_COMMITTER_NAME, NEKOTICK_COMMITTER_EMAIL)?,
+            append_tool_trailer(message),
+        )
+    } else {
+        (author.clone(), message.to_string())
+    };
     
     // Get parent commit
     let parent = match repo.head() {
@@ -223,9 +712,9 @@ pub fn commit_all(
     
     let commit_id = repo.commit(
         Some("HEAD"),
-        &signature,
-        &signature,
-        message,
+        &author,
+        &committer,
+        &message,
         &tree,
         &parents,
     )?;
@@ -239,15 +728,24 @@ pub fn commit_all(
 #[serde(rename_all = "camelCase")]
 pub struct FileStatus {
     pub path: String,
-    pub status: String, // "new", "modified", "deleted", "renamed", "untracked"
+    pub status: String, // "new", "modified", "deleted", "renamed", "untracked", "ignored"
 }
 
 /// Get status of all files in the repository
-pub fn get_status(owner: &str, repo_name: &str) -> Result<Vec<FileStatus>, GitError> {
+///
+/// Files matched by `.gitignore` are left out unless `show_ignored` is set.
+pub fn get_status(owner: &str, repo_name: &str, show_ignored: bool) -> Result<Vec<FileStatus>, GitError> {
     let repo = open_repo(owner, repo_name)?;
+    get_status_in(&repo, show_ignored)
+}
+
+fn get_status_in(repo: &Repository, show_ignored: bool) -> Result<Vec<FileStatus>, GitError> {
     let mut options = StatusOptions::new();
     options.include_untracked(true);
     options.recurse_untracked_dirs(true);
+    options.exclude_submodules(true);
+    options.include_ignored(show_ignored);
+    options.recurse_ignored_dirs(show_ignored);
     
     let statuses = repo.statuses(Some(&mut options))?;
     let mut result = Vec::new();
@@ -256,7 +754,9 @@ pub fn get_status(owner: &str, repo_name: &str) -> Result<Vec<FileStatus>, GitEr
         let path = entry.path().unwrap_or("").to_string();
         let status = entry.status();
         
-        let status_str = if status.is_index_new() || status.is_wt_new() {
+        let status_str = if status.is_ignored() {
+            "ignored"
+        } else if status.is_index_new() || status.is_wt_new() {
             "new"
         } else if status.is_index_modified() || status.is_wt_modified() {
             "modified"
@@ -292,32 +792,99 @@ pub struct CommitInfo {
 /// Get commit history
 pub fn get_log(owner: &str, repo_name: &str, limit: usize) -> Result<Vec<CommitInfo>, GitError> {
     let repo = open_repo(owner, repo_name)?;
+    get_log_in(&repo, limit)
+}
+
+/// Up to `limit` commits from HEAD
+///
+/// A shallow clone whose boundary commits can't be walked falls back to
+/// `first_parent_log`, so truncated history yields what is there instead
+/// of an error.
+fn get_log_in(repo: &Repository, limit: usize) -> Result<Vec<CommitInfo>, GitError> {
+    match walk_log(repo, limit) {
+        Err(e) if e.code() == ErrorCode::NotFound && repo.is_shallow() => first_parent_log(repo, limit),
+        result => result.map_err(GitError::from),
+    }
+}
+
+fn walk_log(repo: &Repository, limit: usize) -> Result<Vec<CommitInfo>, git2::Error> {
     let mut revwalk = repo.revwalk()?;
     revwalk.push_head()?;
     
     let mut commits = Vec::new();
     
-    for (i, oid) in revwalk.enumerate() {
-        if i >= limit {
-            break;
-        }
-        
-        let oid = oid?;
-        let commit = repo.find_commit(oid)?;
-        
-        commits.push(CommitInfo {
-            id: oid.to_string(),
-            short_id: oid.to_string()[..7].to_string(),
-            message: commit.message().unwrap_or("").to_string(),
-            author_name: commit.author().name().unwrap_or("").to_string(),
-            author_email: commit.author().email().unwrap_or("").to_string(),
-            timestamp: commit.time().seconds(),
-        });
+    for oid in revwalk.take(limit) {
+        commits.push(commit_info(&repo.find_commit(oid?)?));
     }
     
     Ok(commits)
 }
 
+/// First-parent history from HEAD, ending at the first commit whose parent is missing
+fn first_parent_log(repo: &Repository, limit: usize) -> Result<Vec<CommitInfo>, GitError> {
+    let mut commits = Vec::new();
+    let mut next = Some(repo.head()?.peel_to_commit()?);
+    while let Some(commit) = next.filter(|_| commits.len() < limit) {
+        commits.push(commit_info(&commit));
+        next = commit.parent(0).ok();
+    }
+    Ok(commits)
+}
+
+fn commit_info(commit: &git2::Commit) -> CommitInfo {
+    let id = commit.id().to_string();
+    CommitInfo {
+        short_id: id[..7].to_string(),
+        id,
+        message: commit.message().unwrap_or("").to_string(),
+        author_name: commit.author().name().unwrap_or("").to_string(),
+        author_email: commit.author().email().unwrap_or("").to_string(),
+        timestamp: commit.time().seconds(),
+    }
+}
+
+/// Most recent commit on HEAD that changed `path`, or `None` if it never did
+pub fn get_file_last_commit(owner: &str, repo_name: &str, path: &str) -> Result<Option<CommitInfo>, GitError> {
+    let repo = open_repo(owner, repo_name)?;
+    file_last_commit_in(&repo, path)
+}
+
+fn file_last_commit_in(repo: &Repository, path: &str) -> Result<Option<CommitInfo>, GitError> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(git2::Sort::TIME)?;
+    revwalk.push_head()?;
+
+    let mut diff_options = DiffOptions::new();
+    diff_options.pathspec(path).disable_pathspec_match(true);
+
+    // Newest first, so the first commit that touches the path is the answer
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let tree = commit.tree()?;
+
+        // Like `git log -- path`: a merge only counts if the path differs from every parent
+        let touched = if commit.parent_count() == 0 {
+            repo.diff_tree_to_tree(None, Some(&tree), Some(&mut diff_options))?.deltas().len() > 0
+        } else {
+            let mut touched = true;
+            for parent in commit.parents() {
+                let diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&tree), Some(&mut diff_options))?;
+                if diff.deltas().len() == 0 {
+                    touched = false;
+                    break;
+                }
+            }
+            touched
+        };
+
+        if touched {
+            return Ok(Some(commit_info(&commit)));
+        }
+    }
+
+    Ok(None)
+}
+
 /// Get diff for a specific file
 pub fn get_file_diff(
     owner: &str,
@@ -330,7 +897,52 @@ pub fn get_file_diff(
     diff_options.pathspec(file_path);
     
     let diff = repo.diff_index_to_workdir(None, Some(&mut diff_options))?;
-    
+    patch_text(&diff)
+}
+
+/// SHA-1 of git's empty tree, the conventional "before" side of a root commit
+const EMPTY_TREE_SHA: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// Diff two commits as a patch, optionally limited to one file
+///
+/// An empty `from_sha` (or the empty-tree SHA) diffs against nothing, so a
+/// root commit shows every file as added.
+pub fn get_commit_diff(
+    owner: &str,
+    repo_name: &str,
+    from_sha: &str,
+    to_sha: &str,
+    file_path: Option<&str>,
+) -> Result<String, GitError> {
+    let repo = open_repo(owner, repo_name)?;
+    commit_diff_in(&repo, from_sha, to_sha, file_path)
+}
+
+fn commit_diff_in(
+    repo: &Repository,
+    from_sha: &str,
+    to_sha: &str,
+    file_path: Option<&str>,
+) -> Result<String, GitError> {
+    let from_sha = from_sha.trim();
+    let from_tree = if from_sha.is_empty() || from_sha == EMPTY_TREE_SHA {
+        None
+    } else {
+        Some(repo.revparse_single(from_sha)?.peel_to_commit()?.tree()?)
+    };
+    let to_tree = repo.revparse_single(to_sha.trim())?.peel_to_commit()?.tree()?;
+
+    let mut diff_options = DiffOptions::new();
+    if let Some(file_path) = file_path {
+        diff_options.pathspec(file_path);
+    }
+
+    let diff = repo.diff_tree_to_tree(from_tree.as_ref(), Some(&to_tree), Some(&mut diff_options))?;
+    patch_text(&diff)
+}
+
+/// Render a diff as unified patch text
+fn patch_text(diff: &git2::Diff) -> Result<String, GitError> {
     let mut diff_text = String::new();
     diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
         let prefix = match line.origin() {
@@ -388,3 +1000,490 @@ pub fn list_local_repos() -> Result<Vec<(String, String)>, GitError> {
     
     Ok(repos)
 }
+
+/// Stop collecting search hits after this many
+pub const MAX_SEARCH_HITS: usize = 500;
+/// Files larger than this are skipped by search
+const MAX_SEARCH_FILE_BYTES: u64 = 1024 * 1024;
+
+/// A matching line in a locally cloned repository
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub owner: String,
+    pub repo: String,
+    pub path: String,
+    pub line_number: usize,
+    pub line: String,
+}
+
+/// Search the tracked text files of every locally cloned repository
+///
+/// Results are capped at `MAX_SEARCH_HITS`; binary and very large files are skipped.
+pub fn search_local_repos(query: &str, case_sensitive: bool) -> Result<Vec<SearchHit>, GitError> {
+    let mut hits = Vec::new();
+    if query.is_empty() {
+        return Ok(hits);
+    }
+
+    for (owner, repo_name) in list_local_repos()? {
+        let repo = open_repo(&owner, &repo_name)?;
+        search_repo_in(&repo, &owner, &repo_name, query, case_sensitive, &mut hits)?;
+        if hits.len() >= MAX_SEARCH_HITS {
+            break;
+        }
+    }
+
+    Ok(hits)
+}
+
+fn search_repo_in(
+    repo: &Repository,
+    owner: &str,
+    repo_name: &str,
+    query: &str,
+    case_sensitive: bool,
+    hits: &mut Vec<SearchHit>,
+) -> Result<(), GitError> {
+    let workdir = repo.workdir().ok_or_else(|| GitError::RepoNotFound(repo_name.to_string()))?;
+    let needle = if case_sensitive { query.to_string() } else { query.to_lowercase() };
+
+    // Only index entries, so untracked files and `.git/` are never searched
+    for entry in repo.index()?.iter() {
+        let path = String::from_utf8_lossy(&entry.path).into_owned();
+        let full_path = workdir.join(&path);
+        let Ok(metadata) = std::fs::metadata(&full_path) else {
+            continue;
+        };
+        if !metadata.is_file() || metadata.len() > MAX_SEARCH_FILE_BYTES {
+            continue;
+        }
+
+        let bytes = std::fs::read(&full_path)?;
+        if bytes.contains(&0) {
+            continue;
+        }
+        let Ok(text) = String::from_utf8(bytes) else {
+            continue;
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            let matched = if case_sensitive {
+                line.contains(&needle)
+            } else {
+                line.to_lowercase().contains(&needle)
+            };
+            if !matched {
+                continue;
+            }
+
+            hits.push(SearchHit {
+                owner: owner.to_string(),
+                repo: repo_name.to_string(),
+                path: path.clone(),
+                line_number: index + 1,
+                line: line.to_string(),
+            });
+            if hits.len() >= MAX_SEARCH_HITS {
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo_with_file(dir: &std::path::Path) -> Repository {
+        let repo = Repository::init(dir).unwrap();
+        std::fs::write(dir.join("note.md"), "hello").unwrap();
+        repo
+    }
+
+    fn progress(received: usize, total: usize) -> GitProgress {
+        GitProgress { received_objects: received, total_objects: total, received_bytes: received * 100 }
+    }
+
+    /// Repo with commits "1".."n" and the ids of all of them, oldest first
+    fn repo_with_history(dir: &std::path::Path, n: usize) -> (Repository, Vec<git2::Oid>) {
+        let repo = init_repo_with_file(dir);
+        let mut ids = Vec::new();
+        for i in 1..=n {
+            std::fs::write(dir.join("note.md"), format!("version {}", i)).unwrap();
+            commit_all_in(&repo, &i.to_string(), "Alice", "alice@example.com", false).unwrap();
+            ids.push(repo.head().unwrap().target().unwrap());
+        }
+        (repo, ids)
+    }
+
+    fn remove_object(dir: &std::path::Path, id: git2::Oid) {
+        let hex = id.to_string();
+        std::fs::remove_file(dir.join(".git/objects").join(&hex[..2]).join(&hex[2..])).unwrap();
+    }
+
+    #[test]
+    fn test_log_of_shallow_clone_ends_at_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, ids) = repo_with_history(dir.path(), 3);
+
+        // Like a depth-2 clone: commit 2 is the boundary and commit 1 was never fetched
+        remove_object(dir.path(), ids[0]);
+        std::fs::write(dir.path().join(".git/shallow"), format!("{}\n", ids[1])).unwrap();
+        let repo = Repository::open(dir.path()).unwrap();
+
+        let messages: Vec<String> = get_log_in(&repo, 10).unwrap().into_iter().map(|c| c.message).collect();
+        assert_eq!(messages, ["3", "2"]);
+    }
+
+    #[test]
+    fn test_log_falls_back_when_shallow_boundary_is_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (_, ids) = repo_with_history(dir.path(), 2);
+        remove_object(dir.path(), ids[0]);
+        std::fs::write(dir.path().join(".git/shallow"), format!("{}\n", ids[0])).unwrap();
+        let repo = Repository::open(dir.path()).unwrap();
+
+        let log = get_log_in(&repo, 10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].message, "2");
+    }
+
+    #[test]
+    fn test_ahead_behind_after_fetch() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let (origin, _) = repo_with_history(origin_dir.path(), 1);
+        let clone_dir = tempfile::tempdir().unwrap();
+        let clone = Repository::clone(origin_dir.path().to_str().unwrap(), clone_dir.path()).unwrap();
+
+        std::fs::write(origin_dir.path().join("note.md"), "remote edit").unwrap();
+        commit_all_in(&origin, "Remote", "Bob", "bob@example.com", false).unwrap();
+        std::fs::write(clone_dir.path().join("note.md"), "local edit").unwrap();
+        commit_all_in(&clone, "Local", "Alice", "alice@example.com", false).unwrap();
+
+        // Nothing fetched yet
+        let status = ahead_behind_in(&clone).unwrap();
+        assert_eq!((status.ahead, status.behind), (1, 0));
+
+        fetch_origin(&clone, "", &[], None).unwrap();
+        let status = ahead_behind_in(&clone).unwrap();
+        assert_eq!((status.ahead, status.behind), (1, 1));
+        assert_eq!(status.upstream, Some(format!("origin/{}", status.branch)));
+        // The worktree is untouched
+        assert_eq!(std::fs::read_to_string(clone_dir.path().join("note.md")).unwrap(), "local edit");
+    }
+
+    #[test]
+    fn test_progress_throttle() {
+        let start = Instant::now();
+        let mut throttle = ProgressThrottle::new(Duration::from_millis(250));
+
+        assert!(throttle.should_emit(&progress(1, 100), start));
+        assert!(!throttle.should_emit(&progress(2, 100), start + Duration::from_millis(100)));
+        assert!(throttle.should_emit(&progress(3, 100), start + Duration::from_millis(300)));
+        // Completion is always reported, even inside the interval
+        assert!(throttle.should_emit(&progress(100, 100), start + Duration::from_millis(310)));
+    }
+
+    #[test]
+    fn test_staleness_selection() {
+        assert!(is_stale(None, 1_000, 3600));
+        assert!(is_stale(Some(1_000), 1_000 + 3601, 3600));
+        assert!(!is_stale(Some(1_000), 1_000 + 3600, 3600));
+        // Clock moved backwards: treat as fresh
+        assert!(!is_stale(Some(5_000), 1_000, 3600));
+    }
+
+    #[test]
+    fn test_dirty_worktree_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        assert_eq!(auto_pull_decision(&repo, 10_000, 60).unwrap(), AutoPullDecision::Dirty);
+
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+        assert_eq!(auto_pull_decision(&repo, 10_000, 60).unwrap(), AutoPullDecision::Pull);
+    }
+
+    #[test]
+    fn test_recent_pull_is_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        record_pull_time(&repo, 10_000).unwrap();
+        assert_eq!(load_repo_meta(&repo).last_pull_time, Some(10_000));
+        assert_eq!(auto_pull_decision(&repo, 10_030, 60).unwrap(), AutoPullDecision::Fresh);
+        // Metadata lives in .git/ and does not dirty the worktree
+        assert!(is_worktree_clean(&repo).unwrap());
+    }
+
+    #[test]
+    fn test_list_and_checkout_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+        let default_branch = current_branch(&repo).unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("notes", &head, false).unwrap();
+
+        let names: Vec<String> = list_branches_in(&repo).unwrap().into_iter().map(|b| b.name).collect();
+        assert!(names.contains(&"notes".to_string()));
+
+        checkout_branch_in(&repo, "notes").unwrap();
+        assert_eq!(current_branch(&repo).unwrap(), "notes");
+        let current: Vec<BranchInfo> = list_branches_in(&repo).unwrap().into_iter().filter(|b| b.is_current).collect();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].name, "notes");
+
+        checkout_branch_in(&repo, &default_branch).unwrap();
+        assert_eq!(current_branch(&repo).unwrap(), default_branch);
+    }
+
+    #[test]
+    fn test_checkout_refuses_dirty_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("notes", &head, false).unwrap();
+
+        std::fs::write(dir.path().join("note.md"), "edited").unwrap();
+        assert!(matches!(checkout_branch_in(&repo, "notes"), Err(GitError::DirtyWorktree)));
+        assert!(matches!(checkout_branch_in(&repo, "missing"), Err(GitError::DirtyWorktree)));
+    }
+
+    #[test]
+    fn test_missing_branch_and_detached_head() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        assert!(matches!(checkout_branch_in(&repo, "missing"), Err(GitError::BranchNotFound(_))));
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.set_head_detached(head.id()).unwrap();
+        assert!(matches!(current_branch(&repo), Err(GitError::DetachedHead)));
+    }
+
+    /// Commit `content` to note.md as if it had been pulled from the remote
+    fn commit_incoming(repo: &Repository, content: &str) -> Result<(), GitError> {
+        std::fs::write(repo.workdir().unwrap().join("note.md"), content)?;
+        commit_all_in(repo, "Remote edit", "Bob", "bob@example.com", false)?;
+        Ok(())
+    }
+
+    fn stash_count(repo: &mut Repository) -> usize {
+        let mut count = 0;
+        repo.stash_foreach(|_, _, _| {
+            count += 1;
+            true
+        })
+        .unwrap();
+        count
+    }
+
+    #[test]
+    fn test_autostash_restores_local_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = init_repo_with_file(dir.path());
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        std::fs::write(dir.path().join("draft.md"), "local draft").unwrap();
+        with_autostash(&mut repo, |repo| {
+            // The worktree is clean while the pull runs
+            assert!(is_worktree_clean(repo).unwrap());
+            commit_incoming(repo, "remote")
+        })
+        .unwrap();
+
+        assert_eq!(std::fs::read_to_string(dir.path().join("draft.md")).unwrap(), "local draft");
+        assert_eq!(std::fs::read_to_string(dir.path().join("note.md")).unwrap(), "remote");
+        assert_eq!(stash_count(&mut repo), 0);
+    }
+
+    #[test]
+    fn test_autostash_conflict_keeps_stash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repo = init_repo_with_file(dir.path());
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        std::fs::write(dir.path().join("note.md"), "local edit").unwrap();
+        let result = with_autostash(&mut repo, |repo| commit_incoming(repo, "remote edit"));
+
+        assert!(matches!(result, Err(GitError::StashConflict)));
+        assert_eq!(stash_count(&mut repo), 1);
+    }
+
+    /// Initial commit, then `theirs` on a side branch and `ours` on the
+    /// default branch, each writing (file, content)
+    fn diverge(dir: &std::path::Path, ours: (&str, &str), theirs: (&str, &str)) -> (Repository, git2::Oid) {
+        let repo = init_repo_with_file(dir);
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+        let default_branch = current_branch(&repo).unwrap();
+        {
+            let base = repo.head().unwrap().peel_to_commit().unwrap();
+            repo.branch("remote", &base, false).unwrap();
+        }
+
+        checkout_branch_in(&repo, "remote").unwrap();
+        std::fs::write(dir.join(theirs.0), theirs.1).unwrap();
+        let their_id = commit_all_in(&repo, "Remote edit", "Bob", "bob@example.com", false).unwrap();
+
+        checkout_branch_in(&repo, &default_branch).unwrap();
+        std::fs::write(dir.join(ours.0), ours.1).unwrap();
+        commit_all_in(&repo, "Local edit", "Alice", "alice@example.com", false).unwrap();
+
+        (repo, git2::Oid::from_str(&their_id).unwrap())
+    }
+
+    #[test]
+    fn test_diverged_pull_creates_merge_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, their_id) = diverge(dir.path(), ("local.md", "local"), ("note.md", "remote"));
+        let theirs = repo.find_commit(their_id).unwrap();
+
+        let id = merge_into_head(&repo, &theirs, "Merge", Some(("Alice", "alice@example.com"))).unwrap();
+        let merge = repo.find_commit(id).unwrap();
+
+        assert_eq!(merge.parent_count(), 2);
+        assert_eq!(merge.author().name(), Some("Alice"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("note.md")).unwrap(), "remote");
+        assert_eq!(std::fs::read_to_string(dir.path().join("local.md")).unwrap(), "local");
+        assert!(is_worktree_clean(&repo).unwrap());
+    }
+
+    #[test]
+    fn test_conflicting_pull_reports_paths_and_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, their_id) = diverge(dir.path(), ("note.md", "local"), ("note.md", "remote"));
+        let theirs = repo.find_commit(their_id).unwrap();
+        let head_before = repo.head().unwrap().target();
+
+        let err = merge_into_head(&repo, &theirs, "Merge", None).unwrap_err();
+
+        assert!(matches!(err, GitError::MergeConflict(ref paths) if paths == &["note.md".to_string()]));
+        assert_eq!(repo.head().unwrap().target(), head_before);
+        assert_eq!(std::fs::read_to_string(dir.path().join("note.md")).unwrap(), "local");
+        assert!(is_worktree_clean(&repo).unwrap());
+    }
+
+    #[test]
+    fn test_commit_diff_between_revisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        let first = commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        std::fs::write(dir.path().join("note.md"), "hello\nworld").unwrap();
+        std::fs::write(dir.path().join("todo.md"), "- [ ] tea").unwrap();
+        let second = commit_all_in(&repo, "Second", "Alice", "alice@example.com", false).unwrap();
+
+        let patch = commit_diff_in(&repo, &first, &second, None).unwrap();
+        assert!(patch.contains("+world"));
+        assert!(patch.contains("+- [ ] tea"));
+
+        let scoped = commit_diff_in(&repo, &first, &second, Some("todo.md")).unwrap();
+        assert!(scoped.contains("+- [ ] tea"));
+        assert!(!scoped.contains("world"));
+    }
+
+    #[test]
+    fn test_commit_diff_from_empty_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        let root = commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        assert!(commit_diff_in(&repo, "", &root, None).unwrap().contains("+hello"));
+        assert!(commit_diff_in(&repo, EMPTY_TREE_SHA, &root, None).unwrap().contains("+hello"));
+        assert!(commit_diff_in(&repo, "0123456", &root, None).is_err());
+    }
+
+    #[test]
+    fn test_file_last_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        let first = commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+
+        std::fs::write(dir.path().join("todo.md"), "- [ ] tea").unwrap();
+        let second = commit_all_in(&repo, "Add todo", "Bob", "bob@example.com", false).unwrap();
+
+        let note = file_last_commit_in(&repo, "note.md").unwrap().unwrap();
+        assert_eq!(note.id, first);
+        assert_eq!(note.author_name, "Alice");
+
+        let todo = file_last_commit_in(&repo, "todo.md").unwrap().unwrap();
+        assert_eq!(todo.id, second);
+        assert_eq!(todo.author_name, "Bob");
+
+        assert!(file_last_commit_in(&repo, "missing.md").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_commit_with_tool_trailer() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+
+        let id = commit_all_in(&repo, "Update notes", "Alice", "alice@example.com", true).unwrap();
+        let commit = repo.find_commit(git2::Oid::from_str(&id).unwrap()).unwrap();
+
+        let message = commit.message().unwrap();
+        assert!(message.starts_with("Update notes\n\n"));
+        assert!(message.contains(&format!("NekoTick-Version: {}", env!("CARGO_PKG_VERSION"))));
+        assert_eq!(commit.author().name(), Some("Alice"));
+        assert_eq!(commit.committer().name(), Some(NEKOTICK_COMMITTER_NAME));
+    }
+
+    #[test]
+    fn test_commit_without_tool_trailer() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+
+        let id = commit_all_in(&repo, "Update notes", "Alice", "alice@example.com", false).unwrap();
+        let commit = repo.find_commit(git2::Oid::from_str(&id).unwrap()).unwrap();
+
+        assert_eq!(commit.message(), Some("Update notes"));
+        assert_eq!(commit.committer().name(), Some("Alice"));
+    }
+
+    fn search(repo: &Repository, query: &str, case_sensitive: bool) -> Vec<SearchHit> {
+        let mut hits = Vec::new();
+        search_repo_in(repo, "alice", "nekotick-notes", query, case_sensitive, &mut hits).unwrap();
+        hits
+    }
+
+    #[test]
+    fn test_search_tracked_text_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        std::fs::write(dir.path().join("todo.md"), "buy milk\nCall Bob\n").unwrap();
+        std::fs::write(dir.path().join("image.bin"), b"call\0bob").unwrap();
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+        std::fs::write(dir.path().join("untracked.md"), "call bob").unwrap();
+
+        let hits = search(&repo, "call bob", false);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].path, "todo.md");
+        assert_eq!(hits[0].line_number, 2);
+        assert_eq!(hits[0].line, "Call Bob");
+
+        assert!(search(&repo, "call bob", true).is_empty());
+    }
+
+    #[test]
+    fn test_status_respects_gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo_with_file(dir.path());
+        std::fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        commit_all_in(&repo, "Initial", "Alice", "alice@example.com", false).unwrap();
+        std::fs::write(dir.path().join("debug.log"), "noise").unwrap();
+        std::fs::write(dir.path().join("draft.md"), "todo").unwrap();
+
+        let paths = |show_ignored| -> Vec<(String, String)> {
+            get_status_in(&repo, show_ignored).unwrap().into_iter().map(|f| (f.path, f.status)).collect()
+        };
+        assert_eq!(paths(false), vec![("draft.md".to_string(), "new".to_string())]);
+        assert!(paths(true).contains(&("debug.log".to_string(), "ignored".to_string())));
+    }
+}
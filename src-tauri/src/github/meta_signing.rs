@@ -0,0 +1,137 @@
+//! HMAC signing for local sync metadata
+//!
+//! Sync metadata such as `last_sync_time` decides whether a sync pulls or
+//! pushes, so it is signed with a per-install key. Metadata that fails
+//! verification is treated as absent, which forces a conservative full sync.
+//!
+//! The key lives in the same store directory as the metadata, so this is
+//! not tamper protection: anything that can rewrite the metadata can read
+//! the key and re-sign it. What the signature catches is metadata this
+//! install didn't write: a file copied from another install or restored
+//! from an old backup, a hand edit, or a partial write.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const KEY_FILE: &str = ".sync_meta_key";
+const KEY_LENGTH: usize = 32;
+
+/// Serializes key creation within the process, so no caller reads a key file mid-write
+static CREATE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Load the signing key from `store_dir`, or `None` if there is no usable key
+///
+/// Never writes: callers that only read metadata treat a missing key as
+/// "nothing is signed".
+pub fn load_key(store_dir: &Path) -> Option<Vec<u8>> {
+    let encoded = fs::read_to_string(store_dir.join(KEY_FILE)).ok()?;
+    URL_SAFE_NO_PAD.decode(encoded.trim()).ok().filter(|key| key.len() == KEY_LENGTH)
+}
+
+/// Load the signing key from `store_dir`, creating it on first use
+///
+/// The file is created with `create_new`, so when another process wins the
+/// race its key is re-read instead of overwritten. A key file that exists
+/// but can't be decoded is replaced; nothing signed with it can be verified.
+/// Credentials have their own key in `credential_store`, so this loses at
+/// most the metadata signatures.
+pub fn load_or_create_key(store_dir: &Path) -> Result<Vec<u8>, String> {
+    let _guard = CREATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(key) = load_key(store_dir) {
+        return Ok(key);
+    }
+
+    let path = store_dir.join(KEY_FILE);
+    let mut key = vec![0u8; KEY_LENGTH];
+    rand::thread_rng().fill_bytes(&mut key);
+    let encoded = URL_SAFE_NO_PAD.encode(&key);
+
+    fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(mut file) => {
+            file.write_all(encoded.as_bytes()).map_err(|e| e.to_string())?;
+            file.sync_all().map_err(|e| e.to_string())?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            if let Some(existing) = load_key(store_dir) {
+                return Ok(existing);
+            }
+            log::warn!("Replacing unreadable sync metadata key");
+            fs::write(&path, &encoded).map_err(|e| e.to_string())?;
+        }
+        Err(e) => return Err(e.to_string()),
+    }
+
+    Ok(key)
+}
+
+/// Compute the signature of `payload`
+pub fn sign(key: &[u8], payload: &[u8]) -> String {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// Check `signature` against `payload` in constant time
+pub fn verify(key: &[u8], payload: &[u8], signature: &str) -> bool {
+    let Ok(expected) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    mac.verify_slice(&expected).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_and_verify() {
+        let key = [7u8; KEY_LENGTH];
+        let signature = sign(&key, b"{\"lastSyncTime\":100}");
+        assert!(verify(&key, b"{\"lastSyncTime\":100}", &signature));
+    }
+
+    #[test]
+    fn test_modified_payload_fails() {
+        let key = [7u8; KEY_LENGTH];
+        let signature = sign(&key, b"{\"lastSyncTime\":100}");
+        assert!(!verify(&key, b"{\"lastSyncTime\":999}", &signature));
+        assert!(!verify(&[8u8; KEY_LENGTH], b"{\"lastSyncTime\":100}", &signature));
+        assert!(!verify(&key, b"{\"lastSyncTime\":100}", "not-a-signature"));
+    }
+
+    #[test]
+    fn test_key_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_key(dir.path()).is_none());
+        assert!(!dir.path().join(KEY_FILE).exists());
+
+        let first = load_or_create_key(dir.path()).unwrap();
+        let second = load_or_create_key(dir.path()).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.len(), KEY_LENGTH);
+        assert_eq!(load_key(dir.path()), Some(first));
+    }
+
+    #[test]
+    fn test_existing_key_is_not_overwritten() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(KEY_FILE), URL_SAFE_NO_PAD.encode([5u8; KEY_LENGTH])).unwrap();
+        assert_eq!(load_or_create_key(dir.path()).unwrap(), vec![5u8; KEY_LENGTH]);
+
+        fs::write(dir.path().join(KEY_FILE), "corrupt").unwrap();
+        assert!(load_key(dir.path()).is_none());
+        let replaced = load_or_create_key(dir.path()).unwrap();
+        assert_eq!(load_key(dir.path()), Some(replaced));
+    }
+}
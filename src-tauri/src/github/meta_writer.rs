@@ -0,0 +1,195 @@
+//! Write-coalescing layer for small metadata files
+//!
+//! Sync metadata is rewritten on every sync. Writes are buffered per path and
+//! flushed once after a short debounce, and `flush` is called on app exit so
+//! the last update is never lost. Content that fails to write stays queued
+//! for the next flush.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Default debounce before pending writes hit the disk
+const DEFAULT_DEBOUNCE: Duration = Duration::from_secs(1);
+
+struct Inner {
+    debounce: Duration,
+    pending: Mutex<HashMap<PathBuf, String>>,
+    flush_scheduled: AtomicBool,
+    disk_writes: AtomicUsize,
+}
+
+/// Debounced writer that keeps only the latest content per path
+#[derive(Clone)]
+pub struct CoalescedWriter {
+    inner: Arc<Inner>,
+}
+
+impl CoalescedWriter {
+    /// Create a writer with the given debounce delay
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                debounce,
+                pending: Mutex::new(HashMap::new()),
+                flush_scheduled: AtomicBool::new(false),
+                disk_writes: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// Process-wide writer used for sync metadata
+    pub fn global() -> &'static CoalescedWriter {
+        static WRITER: OnceLock<CoalescedWriter> = OnceLock::new();
+        WRITER.get_or_init(|| CoalescedWriter::new(DEFAULT_DEBOUNCE))
+    }
+
+    /// Queue content for `path`, replacing any pending content for it
+    ///
+    /// Outside a tokio runtime there is nothing to schedule the flush on,
+    /// so the write happens immediately.
+    pub fn write(&self, path: &Path, content: String) -> Result<(), String> {
+        self.inner
+            .pending
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(path.to_path_buf(), content);
+
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => return self.flush(),
+        };
+
+        if !self.inner.flush_scheduled.swap(true, Ordering::SeqCst) {
+            let writer = self.clone();
+            handle.spawn(async move {
+                tokio::time::sleep(writer.inner.debounce).await;
+                if let Err(e) = writer.flush() {
+                    log::error!("Failed to flush metadata: {}", e);
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Content queued for `path` that has not been flushed yet
+    pub fn pending(&self, path: &Path) -> Option<String> {
+        self.inner.pending.lock().ok()?.get(path).cloned()
+    }
+
+    /// Drop any pending content for `path` so a later flush won't recreate it
+    pub fn discard(&self, path: &Path) {
+        if let Ok(mut pending) = self.inner.pending.lock() {
+            pending.remove(path);
+        }
+    }
+
+    /// Write all pending content to disk now
+    ///
+    /// Content that fails to write is queued again unless a newer write for
+    /// the same path arrived meanwhile; the first error is returned.
+    pub fn flush(&self) -> Result<(), String> {
+        self.inner.flush_scheduled.store(false, Ordering::SeqCst);
+        let pending: Vec<(PathBuf, String)> = self
+            .inner
+            .pending
+            .lock()
+            .map_err(|e| e.to_string())?
+            .drain()
+            .collect();
+
+        let mut first_error = None;
+        for (path, content) in pending {
+            match write_file(&path, &content) {
+                Ok(()) => {
+                    self.inner.disk_writes.fetch_add(1, Ordering::SeqCst);
+                }
+                Err(e) => {
+                    let message = format!("Failed to write {}: {}", path.display(), e);
+                    if let Ok(mut queued) = self.inner.pending.lock() {
+                        queued.entry(path).or_insert(content);
+                    }
+                    first_error.get_or_insert(message);
+                }
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Number of files written to disk so far
+    pub fn disk_writes(&self) -> usize {
+        self.inner.disk_writes.load(Ordering::SeqCst)
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rapid_updates_coalesce_into_single_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("github_sync_meta.json");
+        // Long enough that the scheduled flush never fires during the test
+        let writer = CoalescedWriter::new(Duration::from_secs(60));
+
+        for i in 0..10 {
+            writer.write(&path, format!("{{\"lastSyncTime\":{}}}", i)).unwrap();
+        }
+        assert_eq!(writer.pending(&path).as_deref(), Some("{\"lastSyncTime\":9}"));
+        assert!(!path.exists());
+
+        writer.flush().unwrap();
+
+        assert_eq!(writer.disk_writes(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"lastSyncTime\":9}");
+        assert!(writer.pending(&path).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_flush_writes_immediately() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("meta.json");
+        let writer = CoalescedWriter::new(Duration::from_secs(60));
+
+        writer.write(&path, "{}".to_string()).unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{}");
+        assert_eq!(writer.disk_writes(), 1);
+    }
+
+    #[test]
+    fn test_failed_write_stays_queued() {
+        let dir = tempfile::tempdir().unwrap();
+        let blocker = dir.path().join("not_a_dir");
+        fs::write(&blocker, "").unwrap();
+        let bad_path = blocker.join("meta.json");
+        let good_path = dir.path().join("meta.json");
+        let writer = CoalescedWriter::new(Duration::from_secs(60));
+
+        writer.write(&bad_path, "{\"a\":1}".to_string()).unwrap_err();
+        writer.write(&good_path, "{}".to_string()).unwrap_err();
+
+        // The good file was still written; the failed one waits for a retry
+        assert_eq!(fs::read_to_string(&good_path).unwrap(), "{}");
+        assert_eq!(writer.pending(&bad_path).as_deref(), Some("{\"a\":1}"));
+
+        fs::remove_file(&blocker).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(fs::read_to_string(&bad_path).unwrap(), "{\"a\":1}");
+        assert!(writer.pending(&bad_path).is_none());
+    }
+}
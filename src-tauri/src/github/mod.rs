@@ -12,11 +12,17 @@ pub mod repos;
 pub mod repo_commands;
 pub mod git_ops;
 pub mod git_commands;
+pub mod meta_writer;
+pub mod meta_signing;
+pub mod compression;
+pub mod credential_store;
+pub mod server;
 
 // Re-export commonly used types
 pub use oauth::GitHubOAuthClient;
 pub use gist_api::GistClient;
 pub use repos::RepoClient;
+pub use meta_writer::CoalescedWriter;
 pub use commands::*;
 pub use repo_commands::*;
 pub use git_commands::*;
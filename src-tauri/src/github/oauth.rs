@@ -3,6 +3,8 @@
 //! Implements the OAuth2 Authorization Code flow with PKCE extension
 //! for secure desktop application authentication.
 
+use crate::github::server;
+use crate::redact::redact;
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rand::Rng;
 use sha2::{Digest, Sha256};
@@ -15,14 +17,58 @@ pub struct GitHubOAuthClient {
     pub client_id: String,
     pub client_secret: String,
     pub redirect_uri: String,
+    /// Web base of the GitHub server hosting the OAuth endpoints
+    pub web_base: String,
 }
 
 /// Token response from GitHub OAuth2
+///
+/// OAuth Apps issue non-expiring tokens; GitHub Apps (user-to-server) also
+/// return `expires_in` and a `refresh_token` with its own lifetime.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GitHubTokenResponse {
     pub access_token: String,
     pub token_type: String,
     pub scope: Option<String>,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub expires_in: Option<i64>,
+    #[serde(default)]
+    pub refresh_token_expires_in: Option<i64>,
+}
+
+impl GitHubTokenResponse {
+    /// Parse a token endpoint JSON body, surfacing `error` responses
+    pub fn from_json(token_response: &serde_json::Value) -> Result<Self, GitHubOAuthError> {
+        // Check for error in response
+        if let Some(error) = token_response.get("error") {
+            let error_desc = token_response
+                .get("error_description")
+                .and_then(|v| v.as_str())
+                .unwrap_or("Unknown error");
+            return Err(GitHubOAuthError::TokenExchangeError(format!(
+                "{}: {}",
+                error.as_str().unwrap_or("error"),
+                error_desc
+            )));
+        }
+
+        Ok(GitHubTokenResponse {
+            access_token: token_response["access_token"]
+                .as_str()
+                .ok_or_else(|| GitHubOAuthError::TokenExchangeError("Missing access_token".to_string()))?
+                .to_string(),
+            token_type: token_response["token_type"]
+                .as_str()
+                .unwrap_or("bearer")
+                .to_string(),
+            scope: token_response["scope"].as_str().map(|s| s.to_string()),
+            refresh_token: token_response["refresh_token"].as_str().map(|s| s.to_string()),
+            expires_in: token_response["expires_in"].as_i64(),
+            refresh_token_expires_in: token_response["refresh_token_expires_in"].as_i64(),
+        })
+    }
 }
 
 /// Error types for OAuth operations
@@ -47,6 +93,7 @@ impl GitHubOAuthClient {
             client_id,
             client_secret,
             redirect_uri: "http://localhost".to_string(),
+            web_base: server::current().web_base,
         }
     }
 
@@ -81,19 +128,62 @@ impl GitHubOAuthClient {
     pub fn build_auth_url(&self, state: &str, port: u16) -> String {
         let redirect_uri = format!("{}:{}", self.redirect_uri, port);
         
-        let mut url = Url::parse("https://github.com/login/oauth/authorize").unwrap();
+        let mut url = Url::parse(&format!("{}/login/oauth/authorize", self.web_base)).unwrap();
         url.query_pairs_mut()
             .append_pair("client_id", &self.client_id)
             .append_pair("redirect_uri", &redirect_uri)
-            .append_pair("scope", "repo gist read:user")
+            .append_pair("scope", "repo gist read:user user:email")
             .append_pair("state", state);
 
         url.to_string()
     }
 
+    /// Verify that a callback was addressed to the redirect URI we registered.
+    ///
+    /// The `Host` header must name the port we actually bound and the request
+    /// must target the root path, so a request crafted for another redirect
+    /// URI is rejected even if it carries a valid state.
+    pub fn verify_callback_target(
+        host_header: Option<&str>,
+        path: &str,
+        expected_port: u16,
+    ) -> Result<(), GitHubOAuthError> {
+        let host = host_header.ok_or_else(|| {
+            GitHubOAuthError::AuthorizationError("Missing Host header in callback".to_string())
+        })?;
+
+        let port = host
+            .trim()
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse::<u16>().ok())
+            .ok_or_else(|| {
+                GitHubOAuthError::AuthorizationError(format!("Callback host has no port: {}", host))
+            })?;
+
+        if port != expected_port {
+            return Err(GitHubOAuthError::AuthorizationError(format!(
+                "Callback port mismatch: expected {}, got {}",
+                expected_port, port
+            )));
+        }
+
+        if path != "/" {
+            return Err(GitHubOAuthError::AuthorizationError(format!(
+                "Unexpected callback path: {}",
+                path
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Wait for OAuth callback on an existing listener
     /// Returns the authorization code
     pub fn wait_for_callback(listener: TcpListener, expected_state: &str) -> Result<String, GitHubOAuthError> {
+        let expected_port = listener.local_addr()
+            .map_err(|e| GitHubOAuthError::ServerError(e.to_string()))?
+            .port();
+
         let (mut stream, _) = listener.accept()
             .map_err(|e| GitHubOAuthError::ServerError(e.to_string()))?;
 
@@ -102,6 +192,23 @@ impl GitHubOAuthClient {
         reader.read_line(&mut request_line)
             .map_err(|e| GitHubOAuthError::ServerError(e.to_string()))?;
 
+        // Read headers until the blank line to find the Host header
+        let mut host_header = None;
+        loop {
+            let mut line = String::new();
+            let read = reader.read_line(&mut line)
+                .map_err(|e| GitHubOAuthError::ServerError(e.to_string()))?;
+            let line = line.trim_end();
+            if read == 0 || line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("host") {
+                    host_header = Some(value.trim().to_string());
+                }
+            }
+        }
+
         // Parse the request to extract code and state
         let url_part = request_line
             .split_whitespace()
@@ -112,6 +219,9 @@ impl GitHubOAuthClient {
         let parsed = Url::parse(&full_url)
             .map_err(|e| GitHubOAuthError::AuthorizationError(e.to_string()))?;
 
+        // Reject callbacks that were not addressed to our redirect URI
+        Self::verify_callback_target(host_header.as_deref(), parsed.path(), expected_port)?;
+
         let mut code = None;
         let mut state = None;
         let mut error = None;
@@ -171,9 +281,9 @@ impl GitHubOAuthClient {
     ) -> Result<GitHubTokenResponse, GitHubOAuthError> {
         let redirect_uri = format!("{}:{}", self.redirect_uri, port);
         
-        let client = reqwest::Client::new();
+        let client = crate::http::build_http_client();
         let response = client
-            .post("https://github.com/login/oauth/access_token")
+            .post(format!("{}/login/oauth/access_token", self.web_base))
             .header("Accept", "application/json")
             .form(&[
                 ("client_id", self.client_id.as_str()),
@@ -186,8 +296,9 @@ impl GitHubOAuthClient {
             .map_err(|e| GitHubOAuthError::NetworkError(e.to_string()))?;
 
         if !response.status().is_success() {
+            // The body can echo the request's code or refresh token
             let error_text = response.text().await.unwrap_or_default();
-            return Err(GitHubOAuthError::TokenExchangeError(error_text));
+            return Err(GitHubOAuthError::TokenExchangeError(redact(&error_text)));
         }
 
         let token_response: serde_json::Value = response
@@ -195,30 +306,40 @@ impl GitHubOAuthClient {
             .await
             .map_err(|e| GitHubOAuthError::TokenExchangeError(e.to_string()))?;
 
-        // Check for error in response
-        if let Some(error) = token_response.get("error") {
-            let error_desc = token_response
-                .get("error_description")
-                .and_then(|v| v.as_str())
-                .unwrap_or("Unknown error");
-            return Err(GitHubOAuthError::TokenExchangeError(format!(
-                "{}: {}",
-                error.as_str().unwrap_or("error"),
-                error_desc
-            )));
+        GitHubTokenResponse::from_json(&token_response)
+    }
+
+    /// Exchange a refresh token for a new access token (GitHub Apps only)
+    ///
+    /// GitHub rotates the refresh token on every use, so callers must store
+    /// the returned `refresh_token` in place of the old one.
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<GitHubTokenResponse, GitHubOAuthError> {
+        let client = crate::http::build_http_client();
+        let response = client
+            .post(format!("{}/login/oauth/access_token", self.web_base))
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ])
+            .send()
+            .await
+            .map_err(|e| GitHubOAuthError::NetworkError(e.to_string()))?;
+
+        if !response.status().is_success() {
+            // The body can echo the request's code or refresh token
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(GitHubOAuthError::TokenExchangeError(redact(&error_text)));
         }
 
-        Ok(GitHubTokenResponse {
-            access_token: token_response["access_token"]
-                .as_str()
-                .ok_or_else(|| GitHubOAuthError::TokenExchangeError("Missing access_token".to_string()))?
-                .to_string(),
-            token_type: token_response["token_type"]
-                .as_str()
-                .unwrap_or("bearer")
-                .to_string(),
-            scope: token_response["scope"].as_str().map(|s| s.to_string()),
-        })
+        let token_response: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| GitHubOAuthError::TokenExchangeError(e.to_string()))?;
+
+        GitHubTokenResponse::from_json(&token_response)
     }
 }
 
@@ -243,4 +364,74 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_parse_oauth_app_token_response() {
+        let body = serde_json::json!({
+            "access_token": "gho_abc",
+            "token_type": "bearer",
+            "scope": "repo,gist"
+        });
+        let token = GitHubTokenResponse::from_json(&body).unwrap();
+        assert_eq!(token.access_token, "gho_abc");
+        assert!(token.refresh_token.is_none());
+        assert!(token.expires_in.is_none());
+    }
+
+    #[test]
+    fn test_parse_github_app_token_response() {
+        let body = serde_json::json!({
+            "access_token": "ghu_abc",
+            "token_type": "bearer",
+            "scope": "",
+            "expires_in": 28800,
+            "refresh_token": "ghr_def",
+            "refresh_token_expires_in": 15897600
+        });
+        let token = GitHubTokenResponse::from_json(&body).unwrap();
+        assert_eq!(token.refresh_token.as_deref(), Some("ghr_def"));
+        assert_eq!(token.expires_in, Some(28800));
+        assert_eq!(token.refresh_token_expires_in, Some(15897600));
+    }
+
+    #[test]
+    fn test_parse_token_error_response() {
+        let body = serde_json::json!({
+            "error": "bad_refresh_token",
+            "error_description": "The refresh token passed is incorrect or expired."
+        });
+        assert!(matches!(
+            GitHubTokenResponse::from_json(&body),
+            Err(GitHubOAuthError::TokenExchangeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify_callback_target() {
+        assert!(GitHubOAuthClient::verify_callback_target(Some("localhost:8914"), "/", 8914).is_ok());
+        assert!(GitHubOAuthClient::verify_callback_target(Some("127.0.0.1:8914"), "/", 8914).is_ok());
+        assert!(GitHubOAuthClient::verify_callback_target(Some("localhost"), "/", 8914).is_err());
+        assert!(GitHubOAuthClient::verify_callback_target(None, "/", 8914).is_err());
+        assert!(GitHubOAuthClient::verify_callback_target(Some("localhost:8914"), "/other", 8914).is_err());
+    }
+
+    #[test]
+    fn test_callback_port_mismatch_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let client = std::thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let request = "GET /?code=abc&state=xyz HTTP/1.1\r\nHost: localhost:1\r\n\r\n";
+            stream.write_all(request.as_bytes()).unwrap();
+        });
+
+        let result = GitHubOAuthClient::wait_for_callback(listener, "xyz");
+        client.join().unwrap();
+
+        match result {
+            Err(GitHubOAuthError::AuthorizationError(msg)) => assert!(msg.contains("port mismatch")),
+            other => panic!("expected port mismatch error, got {:?}", other),
+        }
+    }
 }
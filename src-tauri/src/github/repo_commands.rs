@@ -2,25 +2,10 @@
 //!
 //! These commands are exposed to the frontend via Tauri's IPC.
 
-use crate::github::repos::{RepoClient, Repository, TreeEntry, FileContent, CommitResult, get_display_name};
+use crate::github::commands::get_valid_github_token;
+use crate::redact::redact_error;
+use crate::github::repos::{RepoClient, Repository, TreeEntry, FileContent, FileOp, CommitResult, get_display_name};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
-use tauri::Manager;
-
-const NEKOTICK_FOLDER: &str = ".nekotick";
-const STORE_FOLDER: &str = "store";
-const GITHUB_CREDS_FILE: &str = "github_credentials.json";
-
-/// Stored GitHub credentials (same as in commands.rs)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct GitHubCredentials {
-    access_token: String,
-    username: String,
-    #[serde(default)]
-    avatar_url: Option<String>,
-    gist_id: Option<String>,
-}
 
 /// Repository with display name for frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,46 +40,23 @@ impl From<Repository> for RepositoryInfo {
     }
 }
 
-/// Get the data directory path
-fn get_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    app.path()
-        .app_data_dir()
-        .map_err(|e| e.to_string())
-}
-
-/// Get GitHub credentials file path
-fn get_github_creds_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
-    let mut path = get_data_dir(app)?;
-    path.push(NEKOTICK_FOLDER);
-    path.push(STORE_FOLDER);
-    path.push(GITHUB_CREDS_FILE);
-    Ok(path)
-}
-
-/// Load GitHub credentials
-fn load_github_credentials(app: &tauri::AppHandle) -> Option<GitHubCredentials> {
-    let path = get_github_creds_path(app).ok()?;
-    let content = fs::read_to_string(&path).ok()?;
-    serde_json::from_str(&content).ok()
-}
-
-/// Get access token from credentials
-fn get_access_token(app: &tauri::AppHandle) -> Result<String, String> {
-    load_github_credentials(app)
-        .map(|c| c.access_token)
+/// Get access token from credentials, refreshing it first if it is about to expire
+async fn get_access_token(app: &tauri::AppHandle) -> Result<String, String> {
+    get_valid_github_token(app)
+        .await
         .ok_or_else(|| "Not connected to GitHub".to_string())
 }
 
 /// List user's nekotick-* repositories
 #[tauri::command]
 pub async fn list_github_repos(app: tauri::AppHandle) -> Result<Vec<RepositoryInfo>, String> {
-    let token = get_access_token(&app)?;
+    let token = get_access_token(&app).await?;
     let client = RepoClient::new(token);
     
     let repos = client
         .list_nekotick_repos()
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(redact_error)?;
     
     Ok(repos.into_iter().map(RepositoryInfo::from).collect())
 }
@@ -107,13 +69,13 @@ pub async fn get_repo_tree(
     repo: String,
     path: String,
 ) -> Result<Vec<TreeEntry>, String> {
-    let token = get_access_token(&app)?;
+    let token = get_access_token(&app).await?;
     let client = RepoClient::new(token);
     
     client
         .get_repo_contents(&owner, &repo, &path)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(redact_error)
 }
 
 /// Get file content from repository
@@ -124,17 +86,21 @@ pub async fn get_repo_file_content(
     repo: String,
     path: String,
 ) -> Result<FileContent, String> {
-    let token = get_access_token(&app)?;
+    let token = get_access_token(&app).await?;
     let client = RepoClient::new(token);
     
     client
         .get_file_content(&owner, &repo, &path)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(redact_error)
 }
 
 /// Update or create a file in repository
+///
+/// A missing or stale `sha` fails with a conflict unless `allow_overwrite`
+/// is set, in which case the current sha is fetched and the update retried.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn update_repo_file(
     app: tauri::AppHandle,
     owner: String,
@@ -143,14 +109,17 @@ pub async fn update_repo_file(
     content: String,
     sha: Option<String>,
     message: String,
+    allow_overwrite: Option<bool>,
 ) -> Result<CommitResult, String> {
-    let token = get_access_token(&app)?;
+    let token = get_access_token(&app).await?;
     let client = RepoClient::new(token);
     
-    client
-        .update_file(&owner, &repo, &path, &content, sha.as_deref(), &message)
-        .await
-        .map_err(|e| e.to_string())
+    let result = if allow_overwrite.unwrap_or(false) {
+        client.update_file_overwriting(&owner, &repo, &path, &content, sha.as_deref(), &message).await
+    } else {
+        client.update_file(&owner, &repo, &path, &content, sha.as_deref(), &message).await
+    };
+    result.map_err(redact_error)
 }
 
 /// Create a new repository with nekotick- prefix
@@ -161,13 +130,13 @@ pub async fn create_github_repo(
     private: bool,
     description: Option<String>,
 ) -> Result<RepositoryInfo, String> {
-    let token = get_access_token(&app)?;
+    let token = get_access_token(&app).await?;
     let client = RepoClient::new(token);
     
     let repo = client
         .create_repo(&name, private, description.as_deref())
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(redact_error)?;
     
     Ok(RepositoryInfo::from(repo))
 }
@@ -182,11 +151,48 @@ pub async fn delete_repo_file(
     sha: String,
     message: String,
 ) -> Result<CommitResult, String> {
-    let token = get_access_token(&app)?;
+    let token = get_access_token(&app).await?;
     let client = RepoClient::new(token);
     
     client
         .delete_file(&owner, &repo, &path, &sha, &message)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(redact_error)
+}
+
+/// Move or rename a file in a single commit, keeping its content
+#[tauri::command]
+pub async fn move_repo_file(
+    app: tauri::AppHandle,
+    owner: String,
+    repo: String,
+    from_path: String,
+    to_path: String,
+    message: String,
+) -> Result<CommitResult, String> {
+    let token = get_access_token(&app).await?;
+    let client = RepoClient::new(token);
+    
+    client
+        .move_file(&owner, &repo, &from_path, &to_path, &message)
+        .await
+        .map_err(redact_error)
+}
+
+/// Apply several file creates/updates/deletes as a single commit
+#[tauri::command]
+pub async fn commit_repo_files(
+    app: tauri::AppHandle,
+    owner: String,
+    repo: String,
+    ops: Vec<FileOp>,
+    message: String,
+) -> Result<CommitResult, String> {
+    let token = get_access_token(&app).await?;
+    let client = RepoClient::new(token);
+    
+    client
+        .create_tree_commit(&owner, &repo, &ops, &message)
+        .await
+        .map_err(redact_error)
 }
@@ -3,11 +3,14 @@
 //! Provides methods to interact with GitHub Repository API for browsing
 //! and managing user repositories with `nekotick-` prefix.
 
+use crate::github::gist_api::rate_limit_reset;
+use crate::github::server;
 use serde::{Deserialize, Serialize};
 use base64::{engine::general_purpose::STANDARD, Engine};
 
-const GITHUB_API_BASE: &str = "https://api.github.com";
 const NEKOTICK_PREFIX: &str = "nekotick-";
+/// Largest file the Contents API accepts
+pub const CONTENTS_API_SIZE_LIMIT: usize = 1024 * 1024;
 
 /// GitHub repository info (from GitHub API - uses snake_case)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +63,24 @@ pub struct CommitResult {
     pub html_url: Option<String>,
 }
 
+/// One change in a batch commit - from frontend (camelCase)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum FileOp {
+    /// Create or overwrite a file
+    Put { path: String, content: String },
+    /// Remove a file
+    Delete { path: String },
+}
+
+impl FileOp {
+    fn path(&self) -> &str {
+        match self {
+            FileOp::Put { path, .. } | FileOp::Delete { path } => path,
+        }
+    }
+}
+
 /// Create repository request
 #[derive(Debug, Clone, Serialize)]
 pub struct CreateRepoRequest {
@@ -91,6 +112,32 @@ struct ContentsResponse {
     encoding: Option<String>,
 }
 
+/// Git object reference (`{ "sha": ... }`) in Git Data API responses
+#[derive(Debug, Clone, Deserialize)]
+struct GitObject {
+    sha: String,
+}
+
+/// GitHub API response for `/git/ref/...`
+#[derive(Debug, Clone, Deserialize)]
+struct GitRefResponse {
+    object: GitObject,
+}
+
+/// GitHub API response for `/git/commits/{sha}`
+#[derive(Debug, Clone, Deserialize)]
+struct GitCommitResponse {
+    tree: GitObject,
+}
+
+/// A branch head a tree commit is built on
+#[derive(Debug, Clone)]
+struct BranchHead {
+    branch: String,
+    commit: String,
+    tree: String,
+}
+
 /// GitHub API response for commit
 #[derive(Debug, Clone, Deserialize)]
 #[allow(dead_code)]
@@ -125,24 +172,41 @@ pub enum RepoApiError {
     NotFound(String),
     #[error("Unauthorized")]
     Unauthorized,
-    #[error("Rate limited")]
-    RateLimited,
+    #[error("GitHub API rate limit exceeded (resets at {reset_at})")]
+    RateLimited { reset_at: i64 },
     #[error("Conflict: {0}")]
     Conflict(String),
+    #[error("GitHub timed out")]
+    Timeout,
+    #[error("{path} is too large for the Contents API ({size} bytes, limit {limit}). Commit it with commit_repo_files, which uploads through the Git Trees API.")]
+    TooLarge { path: String, size: usize, limit: usize },
+}
+
+impl RepoApiError {
+    /// Classify a failed request, keeping timeouts distinct from other network errors
+    fn from_request(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            RepoApiError::Timeout
+        } else {
+            RepoApiError::NetworkError(e.to_string())
+        }
+    }
 }
 
 /// GitHub Repository API client
 pub struct RepoClient {
     access_token: String,
     client: reqwest::Client,
+    api_base: String,
 }
 
 impl RepoClient {
-    /// Create a new Repository client
+    /// Create a new Repository client for the configured GitHub server
     pub fn new(access_token: String) -> Self {
         Self {
             access_token,
-            client: reqwest::Client::new(),
+            client: crate::http::build_http_client(),
+            api_base: server::current().api_base,
         }
     }
 
@@ -170,13 +234,8 @@ impl RepoClient {
             return RepoApiError::Unauthorized;
         }
         
-        if status == 403 {
-            // Check for rate limiting
-            if let Some(remaining) = response.headers().get("x-ratelimit-remaining") {
-                if remaining.to_str().unwrap_or("1") == "0" {
-                    return RepoApiError::RateLimited;
-                }
-            }
+        if let Some(reset_at) = rate_limit_reset(status, response.headers()) {
+            return RepoApiError::RateLimited { reset_at };
         }
         
         if status == 404 {
@@ -199,7 +258,7 @@ impl RepoClient {
         
         loop {
             let response = self.client
-                .get(format!("{}/user/repos", GITHUB_API_BASE))
+                .get(format!("{}/user/repos", self.api_base))
                 .headers(self.build_headers())
                 .query(&[
                     ("per_page", "100"),
@@ -209,7 +268,7 @@ impl RepoClient {
                 ])
                 .send()
                 .await
-                .map_err(|e| RepoApiError::NetworkError(e.to_string()))?;
+                .map_err(RepoApiError::from_request)?;
 
             if !response.status().is_success() {
                 return Err(self.handle_error(response).await);
@@ -250,9 +309,9 @@ impl RepoClient {
         path: &str,
     ) -> Result<Vec<TreeEntry>, RepoApiError> {
         let url = if path.is_empty() {
-            format!("{}/repos/{}/{}/contents", GITHUB_API_BASE, owner, repo)
+            format!("{}/repos/{}/{}/contents", self.api_base, owner, repo)
         } else {
-            format!("{}/repos/{}/{}/contents/{}", GITHUB_API_BASE, owner, repo, path)
+            format!("{}/repos/{}/{}/contents/{}", self.api_base, owner, repo, path)
         };
 
         let response = self.client
@@ -260,7 +319,7 @@ impl RepoClient {
             .headers(self.build_headers())
             .send()
             .await
-            .map_err(|e| RepoApiError::NetworkError(e.to_string()))?;
+            .map_err(RepoApiError::from_request)?;
 
         if !response.status().is_success() {
             return Err(self.handle_error(response).await);
@@ -292,14 +351,14 @@ impl RepoClient {
         repo: &str,
         path: &str,
     ) -> Result<FileContent, RepoApiError> {
-        let url = format!("{}/repos/{}/{}/contents/{}", GITHUB_API_BASE, owner, repo, path);
+        let url = format!("{}/repos/{}/{}/contents/{}", self.api_base, owner, repo, path);
 
         let response = self.client
             .get(&url)
             .headers(self.build_headers())
             .send()
             .await
-            .map_err(|e| RepoApiError::NetworkError(e.to_string()))?;
+            .map_err(RepoApiError::from_request)?;
 
         if !response.status().is_success() {
             return Err(self.handle_error(response).await);
@@ -330,6 +389,11 @@ impl RepoClient {
     }
 
     /// Update or create a file in repository
+    ///
+    /// Content over `CONTENTS_API_SIZE_LIMIT` fails with `TooLarge` without
+    /// a request; `create_tree_commit` handles larger files. Updating an
+    /// existing file without its current `sha`, or with a stale one, fails
+    /// with `Conflict`.
     pub async fn update_file(
         &self,
         owner: &str,
@@ -339,7 +403,15 @@ impl RepoClient {
         sha: Option<&str>,
         message: &str,
     ) -> Result<CommitResult, RepoApiError> {
-        let url = format!("{}/repos/{}/{}/contents/{}", GITHUB_API_BASE, owner, repo, path);
+        if content.len() > CONTENTS_API_SIZE_LIMIT {
+            return Err(RepoApiError::TooLarge {
+                path: path.to_string(),
+                size: content.len(),
+                limit: CONTENTS_API_SIZE_LIMIT,
+            });
+        }
+
+        let url = format!("{}/repos/{}/{}/contents/{}", self.api_base, owner, repo, path);
 
         // Encode content to base64
         let encoded_content = STANDARD.encode(content.as_bytes());
@@ -357,9 +429,14 @@ impl RepoClient {
             .json(&request)
             .send()
             .await
-            .map_err(|e| RepoApiError::NetworkError(e.to_string()))?;
+            .map_err(RepoApiError::from_request)?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if status == 409 || status == 422 {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(update_rejection(status, &error_text, path));
+        }
+        if !status.is_success() {
             return Err(self.handle_error(response).await);
         }
 
@@ -375,6 +452,30 @@ impl RepoClient {
         })
     }
 
+    /// `update_file` that overwrites whatever is on GitHub
+    ///
+    /// On a sha `Conflict` the file's current sha is fetched and the update
+    /// retried once with it.
+    pub async fn update_file_overwriting(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        content: &str,
+        sha: Option<&str>,
+        message: &str,
+    ) -> Result<CommitResult, RepoApiError> {
+        match self.update_file(owner, repo, path, content, sha, message).await {
+            Err(RepoApiError::Conflict(_)) => {
+                let current: ContentsResponse = self
+                    .get_repo_json(owner, repo, &format!("/contents/{}", path))
+                    .await?;
+                self.update_file(owner, repo, path, content, Some(&current.sha), message).await
+            }
+            result => result,
+        }
+    }
+
     /// Create a new repository with nekotick- prefix
     pub async fn create_repo(
         &self,
@@ -397,12 +498,12 @@ impl RepoClient {
         };
 
         let response = self.client
-            .post(format!("{}/user/repos", GITHUB_API_BASE))
+            .post(format!("{}/user/repos", self.api_base))
             .headers(self.build_headers())
             .json(&request)
             .send()
             .await
-            .map_err(|e| RepoApiError::NetworkError(e.to_string()))?;
+            .map_err(RepoApiError::from_request)?;
 
         if !response.status().is_success() {
             return Err(self.handle_error(response).await);
@@ -423,7 +524,7 @@ impl RepoClient {
         sha: &str,
         message: &str,
     ) -> Result<CommitResult, RepoApiError> {
-        let url = format!("{}/repos/{}/{}/contents/{}", GITHUB_API_BASE, owner, repo, path);
+        let url = format!("{}/repos/{}/{}/contents/{}", self.api_base, owner, repo, path);
 
         let request = serde_json::json!({
             "message": message,
@@ -436,7 +537,7 @@ impl RepoClient {
             .json(&request)
             .send()
             .await
-            .map_err(|e| RepoApiError::NetworkError(e.to_string()))?;
+            .map_err(RepoApiError::from_request)?;
 
         if !response.status().is_success() {
             return Err(self.handle_error(response).await);
@@ -453,6 +554,219 @@ impl RepoClient {
             html_url: commit_response.commit.html_url,
         })
     }
+
+    /// GET a JSON resource under `/repos/{owner}/{repo}`
+    async fn get_repo_json<T: serde::de::DeserializeOwned>(&self, owner: &str, repo: &str, path: &str) -> Result<T, RepoApiError> {
+        let response = self.client
+            .get(format!("{}/repos/{}/{}{}", self.api_base, owner, repo, path))
+            .headers(self.build_headers())
+            .send()
+            .await
+            .map_err(RepoApiError::from_request)?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| RepoApiError::ParseError(e.to_string()))
+    }
+
+    /// POST a JSON body to a resource under `/repos/{owner}/{repo}`
+    async fn post_repo_json<T: serde::de::DeserializeOwned>(
+        &self,
+        owner: &str,
+        repo: &str,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<T, RepoApiError> {
+        let response = self.client
+            .post(format!("{}/repos/{}/{}{}", self.api_base, owner, repo, path))
+            .headers(self.build_headers())
+            .json(body)
+            .send()
+            .await
+            .map_err(RepoApiError::from_request)?;
+
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|e| RepoApiError::ParseError(e.to_string()))
+    }
+
+    /// Apply several file changes as one commit on the default branch
+    ///
+    /// Uses the Git Data API: build a tree on top of the branch head, commit
+    /// it, then fast-forward the branch. Nothing is visible until the final
+    /// ref update, so a failure part way leaves the repository untouched. If
+    /// the branch moved in the meantime the update is refused with `Conflict`.
+    pub async fn create_tree_commit(
+        &self,
+        owner: &str,
+        repo: &str,
+        ops: &[FileOp],
+        message: &str,
+    ) -> Result<CommitResult, RepoApiError> {
+        let entries = tree_entries(ops)?;
+        let head = self.default_branch_head(owner, repo).await?;
+        self.commit_tree_entries(owner, repo, &head, entries, message).await
+    }
+
+    /// Move or rename a file in a single commit on the default branch
+    ///
+    /// The new path points at the existing blob, so the content is carried
+    /// over byte for byte and git sees a rename rather than a delete and a
+    /// create.
+    pub async fn move_file(
+        &self,
+        owner: &str,
+        repo: &str,
+        from_path: &str,
+        to_path: &str,
+        message: &str,
+    ) -> Result<CommitResult, RepoApiError> {
+        let head = self.default_branch_head(owner, repo).await?;
+        let source: ContentsResponse = self
+            .get_repo_json(owner, repo, &format!("/contents/{}?ref={}", from_path, head.commit))
+            .await?;
+        if source.content_type != "file" {
+            return Err(RepoApiError::ApiError(format!("{} is not a file", from_path)));
+        }
+
+        let entries = move_entries(from_path, to_path, &source.sha)?;
+        self.commit_tree_entries(owner, repo, &head, entries, message).await
+    }
+
+    /// Default branch, its head commit and that commit's tree
+    async fn default_branch_head(&self, owner: &str, repo: &str) -> Result<BranchHead, RepoApiError> {
+        let repository: Repository = self.get_repo_json(owner, repo, "").await?;
+        let branch = repository.default_branch;
+        let head: GitRefResponse = self
+            .get_repo_json(owner, repo, &format!("/git/ref/heads/{}", branch))
+            .await?;
+        let head_commit: GitCommitResponse = self
+            .get_repo_json(owner, repo, &format!("/git/commits/{}", head.object.sha))
+            .await?;
+        Ok(BranchHead {
+            branch,
+            commit: head.object.sha,
+            tree: head_commit.tree.sha,
+        })
+    }
+
+    /// Commit `entries` on top of `head` and fast-forward its branch
+    async fn commit_tree_entries(
+        &self,
+        owner: &str,
+        repo: &str,
+        head: &BranchHead,
+        entries: Vec<serde_json::Value>,
+        message: &str,
+    ) -> Result<CommitResult, RepoApiError> {
+        let branch = &head.branch;
+        let tree: GitObject = self
+            .post_repo_json(owner, repo, "/git/trees", &serde_json::json!({
+                "base_tree": head.tree,
+                "tree": entries
+            }))
+            .await?;
+        let commit: CommitInfo = self
+            .post_repo_json(owner, repo, "/git/commits", &serde_json::json!({
+                "message": message,
+                "tree": tree.sha,
+                "parents": [head.commit]
+            }))
+            .await?;
+
+        let response = self.client
+            .patch(format!("{}/repos/{}/{}/git/refs/heads/{}", self.api_base, owner, repo, branch))
+            .headers(self.build_headers())
+            .json(&serde_json::json!({ "sha": commit.sha, "force": false }))
+            .send()
+            .await
+            .map_err(RepoApiError::from_request)?;
+
+        // 422 means the update is no longer a fast-forward
+        if response.status() == 422 {
+            return Err(RepoApiError::Conflict(format!("{} changed while committing; reload and try again", branch)));
+        }
+        if !response.status().is_success() {
+            return Err(self.handle_error(response).await);
+        }
+
+        Ok(CommitResult {
+            sha: commit.sha,
+            message: commit.message,
+            html_url: commit.html_url,
+        })
+    }
+}
+
+/// Build the `tree` array for `/git/trees`, rejecting empty or ambiguous batches
+///
+/// A `null` sha removes the path from the base tree.
+fn tree_entries(ops: &[FileOp]) -> Result<Vec<serde_json::Value>, RepoApiError> {
+    if ops.is_empty() {
+        return Err(RepoApiError::ApiError("No file changes to commit".to_string()));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    ops.iter()
+        .map(|op| {
+            if !seen.insert(op.path()) {
+                return Err(RepoApiError::ApiError(format!("{} is changed more than once", op.path())));
+            }
+            Ok(match op {
+                FileOp::Put { path, content } => serde_json::json!({
+                    "path": path,
+                    "mode": "100644",
+                    "type": "blob",
+                    "content": content
+                }),
+                FileOp::Delete { path } => serde_json::json!({
+                    "path": path,
+                    "mode": "100644",
+                    "type": "blob",
+                    "sha": null
+                }),
+            })
+        })
+        .collect()
+}
+
+/// Classify a 409/422 from a Contents API update
+///
+/// GitHub answers 409 when `sha` doesn't match the file and 422 when `sha`
+/// is missing for a file that exists; other 422s are plain API errors.
+fn update_rejection(status: reqwest::StatusCode, error_text: &str, path: &str) -> RepoApiError {
+    if status == 409 || error_text.contains("sha") {
+        return RepoApiError::Conflict(format!(
+            "{} was changed on GitHub or its sha is missing; fetch the file again for its current sha",
+            path
+        ));
+    }
+    RepoApiError::ApiError(format!("{}: {}", status, error_text))
+}
+
+/// Tree entries that move the blob `sha` from `from_path` to `to_path`
+fn move_entries(from_path: &str, to_path: &str, sha: &str) -> Result<Vec<serde_json::Value>, RepoApiError> {
+    let (from, to) = (from_path.trim_matches('/'), to_path.trim_matches('/'));
+    if to.is_empty() {
+        return Err(RepoApiError::ApiError("Destination path is empty".to_string()));
+    }
+    if from == to {
+        return Err(RepoApiError::ApiError(format!("{} is already at that path", from)));
+    }
+    Ok(vec![
+        serde_json::json!({ "path": to, "mode": "100644", "type": "blob", "sha": sha }),
+        serde_json::json!({ "path": from, "mode": "100644", "type": "blob", "sha": null }),
+    ])
 }
 
 /// Get display name by removing nekotick- prefix
@@ -470,3 +784,72 @@ pub fn filter_nekotick_repos(repos: Vec<Repository>) -> Vec<Repository> {
         .filter(|r| r.name.starts_with(NEKOTICK_PREFIX))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_entries_for_put_and_delete() {
+        let ops = vec![
+            FileOp::Put { path: "notes/a.md".to_string(), content: "# A".to_string() },
+            FileOp::Delete { path: "notes/b.md".to_string() },
+        ];
+        let entries = tree_entries(&ops).unwrap();
+        assert_eq!(entries[0]["content"], "# A");
+        assert_eq!(entries[1]["path"], "notes/b.md");
+        assert!(entries[1]["sha"].is_null());
+    }
+
+    #[test]
+    fn test_tree_entries_rejects_empty_and_duplicate_paths() {
+        assert!(tree_entries(&[]).is_err());
+        let ops = vec![
+            FileOp::Put { path: "a.md".to_string(), content: String::new() },
+            FileOp::Delete { path: "a.md".to_string() },
+        ];
+        assert!(tree_entries(&ops).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_file_rejects_oversized_content() {
+        let content = "x".repeat(CONTENTS_API_SIZE_LIMIT + 1);
+        let err = RepoClient::new("token".to_string())
+            .update_file("octo", "nekotick-notes", "big.md", &content, None, "Update big.md")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RepoApiError::TooLarge { size, .. } if size == CONTENTS_API_SIZE_LIMIT + 1));
+    }
+
+    #[test]
+    fn test_update_rejection_detects_sha_conflicts() {
+        use reqwest::StatusCode;
+
+        let missing = update_rejection(StatusCode::UNPROCESSABLE_ENTITY, r#"{"message":"Invalid request.\n\n\"sha\" wasn't supplied."}"#, "a.md");
+        assert!(matches!(missing, RepoApiError::Conflict(message) if message.starts_with("a.md")));
+
+        let stale = update_rejection(StatusCode::CONFLICT, r#"{"message":"a.md does not match 0123abc"}"#, "a.md");
+        assert!(matches!(stale, RepoApiError::Conflict(_)));
+
+        let invalid = update_rejection(StatusCode::UNPROCESSABLE_ENTITY, r#"{"message":"Invalid path"}"#, "a.md");
+        assert!(matches!(invalid, RepoApiError::ApiError(_)));
+    }
+
+    #[test]
+    fn test_move_entries_reuse_blob_and_remove_source() {
+        let entries = move_entries("notes/a.md", "archive/a.md", "abc123").unwrap();
+        assert_eq!(entries[0]["path"], "archive/a.md");
+        assert_eq!(entries[0]["sha"], "abc123");
+        assert_eq!(entries[1]["path"], "notes/a.md");
+        assert!(entries[1]["sha"].is_null());
+
+        assert!(move_entries("a.md", "/a.md", "abc123").is_err());
+        assert!(move_entries("a.md", "", "abc123").is_err());
+    }
+
+    #[test]
+    fn test_file_op_from_frontend() {
+        let op: FileOp = serde_json::from_str(r#"{"op":"delete","path":"a.md"}"#).unwrap();
+        assert!(matches!(op, FileOp::Delete { path } if path == "a.md"));
+    }
+}
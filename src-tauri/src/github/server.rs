@@ -0,0 +1,175 @@
+//! GitHub server selection (github.com or GitHub Enterprise Server)
+//!
+//! The configured server is loaded from `github_server.json` at startup and
+//! read by every GitHub client built afterwards, the same way `http` applies
+//! the proxy setting. Enterprise Server serves its REST API under `/api/v3`
+//! on the web host, and git clones from the web host as well.
+
+use crate::github::commands::clear_github_server_state;
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+use url::Url;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const GITHUB_SERVER_FILE: &str = "github_server.json";
+
+pub const DOTCOM_WEB_BASE: &str = "https://github.com";
+pub const DOTCOM_API_BASE: &str = "https://api.github.com";
+
+/// Server set in the app, applied to clients built afterwards
+static CONFIGURED_SERVER: RwLock<Option<GitHubServer>> = RwLock::new(None);
+
+/// Base URLs of a GitHub server, without trailing slashes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitHubServer {
+    pub web_base: String,
+    pub api_base: String,
+}
+
+impl Default for GitHubServer {
+    fn default() -> Self {
+        Self {
+            web_base: DOTCOM_WEB_BASE.to_string(),
+            api_base: DOTCOM_API_BASE.to_string(),
+        }
+    }
+}
+
+impl GitHubServer {
+    /// Server for a web base URL such as `https://github.example.com`
+    ///
+    /// Only https URLs without credentials, query or fragment are accepted;
+    /// github.com itself maps to the default server.
+    pub fn from_base_url(base_url: &str) -> Result<Self, String> {
+        let url = Url::parse(base_url.trim()).map_err(|e| format!("Invalid GitHub URL: {}", e))?;
+        if url.scheme() != "https" {
+            return Err("GitHub URL must use https".to_string());
+        }
+        if !url.username().is_empty() || url.password().is_some() || url.query().is_some() || url.fragment().is_some() {
+            return Err("GitHub URL must not contain credentials, a query or a fragment".to_string());
+        }
+        let host = url.host_str().ok_or("GitHub URL has no host")?;
+        if host.eq_ignore_ascii_case("github.com") || host.eq_ignore_ascii_case("api.github.com") {
+            return Ok(Self::default());
+        }
+
+        let web_base = url.as_str().trim_end_matches('/').to_string();
+        Ok(Self {
+            api_base: format!("{}/api/v3", web_base),
+            web_base,
+        })
+    }
+
+    /// Whether this is github.com rather than an Enterprise Server
+    pub fn is_dotcom(&self) -> bool {
+        self.web_base == DOTCOM_WEB_BASE
+    }
+
+    /// HTTPS clone URL of a repository
+    pub fn clone_url(&self, owner: &str, repo: &str) -> String {
+        format!("{}/{}/{}.git", self.web_base, owner, repo)
+    }
+}
+
+/// Persisted server setting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GitHubServerConfig {
+    base_url: Option<String>,
+}
+
+/// The configured server, or github.com
+pub fn current() -> GitHubServer {
+    CONFIGURED_SERVER.read().ok().and_then(|s| s.clone()).unwrap_or_default()
+}
+
+fn set_current(server: GitHubServer) {
+    if let Ok(mut current) = CONFIGURED_SERVER.write() {
+        *current = Some(server);
+    }
+}
+
+/// Load the persisted server setting into memory
+///
+/// An invalid stored URL falls back to github.com.
+pub fn load_github_server_config(store_dir: &Path) {
+    let config: GitHubServerConfig = fs::read_to_string(store_dir.join(GITHUB_SERVER_FILE))
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default();
+    let server = config
+        .base_url
+        .and_then(|url| GitHubServer::from_base_url(&url).ok())
+        .unwrap_or_default();
+    set_current(server);
+}
+
+/// Set the GitHub server by its web URL (`None` or blank for github.com)
+///
+/// Switching servers signs out of GitHub: the stored token, sync metadata
+/// and merge base belong to the previous server. Reconnect afterwards.
+#[tauri::command]
+pub async fn set_github_base_url(app: tauri::AppHandle, base_url: Option<String>) -> Result<GitHubServer, String> {
+    let base_url = base_url.filter(|u| !u.trim().is_empty());
+    let server = match &base_url {
+        Some(url) => GitHubServer::from_base_url(url)?,
+        None => GitHubServer::default(),
+    };
+
+    let data_dir = paths::data_dir(&app)?;
+    if server != current() {
+        clear_github_server_state(&data_dir)?;
+    }
+
+    let store_dir = data_dir
+        .join(NEKOTICK_FOLDER)
+        .join(STORE_FOLDER);
+    fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
+    let config = GitHubServerConfig {
+        base_url: (!server.is_dotcom()).then(|| server.web_base.clone()),
+    };
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(store_dir.join(GITHUB_SERVER_FILE), content).map_err(|e| e.to_string())?;
+
+    set_current(server.clone());
+    Ok(server)
+}
+
+/// The GitHub server in use
+#[tauri::command]
+pub async fn get_github_server() -> Result<GitHubServer, String> {
+    Ok(current())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enterprise_api_lives_under_api_v3() {
+        let server = GitHubServer::from_base_url("https://github.example.com/").unwrap();
+        assert_eq!(server.web_base, "https://github.example.com");
+        assert_eq!(server.api_base, "https://github.example.com/api/v3");
+        assert_eq!(server.clone_url("octo", "nekotick-notes"), "https://github.example.com/octo/nekotick-notes.git");
+        assert!(!server.is_dotcom());
+    }
+
+    #[test]
+    fn test_dotcom_urls_map_to_default() {
+        assert_eq!(GitHubServer::from_base_url("https://github.com").unwrap(), GitHubServer::default());
+        assert_eq!(GitHubServer::from_base_url("https://api.github.com/").unwrap(), GitHubServer::default());
+    }
+
+    #[test]
+    fn test_rejects_insecure_or_unusual_urls() {
+        assert!(GitHubServer::from_base_url("http://github.example.com").is_err());
+        assert!(GitHubServer::from_base_url("https://user:pw@github.example.com").is_err());
+        assert!(GitHubServer::from_base_url("https://github.example.com/?x=1").is_err());
+        assert!(GitHubServer::from_base_url("github.example.com").is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Shared HTTP client construction
+//!
+//! Every reqwest client is built here so proxy settings apply uniformly.
+//! A proxy URL configured in the app overrides the `HTTPS_PROXY` /
+//! `HTTP_PROXY` environment variables; `NO_PROXY` exclusions apply to both.
+//! Clients also share request and connect timeouts so a hung connection
+//! can't stall a sync indefinitely.
+
+use crate::paths;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::Duration;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const NETWORK_CONFIG_FILE: &str = "network_config.json";
+
+/// Limit for a whole request, including reading the response body
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Limit for establishing a connection
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Proxy URL set in the app, applied to clients built afterwards
+static CONFIGURED_PROXY: RwLock<Option<String>> = RwLock::new(None);
+
+/// Persisted network settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkConfig {
+    pub proxy_url: Option<String>,
+}
+
+/// Proxies to use for each scheme, plus hosts that bypass them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxySettings {
+    pub https: Option<String>,
+    pub http: Option<String>,
+    pub no_proxy: Vec<String>,
+}
+
+/// Read an env var in either case, ignoring empty values
+fn env_var(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> Option<String> {
+    lookup(name)
+        .or_else(|| lookup(&name.to_lowercase()))
+        .filter(|v| !v.trim().is_empty())
+}
+
+/// Combine the app-configured proxy with proxy environment variables
+pub fn resolve_proxy_settings(
+    lookup: impl Fn(&str) -> Option<String>,
+    configured: Option<&str>,
+) -> ProxySettings {
+    let configured = configured.filter(|u| !u.trim().is_empty()).map(str::to_string);
+    let no_proxy = env_var(&lookup, "NO_PROXY")
+        .map(|v| {
+            v.split(',')
+                .map(|h| h.trim().to_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ProxySettings {
+        https: configured.clone().or_else(|| env_var(&lookup, "HTTPS_PROXY")),
+        http: configured.or_else(|| env_var(&lookup, "HTTP_PROXY")),
+        no_proxy,
+    }
+}
+
+/// Whether `host` matches a `NO_PROXY` entry (`*`, exact host, or domain suffix)
+pub fn is_no_proxy_host(no_proxy: &[String], host: &str) -> bool {
+    let host = host.to_lowercase();
+    no_proxy.iter().any(|entry| {
+        if entry == "*" {
+            return true;
+        }
+        let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+        host == domain || host.ends_with(&format!(".{}", domain))
+    })
+}
+
+impl ProxySettings {
+    /// Proxy URL to use for a request URL, if any
+    pub fn proxy_for(&self, url: &reqwest::Url) -> Option<&str> {
+        if url.host_str().is_some_and(|h| is_no_proxy_host(&self.no_proxy, h)) {
+            return None;
+        }
+        match url.scheme() {
+            "https" => self.https.as_deref(),
+            "http" => self.http.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// Client builder with the shared timeouts applied
+fn client_builder() -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .connect_timeout(CONNECT_TIMEOUT)
+}
+
+/// Build a client using explicit proxy settings
+pub fn build_http_client_with(settings: ProxySettings) -> Result<reqwest::Client, String> {
+    let mut builder = client_builder();
+    if settings.https.is_some() || settings.http.is_some() {
+        for url in [&settings.https, &settings.http].into_iter().flatten() {
+            reqwest::Url::parse(url).map_err(|e| format!("Invalid proxy URL {}: {}", url, e))?;
+        }
+        builder = builder.proxy(reqwest::Proxy::custom(move |url| {
+            settings.proxy_for(url).map(|p| p.to_string())
+        }));
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Build an HTTP client honoring the configured proxy and proxy env vars
+pub fn build_http_client() -> reqwest::Client {
+    let configured = CONFIGURED_PROXY.read().ok().and_then(|p| p.clone());
+    let settings = resolve_proxy_settings(|name| std::env::var(name).ok(), configured.as_deref());
+    build_http_client_with(settings).unwrap_or_else(|e| {
+        log::warn!("Falling back to a direct HTTP client: {}", e);
+        client_builder().build().unwrap_or_default()
+    })
+}
+
+/// Load the persisted proxy setting into memory
+pub fn load_network_config(store_dir: &Path) {
+    let config: NetworkConfig = fs::read_to_string(store_dir.join(NETWORK_CONFIG_FILE))
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok())
+        .unwrap_or_default();
+    if let Ok(mut proxy) = CONFIGURED_PROXY.write() {
+        *proxy = config.proxy_url;
+    }
+}
+
+/// Set (or clear with `None`) the app-wide proxy URL
+#[tauri::command]
+pub async fn set_http_proxy(app: tauri::AppHandle, proxy_url: Option<String>) -> Result<(), String> {
+    let proxy_url = proxy_url.filter(|u| !u.trim().is_empty());
+    if let Some(url) = &proxy_url {
+        reqwest::Proxy::all(url.as_str()).map_err(|e| format!("Invalid proxy URL: {}", e))?;
+    }
+
+    let store_dir = paths::data_dir(&app)?
+        .join(NEKOTICK_FOLDER)
+        .join(STORE_FOLDER);
+    fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
+    let config = NetworkConfig { proxy_url: proxy_url.clone() };
+    let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(store_dir.join(NETWORK_CONFIG_FILE), content).map_err(|e| e.to_string())?;
+
+    if let Ok(mut proxy) = CONFIGURED_PROXY.write() {
+        *proxy = proxy_url;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    fn url(s: &str) -> reqwest::Url {
+        reqwest::Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_env_proxies_per_scheme() {
+        let settings = resolve_proxy_settings(
+            env(&[("HTTPS_PROXY", "http://secure:8443"), ("http_proxy", "http://plain:8080")]),
+            None,
+        );
+        assert_eq!(settings.proxy_for(&url("https://api.github.com/user")), Some("http://secure:8443"));
+        assert_eq!(settings.proxy_for(&url("http://example.com/")), Some("http://plain:8080"));
+    }
+
+    #[test]
+    fn test_configured_proxy_overrides_env() {
+        let settings = resolve_proxy_settings(env(&[("HTTPS_PROXY", "http://env:1")]), Some("http://app:2"));
+        assert_eq!(settings.https.as_deref(), Some("http://app:2"));
+        assert_eq!(settings.http.as_deref(), Some("http://app:2"));
+    }
+
+    #[test]
+    fn test_no_proxy_excludes_nekotick_api() {
+        let settings = resolve_proxy_settings(
+            env(&[("HTTPS_PROXY", "http://corp:3128"), ("NO_PROXY", "localhost, .nekotick.com")]),
+            None,
+        );
+        assert_eq!(settings.proxy_for(&url("https://api.nekotick.com/check_pro")), None);
+        assert_eq!(settings.proxy_for(&url("https://nekotick.com/")), None);
+        assert_eq!(settings.proxy_for(&url("https://api.github.com/")), Some("http://corp:3128"));
+    }
+
+    #[test]
+    fn test_no_proxy_matching() {
+        let entries = vec!["api.nekotick.com".to_string(), "*.internal".to_string()];
+        assert!(is_no_proxy_host(&entries, "API.nekotick.com"));
+        assert!(is_no_proxy_host(&entries, "git.internal"));
+        assert!(!is_no_proxy_host(&entries, "nekotick.com"));
+        assert!(!is_no_proxy_host(&entries, "evilapi.nekotick.com.example"));
+        assert!(is_no_proxy_host(&["*".to_string()], "anything"));
+    }
+
+    #[test]
+    fn test_invalid_proxy_url_rejected() {
+        let settings = resolve_proxy_settings(env(&[]), Some("not a url"));
+        assert!(build_http_client_with(settings).is_err());
+        assert!(build_http_client_with(ProxySettings::default()).is_ok());
+    }
+}
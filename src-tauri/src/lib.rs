@@ -1,143 +1,209 @@
-use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, LogicalPosition};
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder, LogicalPosition};
 use tauri::window::Color;
 
 // GitHub sync module
 pub mod github;
 
-// Create drag overlay window
+// data.json parsing and validation
+pub mod tasks;
+
+// Local storage health checks
+pub mod storage;
+
+// Drag overlay card rendering
+pub mod overlay;
+
+// Backup-then-wipe of local app state
+pub mod reset;
+
+// Shared HTTP client and proxy settings
+pub mod http;
+
+// Rolling backups of local data files
+pub mod backup;
+
+// WebDAV sync module
+pub mod webdav;
+
+// Structured errors for sync commands
+pub mod sync_error;
+
+// Per-backend locks so syncs can't overlap
+pub mod sync_lock;
+
+// Sync backend health checks
+pub mod diagnostics;
+
+// Opt-in end-to-end encryption of synced data
+pub mod sync_crypto;
+
+// Background sync on a configurable interval
+pub mod auto_sync;
+
+// Task priority colors shared by the overlay and exports
+pub mod priority;
+
+// Data directory resolution (app data dir or portable override)
+pub mod paths;
+
+// Leveled logging to stderr and an optional log file
+pub mod logging;
+
+// Masking of tokens and other secrets in logs and error messages
+pub mod redact;
+
+// Summary of remote sync data shown before a restore
+pub mod remote_preview;
+
+// Pushes that failed offline, retried when the backend is reachable
+pub mod sync_queue;
+
+// Whether the OS is in dark mode, read from the app's own windows (light if unknown)
+fn detect_system_theme(app: &AppHandle) -> bool {
+    app.webview_windows()
+        .into_iter()
+        .filter(|(label, _)| !label.starts_with(overlay::DRAG_WINDOW_LABEL_PREFIX))
+        .find_map(|(_, window)| window.theme().ok())
+        .is_some_and(|theme| theme == tauri::Theme::Dark)
+}
+
+// Logical bounds of the monitor under (x, y), or the nearest one if the point is off-screen
+fn monitor_bounds_at(app: &AppHandle, x: f64, y: f64) -> Option<overlay::ScreenBounds> {
+    let monitors: Vec<_> = app
+        .available_monitors()
+        .ok()?
+        .iter()
+        .map(|m| overlay::ScreenBounds::from_physical(m.position().x, m.position().y, m.size().width, m.size().height, m.scale_factor()))
+        .collect();
+    overlay::nearest_bounds(&monitors, x, y)
+}
+
+// Where a drag card with its grip at the cursor (x, y) is placed, kept on one monitor
 #[tauri::command]
-async fn create_drag_window(app: AppHandle, content: String, x: f64, y: f64, width: f64, height: f64, is_done: bool, is_dark: bool, color: Option<String>) -> Result<(), String> {
-    // Close existing drag window if any
-    if let Some(existing) = app.get_webview_window("drag-overlay") {
+async fn measure_drag_window_position(app: AppHandle, x: f64, y: f64, width: f64, height: f64) -> Result<overlay::OverlayPosition, String> {
+    Ok(overlay::place_overlay(x, y, width, height, monitor_bounds_at(&app, x, y)))
+}
+
+// Create drag overlay window, returning where it was placed
+#[tauri::command]
+async fn create_drag_window(app: AppHandle, registry: State<'_, overlay::DragWindowRegistry>, placement: overlay::DragPlacement, mut params: overlay::OverlayParams) -> Result<overlay::OverlayPosition, String> {
+    let overlay::DragPlacement { drag_id, x, y, width, height } = placement;
+    params.is_dark.get_or_insert_with(|| detect_system_theme(&app));
+    let config = params.window_config()?;
+    let label = overlay::drag_window_label(&drag_id)?;
+
+    // Replace an existing overlay for the same drag; other drags keep theirs
+    if let Some(existing) = app.get_webview_window(&label) {
         let _ = existing.destroy();
     }
 
-    // Theme-based colors
-    let (bg_color, border_color, text_color, text_muted) = if is_dark {
-        ("#18181b", "#3f3f46", "#fafafa", "#71717a")
-    } else {
-        ("#fff", "#e5e5e5", "#18181b", "#a1a1aa")
-    };
-    
-    // Task color - Apple style colors
-    let task_color = match color.as_deref() {
-        Some("red") => "#FE002D",
-        Some("orange") => "#FF8500",
-        Some("yellow") => "#FEC900",
-        Some("green") => "#63DA38",
-        Some("blue") => "#008BFE",
-        Some("purple") => "#DD11E8",
-        Some("brown") => "#B47D58",
-        _ => text_muted,
-    };
-    
-    let has_color = color.is_some() && color.as_deref() != Some("default");
-    
-    // Style for completed tasks
-    let content_style = if is_done {
-        format!("text-decoration:line-through;color:{}", text_muted)
-    } else {
-        String::new()
-    };
-    
-    let checkbox_html = if is_done {
-        format!("<svg class=\"checkbox\" viewBox=\"0 0 16 16\"><rect x=\"0.5\" y=\"0.5\" width=\"15\" height=\"15\" rx=\"2\" fill=\"{}\" stroke=\"{}\"/><path d=\"M4 8l3 3 5-6\" stroke=\"white\" stroke-width=\"2\" fill=\"none\"/></svg>", text_color, text_color)
-    } else if has_color {
-        format!("<div class=\"checkbox\" style=\"border:2px solid {}\"></div>", task_color)
-    } else {
-        format!("<div class=\"checkbox\" style=\"border-color:{}\"></div>", text_muted)
-    };
-
-    // HTML content - transparent background, card fills window
-    let html = format!(r#"<!DOCTYPE html>
-<html style="background:transparent!important">
-<head>
-<style>
-*{{margin:0;padding:0;box-sizing:border-box}}
-html,body{{background:transparent!important;overflow:hidden;width:100%;height:100%}}
-body{{font-family:system-ui,-apple-system,sans-serif;display:flex}}
-.card{{
-  background:{};
-  border:1px solid {};
-  border-radius:4px;
-  padding:8px 12px;
-  display:flex;
-  align-items:start;
-  gap:8px;
-  font-size:14px;
-  color:{};
-  width:100%;
-  height:100%;
-}}
-.grip{{color:{}}}
-.checkbox{{width:16px;height:16px;border:1px solid {};border-radius:3px;flex-shrink:0;margin-top:2px}}
-.content{{flex:1;white-space:pre-wrap;word-break:break-word;overflow-wrap:anywhere}}
-</style>
-</head>
-<body style="background:transparent!important">
-<div class="card">
-<div class="grip">⋮⋮</div>
-{}
-<span class="content" style="{}">{}</span>
-</div>
-</body>
-</html>"#, bg_color, border_color, text_color, text_muted, text_muted, checkbox_html, content_style, content);
+    let html = overlay::build_overlay_html(params.clone());
+    let animate = params.animate;
+    let position = overlay::place_overlay(x, y, width, height, monitor_bounds_at(&app, x, y));
 
     // Create transparent window - hidden first, show after setup
     let window = WebviewWindowBuilder::new(
         &app,
-        "drag-overlay",
+        &label,
         WebviewUrl::default(),
     )
     .title("")
     .inner_size(width, height)
-    .position(x - 20.0, y - (height / 2.0))
+    .position(position.x, position.y)
     .decorations(false)
     .shadow(false)
     .background_color(Color(0, 0, 0, 0))
-    .always_on_top(true)
-    .skip_taskbar(true)
+    .always_on_top(config.always_on_top)
+    .skip_taskbar(config.skip_taskbar)
     .resizable(false)
-    .focused(false)
+    .focused(config.focused)
     .visible(false)
     .build()
     .map_err(|e| e.to_string())?;
+    registry.insert(&label, params);
 
     // Ignore cursor events so drag continues
-    window.set_ignore_cursor_events(true).map_err(|e| e.to_string())?;
+    window.set_ignore_cursor_events(config.ignore_cursor_events).map_err(|e| e.to_string())?;
 
     // Inject HTML content
-    window.eval(&format!(r#"document.write(`{}`); document.close();"#, html.replace('`', "\\`")))
+    window.eval(&overlay::document_write_script(&html))
         .map_err(|e| e.to_string())?;
 
     // Show window
     window.show().map_err(|e| e.to_string())?;
 
-    Ok(())
+    if animate {
+        window.eval(overlay::FADE_IN_SCRIPT).map_err(|e| e.to_string())?;
+    }
+
+    Ok(position)
 }
 
-// Update drag window position
+// Re-render open drag overlays in the dark or light theme without recreating them
 #[tauri::command]
-async fn update_drag_window_position(app: AppHandle, x: f64, y: f64) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("drag-overlay") {
-        // Get window height for vertical centering
-        let size = window.outer_size().unwrap_or(tauri::PhysicalSize::new(0, 36));
-        let half_height = (size.height as f64) / 2.0;
-        window.set_position(LogicalPosition::new(x - 20.0, y - half_height))
-            .map_err(|e| e.to_string())?;
+async fn set_drag_window_theme(app: AppHandle, registry: State<'_, overlay::DragWindowRegistry>, dark: bool) -> Result<(), String> {
+    for (label, params) in registry.set_theme(dark) {
+        if let Some(window) = app.get_webview_window(&label) {
+            window.eval(&overlay::document_write_script(&overlay::rerender_overlay_html(params)))
+                .map_err(|e| e.to_string())?;
+        }
     }
     Ok(())
 }
 
-// Destroy drag window
+// Update drag window position, returning where it was placed (`None` if there is no such window)
 #[tauri::command]
-async fn destroy_drag_window(app: AppHandle) -> Result<(), String> {
-    if let Some(window) = app.get_webview_window("drag-overlay") {
+async fn update_drag_window_position(app: AppHandle, drag_id: String, x: f64, y: f64) -> Result<Option<overlay::OverlayPosition>, String> {
+    let Some(window) = app.get_webview_window(&overlay::drag_window_label(&drag_id)?) else {
+        return Ok(None);
+    };
+    // Window size in logical pixels for centering and clamping
+    let size = window.outer_size().unwrap_or(tauri::PhysicalSize::new(0, 36));
+    let scale = window.scale_factor().unwrap_or(1.0);
+    let (width, height) = (size.width as f64 / scale, size.height as f64 / scale);
+
+    let position = overlay::place_overlay(x, y, width, height, monitor_bounds_at(&app, x, y));
+    window.set_position(LogicalPosition::new(position.x, position.y))
+        .map_err(|e| e.to_string())?;
+    Ok(Some(position))
+}
+
+// Destroy drag window, fading it out first if it faded in (or `animate` says so)
+#[tauri::command]
+async fn destroy_drag_window(app: AppHandle, registry: State<'_, overlay::DragWindowRegistry>, drag_id: String, animate: Option<bool>) -> Result<(), String> {
+    let label = overlay::drag_window_label(&drag_id)?;
+    let params = registry.remove(&label);
+    if let Some(window) = app.get_webview_window(&label) {
+        if animate.unwrap_or_else(|| params.is_some_and(|p| p.animate)) {
+            let _ = window.eval(overlay::FADE_OUT_SCRIPT);
+            tokio::time::sleep(std::time::Duration::from_millis(overlay::FADE_DURATION_MS)).await;
+            // A new overlay for the same drag replaced this one while it faded
+            if registry.labels().contains(&label) {
+                return Ok(());
+            }
+        }
         window.destroy().map_err(|e| e.to_string())?;
     }
     Ok(())
 }
 
+// Destroy every drag window, including orphans the frontend lost track of
+#[tauri::command]
+async fn destroy_all_drag_windows(app: AppHandle, registry: State<'_, overlay::DragWindowRegistry>) -> Result<(), String> {
+    let mut labels = registry.drain();
+    labels.extend(
+        app.webview_windows()
+            .into_keys()
+            .filter(|label| label.starts_with(overlay::DRAG_WINDOW_LABEL_PREFIX)),
+    );
+    for label in labels {
+        if let Some(window) = app.get_webview_window(&label) {
+            let _ = window.destroy();
+        }
+    }
+    Ok(())
+}
+
 // Toggle fullscreen with smooth animation
 #[tauri::command]
 async fn toggle_fullscreen(app: AppHandle) -> Result<(), String> {
@@ -242,23 +308,76 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(overlay::DragWindowRegistry::default())
+        .manage(sync_lock::SyncLocks::default())
+        .setup(|app| {
+            // Resolve the data directory once; every module reads it from here
+            let data_paths = paths::DataPaths::resolve(app.handle());
+
+            // Start logging first so the rest of setup can report problems
+            app.manage(logging::init(data_paths.as_ref().ok().map(|p| p.base())));
+
+            if let Ok(data_paths) = data_paths {
+                let store_dir = data_paths.base().join(".nekotick").join("store");
+                app.manage(data_paths);
+
+                // Apply the saved proxy and GitHub server before any client is built
+                http::load_network_config(&store_dir);
+                github::server::load_github_server_config(&store_dir);
+
+                // Report an unwritable data directory early instead of on the first save
+                let result = storage::probe_writable(&store_dir);
+                if result != storage::StorageWritability::Writable {
+                    log::error!("Data directory is not writable: {:?}", result);
+                }
+            }
+
+            auto_sync::start(app.handle());
+            sync_queue::start(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             create_drag_window,
+            overlay::preview_overlay_html,
+            priority::validate_priority,
+            logging::get_log_path,
+            paths::get_data_dir,
+            logging::set_log_level,
+            github::credential_store::get_device_id_source,
+            remote_preview::preview_remote_data,
+            github::server::set_github_base_url,
+            github::server::get_github_server,
             update_drag_window_position,
+            measure_drag_window_position,
+            set_drag_window_theme,
             destroy_drag_window,
+            destroy_all_drag_windows,
             toggle_fullscreen,
             create_new_window,
             set_window_resizable,
             focus_window,
             move_to_trash,
             github::commands::github_auth,
+            github::commands::github_auth_with_token,
             github::commands::github_disconnect,
+            github::commands::refresh_github_token,
+            github::commands::get_github_primary_email,
+            github::commands::get_github_rate_limit,
+            github::commands::diff_against_github_revision,
+            github::commands::disconnect_all,
             github::commands::get_github_sync_status,
             github::commands::check_github_remote_data,
+            github::commands::set_github_sync_profile,
             github::commands::sync_to_github,
             github::commands::restore_from_github,
+            github::commands::get_github_revisions,
+            github::commands::restore_github_revision,
             github::commands::sync_github_bidirectional,
+            github::commands::set_github_conflict_strategy,
+            github::commands::set_gist_visibility,
+            github::commands::import_shared_gist,
             github::commands::check_pro_status,
+            github::commands::poll_remote_changes,
             // GitHub Repository commands
             github::repo_commands::list_github_repos,
             github::repo_commands::get_repo_tree,
@@ -266,19 +385,63 @@ pub fn run() {
             github::repo_commands::update_repo_file,
             github::repo_commands::create_github_repo,
             github::repo_commands::delete_repo_file,
+            github::repo_commands::move_repo_file,
+            github::repo_commands::commit_repo_files,
             // Git local operations
             github::git_commands::clone_github_repo,
             github::git_commands::is_repo_cloned,
             github::git_commands::get_repo_local_path,
             github::git_commands::pull_github_repo,
+            github::git_commands::fetch_github_repo,
+            github::git_commands::get_repo_ahead_behind,
             github::git_commands::push_github_repo,
+            github::git_commands::pull_stale_repos,
             github::git_commands::commit_repo_changes,
             github::git_commands::get_repo_status,
             github::git_commands::get_repo_log,
+            github::git_commands::get_repo_branches,
+            github::git_commands::checkout_repo_branch,
             github::git_commands::get_file_diff,
+            github::git_commands::get_repo_commit_diff,
+            github::git_commands::get_repo_file_last_commit,
             github::git_commands::delete_local_repo,
-            github::git_commands::list_local_repos
+            github::git_commands::list_local_repos,
+            github::git_commands::search_local_repos,
+            // WebDAV sync commands
+            webdav::commands::webdav_connect,
+            webdav::commands::webdav_disconnect,
+            webdav::commands::get_webdav_sync_status,
+            webdav::commands::sync_to_webdav,
+            webdav::commands::restore_from_webdav,
+            webdav::commands::sync_webdav_bidirectional,
+            webdav::commands::set_webdav_conflict_strategy,
+            diagnostics::run_sync_diagnostics,
+            sync_crypto::set_sync_passphrase,
+            sync_crypto::clear_sync_passphrase,
+            auto_sync::set_auto_sync_interval,
+            auto_sync::get_auto_sync_interval,
+            sync_lock::cancel_sync,
+            sync_queue::flush_sync_queue,
+            sync_queue::get_sync_queue,
+            // Local data commands
+            tasks::commands::check_data_schema_version,
+            tasks::commands::validate_data_against_schema,
+            tasks::commands::import_data_file,
+            tasks::commands::export_markdown,
+            storage::check_storage_writable,
+            backup::list_backups,
+            reset::get_factory_reset_confirmation,
+            reset::factory_reset,
+            http::set_http_proxy
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, event| {
+            // Flush debounced metadata writes before the process exits
+            if let tauri::RunEvent::Exit = event {
+                if let Err(e) = github::CoalescedWriter::global().flush() {
+                    log::error!("Failed to flush metadata on exit: {}", e);
+                }
+            }
+        });
 }
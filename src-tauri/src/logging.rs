@@ -0,0 +1,173 @@
+//! Leveled logging to stderr and, on request, to a file
+//!
+//! Code logs through the `log` macros; a `tracing` subscriber formats the
+//! records. Setting `NEKOTICK_LOG` also writes daily-rotated files under
+//! `.nekotick/logs/`, and its value (`trace` ... `error`) is the starting
+//! level. Every line is passed through `redact` before it is written, so
+//! tokens and auth codes stay out of the logs even at debug level.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{reload, Registry};
+
+use crate::redact::redact;
+
+/// Env var that turns on file logging and sets the starting level
+pub const LOG_ENV: &str = "NEKOTICK_LOG";
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const LOG_FOLDER: &str = "logs";
+const MAX_LOG_FILES: usize = 7;
+
+/// Logging state, held in Tauri managed state
+pub struct LogState {
+    /// Directory of the log files, when file logging is on
+    dir: Option<PathBuf>,
+    level: reload::Handle<LevelFilter, Registry>,
+    /// Flushes buffered file output when the app exits
+    _guard: Option<WorkerGuard>,
+}
+
+/// Writer that masks secrets in everything passed through it
+struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+struct Redacting<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+/// Parse a level name; anything else (e.g. `NEKOTICK_LOG=1`) means `info`
+///
+/// `tracing` would read digits as verbosity (`1` is `error`), but here a
+/// number is just a switch turning file logging on.
+fn parse_level(value: &str) -> LevelFilter {
+    let value = value.trim();
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return LevelFilter::INFO;
+    }
+    LevelFilter::from_str(value).unwrap_or(LevelFilter::INFO)
+}
+
+/// Keep `log` records at or below `level`; `log` filters before `tracing` sees them
+fn set_log_max_level(level: LevelFilter) {
+    log::set_max_level(match level {
+        LevelFilter::OFF => log::LevelFilter::Off,
+        LevelFilter::ERROR => log::LevelFilter::Error,
+        LevelFilter::WARN => log::LevelFilter::Warn,
+        LevelFilter::INFO => log::LevelFilter::Info,
+        LevelFilter::DEBUG => log::LevelFilter::Debug,
+        _ => log::LevelFilter::Trace,
+    });
+}
+
+/// Install the global subscriber
+///
+/// Logs go to stderr at `info` unless `NEKOTICK_LOG` is set, which adds a
+/// file appender under `<data_dir>/.nekotick/logs/`. Without a data
+/// directory, or if the appender can't be created, logging continues on
+/// stderr only.
+pub fn init(data_dir: Option<&Path>) -> LogState {
+    let env_level = std::env::var(LOG_ENV).ok().filter(|v| !v.trim().is_empty());
+    let level = env_level.as_deref().map_or(LevelFilter::INFO, parse_level);
+
+    let dir = data_dir
+        .filter(|_| env_level.is_some())
+        .map(|data_dir| data_dir.join(NEKOTICK_FOLDER).join(LOG_FOLDER));
+    let appender = dir.as_ref().and_then(|dir| {
+        RollingFileAppender::builder()
+            .rotation(Rotation::DAILY)
+            .filename_prefix("nekotick")
+            .filename_suffix("log")
+            .max_log_files(MAX_LOG_FILES)
+            .build(dir)
+            .map_err(|e| eprintln!("Failed to open log file in {:?}: {}", dir, e))
+            .ok()
+    });
+    let (file_layer, guard) = match appender {
+        Some(appender) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Redacting(writer));
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    let (filter, handle) = reload::Layer::new(level);
+    let stderr_layer = tracing_subscriber::fmt::layer().with_writer(Redacting(io::stderr));
+    if let Err(e) = tracing_subscriber::registry()
+        .with(filter)
+        .with(stderr_layer)
+        .with(file_layer)
+        .try_init()
+    {
+        eprintln!("Failed to install logger: {}", e);
+    }
+    set_log_max_level(level);
+
+    LogState {
+        dir: dir.filter(|_| guard.is_some()),
+        level: handle,
+        _guard: guard,
+    }
+}
+
+/// Directory holding the log files, or `None` when `NEKOTICK_LOG` is unset
+#[tauri::command]
+pub fn get_log_path(state: tauri::State<'_, LogState>) -> Option<String> {
+    state.dir.as_ref().map(|dir| dir.to_string_lossy().to_string())
+}
+
+/// Change the log level (`trace`, `debug`, `info`, `warn`, `error` or `off`) until the app exits
+#[tauri::command]
+pub fn set_log_level(state: tauri::State<'_, LogState>, level: String) -> Result<(), String> {
+    let level = LevelFilter::from_str(level.trim())
+        .map_err(|_| format!("Unknown log level: {}", level))?;
+    state.level.reload(level).map_err(|e| e.to_string())?;
+    set_log_max_level(level);
+    log::info!("Log level set to {}", level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_value_sets_level() {
+        assert_eq!(parse_level("debug"), LevelFilter::DEBUG);
+        assert_eq!(parse_level(" WARN "), LevelFilter::WARN);
+        assert_eq!(parse_level("1"), LevelFilter::INFO);
+    }
+
+    #[test]
+    fn test_writer_masks_secrets() {
+        let mut out = Vec::new();
+        RedactingWriter(&mut out).write_all(b"refresh failed: refresh_token=abc\n").unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "refresh failed: refresh_token=[REDACTED]\n");
+    }
+}
@@ -0,0 +1,681 @@
+//! Drag overlay card rendering
+//!
+//! Builds the HTML for the floating card shown while dragging a task, so the
+//! template can be previewed and tested without creating a window. Card
+//! placement against monitor bounds lives here for the same reason.
+
+use crate::priority::Priority;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Window label prefix shared by all drag overlays
+pub const DRAG_WINDOW_LABEL_PREFIX: &str = "drag-overlay-";
+
+/// Length of the optional fade in/out, in milliseconds
+pub const FADE_DURATION_MS: u64 = 120;
+
+/// Fades an animated card in; run after `show()` so the transition is visible
+///
+/// The short timeout lets the hidden state paint first. Only the document
+/// changes, so the unfocused window never takes focus.
+pub const FADE_IN_SCRIPT: &str =
+    "setTimeout(function(){var c=document.querySelector('.card');if(c)c.classList.remove('hidden');},16);";
+
+/// Fades an animated card out; destroy the window after `FADE_DURATION_MS`
+pub const FADE_OUT_SCRIPT: &str =
+    "var c=document.querySelector('.card');if(c)c.classList.add('hidden');";
+
+/// Window label for the drag overlay identified by `drag_id`
+///
+/// Tauri labels only allow alphanumerics and `-/:_`, so ids are checked here
+/// rather than failing inside the window builder.
+pub fn drag_window_label(drag_id: &str) -> Result<String, String> {
+    let valid = !drag_id.is_empty()
+        && drag_id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '/'));
+    if !valid {
+        return Err(format!("Invalid drag id: {:?}", drag_id));
+    }
+    Ok(format!("{}{}", DRAG_WINDOW_LABEL_PREFIX, drag_id))
+}
+
+/// Drag overlay windows currently open, with the params they were rendered from
+#[derive(Default)]
+pub struct DragWindowRegistry {
+    overlays: Mutex<BTreeMap<String, OverlayParams>>,
+}
+
+impl DragWindowRegistry {
+    pub fn insert(&self, label: &str, params: OverlayParams) {
+        if let Ok(mut overlays) = self.overlays.lock() {
+            overlays.insert(label.to_string(), params);
+        }
+    }
+
+    /// Unregister `label`, returning the params it was rendered from
+    pub fn remove(&self, label: &str) -> Option<OverlayParams> {
+        self.overlays.lock().ok()?.remove(label)
+    }
+
+    /// Remove and return every registered label
+    pub fn drain(&self) -> Vec<String> {
+        self.overlays
+            .lock()
+            .map(|mut overlays| std::mem::take(&mut *overlays).into_keys().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        self.overlays
+            .lock()
+            .map(|overlays| overlays.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Switch every overlay to the dark or light theme, returning the updated params
+    pub fn set_theme(&self, is_dark: bool) -> Vec<(String, OverlayParams)> {
+        self.overlays
+            .lock()
+            .map(|mut overlays| {
+                overlays
+                    .iter_mut()
+                    .map(|(label, params)| {
+                        params.is_dark = Some(is_dark);
+                        (label.clone(), params.clone())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Task status shown by the card's checkbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckboxState {
+    #[default]
+    Open,
+    Done,
+    /// Partially complete (indeterminate)
+    Partial,
+    Blocked,
+}
+
+impl FromStr for CheckboxState {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(CheckboxState::Open),
+            "done" => Ok(CheckboxState::Done),
+            "partial" => Ok(CheckboxState::Partial),
+            "blocked" => Ok(CheckboxState::Blocked),
+            _ => Err(format!("Unknown checkbox state: {}", s)),
+        }
+    }
+}
+
+/// Everything that affects how the drag card looks and behaves
+///
+/// Window flags default to the drag overlay behavior; pinned notes override them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayParams {
+    pub content: String,
+    /// Checkbox style; takes precedence over `is_done`
+    #[serde(default)]
+    pub state: Option<CheckboxState>,
+    /// Deprecated alias for `state: "done"`
+    #[serde(default)]
+    pub is_done: bool,
+    /// Dark or light card; unset follows the OS theme when the window is created
+    #[serde(default)]
+    pub is_dark: Option<bool>,
+    pub color: Option<String>,
+    #[serde(default = "default_true")]
+    pub always_on_top: bool,
+    #[serde(default = "default_true")]
+    pub skip_taskbar: bool,
+    /// Pass mouse events through to the windows below
+    #[serde(default = "default_true")]
+    pub click_through: bool,
+    /// The card takes focus and accepts input (pinned notes)
+    #[serde(default)]
+    pub interactive: bool,
+    /// Fade the card in when shown and out before it is destroyed
+    #[serde(default)]
+    pub animate: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Window builder flags derived from `OverlayParams`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OverlayWindowConfig {
+    pub always_on_top: bool,
+    pub skip_taskbar: bool,
+    pub ignore_cursor_events: bool,
+    pub focused: bool,
+}
+
+impl OverlayParams {
+    /// Params for the drag overlay, which floats above everything and never takes input
+    pub fn drag(content: String, state: CheckboxState, is_dark: bool, color: Option<String>) -> Self {
+        Self {
+            content,
+            state: Some(state),
+            is_done: state == CheckboxState::Done,
+            is_dark: Some(is_dark),
+            color,
+            always_on_top: true,
+            skip_taskbar: true,
+            click_through: true,
+            interactive: false,
+            animate: false,
+        }
+    }
+
+    /// Checkbox state, honoring the deprecated `is_done` when `state` is unset
+    pub fn checkbox_state(&self) -> CheckboxState {
+        self.state.unwrap_or(if self.is_done { CheckboxState::Done } else { CheckboxState::Open })
+    }
+
+    /// Map params to window flags, rejecting combinations that cannot work
+    pub fn window_config(&self) -> Result<OverlayWindowConfig, String> {
+        if self.click_through && self.interactive {
+            return Err("An interactive overlay cannot be click-through".to_string());
+        }
+
+        Ok(OverlayWindowConfig {
+            always_on_top: self.always_on_top,
+            skip_taskbar: self.skip_taskbar,
+            ignore_cursor_events: self.click_through,
+            focused: self.interactive,
+        })
+    }
+}
+
+/// Horizontal distance from the card's left edge to the cursor (the grip)
+pub const CURSOR_OFFSET_X: f64 = 20.0;
+
+/// A monitor's area in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenBounds {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl ScreenBounds {
+    /// Bounds of a monitor from the physical values Tauri reports
+    pub fn from_physical(x: i32, y: i32, width: u32, height: u32, scale_factor: f64) -> Self {
+        Self {
+            x: x as f64 / scale_factor,
+            y: y as f64 / scale_factor,
+            width: width as f64 / scale_factor,
+            height: height as f64 / scale_factor,
+        }
+    }
+
+    /// Distance from (x, y) to the nearest point of these bounds, 0 inside
+    fn distance_to(&self, x: f64, y: f64) -> f64 {
+        let dx = (self.x - x).max(x - (self.x + self.width)).max(0.0);
+        let dy = (self.y - y).max(y - (self.y + self.height)).max(0.0);
+        dx.hypot(dy)
+    }
+}
+
+/// Which drag an overlay belongs to and where to open it, in logical pixels
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DragPlacement {
+    pub drag_id: String,
+    /// Cursor position the card is centered on
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Where a drag card was placed, so drop logic can use the same coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayPosition {
+    /// Logical top-left corner of the window
+    pub x: f64,
+    pub y: f64,
+    /// The card was moved to stay fully on the monitor
+    pub clamped: bool,
+}
+
+/// The monitor containing (x, y), or the closest one when the point is off-screen
+pub fn nearest_bounds(monitors: &[ScreenBounds], x: f64, y: f64) -> Option<ScreenBounds> {
+    monitors
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance_to(x, y).total_cmp(&b.distance_to(x, y)))
+}
+
+/// Position a `width` x `height` card with its grip at the cursor (x, y)
+///
+/// The card is vertically centered on the cursor and then moved, if needed,
+/// to stay fully inside `bounds`. A card larger than the monitor is pinned
+/// to its top-left corner.
+pub fn place_overlay(x: f64, y: f64, width: f64, height: f64, bounds: Option<ScreenBounds>) -> OverlayPosition {
+    let (left, top) = (x - CURSOR_OFFSET_X, y - height / 2.0);
+    let Some(bounds) = bounds else {
+        return OverlayPosition { x: left, y: top, clamped: false };
+    };
+
+    let clamped_left = left.min(bounds.x + bounds.width - width).max(bounds.x);
+    let clamped_top = top.min(bounds.y + bounds.height - height).max(bounds.y);
+    OverlayPosition {
+        x: clamped_left,
+        y: clamped_top,
+        clamped: clamped_left != left || clamped_top != top,
+    }
+}
+
+/// Escape text for use inside HTML content and attribute values
+///
+/// Backticks are escaped too, since the document is injected through a
+/// `document.write` template literal.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '`' => escaped.push_str("&#96;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape a string for embedding in a JS template literal (`...`)
+pub fn escape_template_literal(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('`', "\\`")
+        .replace("${", "\\${")
+}
+
+/// Build the full HTML document for the drag overlay card
+///
+/// An animated card starts hidden until `FADE_IN_SCRIPT` runs.
+pub fn build_overlay_html(params: OverlayParams) -> String {
+    let start_hidden = params.animate;
+    render_overlay_html(params, start_hidden)
+}
+
+/// Build the HTML for re-rendering a card that is already on screen
+///
+/// Animated cards keep their transition, for the fade out, but start
+/// visible so a re-render doesn't fade them in again.
+pub fn rerender_overlay_html(params: OverlayParams) -> String {
+    render_overlay_html(params, false)
+}
+
+fn render_overlay_html(params: OverlayParams, start_hidden: bool) -> String {
+    // Theme-based colors
+    let (bg_color, border_color, text_color, text_muted) = if params.is_dark.unwrap_or(false) {
+        ("#18181b", "#3f3f46", "#fafafa", "#71717a")
+    } else {
+        ("#fff", "#e5e5e5", "#18181b", "#a1a1aa")
+    };
+
+    // Unknown colors fall back to the muted color
+    let task_color = params
+        .color
+        .as_deref()
+        .and_then(|c| c.parse::<Priority>().ok())
+        .and_then(Priority::as_color)
+        .unwrap_or(text_muted);
+    let has_color = params.color.is_some() && params.color.as_deref() != Some("default");
+
+    let state = params.checkbox_state();
+
+    // Completed tasks are struck through; blocked ones are only dimmed
+    let content_style = match state {
+        CheckboxState::Done => format!("text-decoration:line-through;color:{}", text_muted),
+        CheckboxState::Blocked => format!("color:{}", text_muted),
+        CheckboxState::Open | CheckboxState::Partial => String::new(),
+    };
+
+    // Partial and blocked boxes keep the priority color, like open ones
+    let box_color = if has_color { task_color } else { text_color };
+    let checkbox_html = match state {
+        CheckboxState::Done => format!("<svg class=\"checkbox\" viewBox=\"0 0 16 16\"><rect x=\"0.5\" y=\"0.5\" width=\"15\" height=\"15\" rx=\"2\" fill=\"{}\" stroke=\"{}\"/><path d=\"M4 8l3 3 5-6\" stroke=\"white\" stroke-width=\"2\" fill=\"none\"/></svg>", text_color, text_color),
+        CheckboxState::Partial => format!("<svg class=\"checkbox\" style=\"border:none\" viewBox=\"0 0 16 16\"><rect x=\"0.5\" y=\"0.5\" width=\"15\" height=\"15\" rx=\"2\" fill=\"none\" stroke=\"{}\"/><path d=\"M4 8h8\" stroke=\"{}\" stroke-width=\"2\"/></svg>", box_color, box_color),
+        CheckboxState::Blocked => format!("<svg class=\"checkbox\" style=\"border:none\" viewBox=\"0 0 16 16\"><rect x=\"0.5\" y=\"0.5\" width=\"15\" height=\"15\" rx=\"2\" fill=\"none\" stroke=\"{}\" stroke-dasharray=\"2 2\"/><path d=\"M4 12L12 4\" stroke=\"{}\" stroke-width=\"1.5\"/></svg>", text_muted, text_muted),
+        CheckboxState::Open if has_color => format!("<div class=\"checkbox\" style=\"border:2px solid {}\"></div>", task_color),
+        CheckboxState::Open => format!("<div class=\"checkbox\" style=\"border-color:{}\"></div>", text_muted),
+    };
+
+    let fade_style = if params.animate {
+        format!(".card{{transition:opacity {}ms ease-out}}\n.card.hidden{{opacity:0}}\n", FADE_DURATION_MS)
+    } else {
+        String::new()
+    };
+    let card_class = if start_hidden { "card hidden" } else { "card" };
+
+    // HTML content - transparent background, card fills window
+    format!(r#"<!DOCTYPE html>
+<html style="background:transparent!important">
+<head>
+<style>
+*{{margin:0;padding:0;box-sizing:border-box}}
+html,body{{background:transparent!important;overflow:hidden;width:100%;height:100%}}
+body{{font-family:system-ui,-apple-system,sans-serif;display:flex}}
+.card{{
+  background:{};
+  border:1px solid {};
+  border-radius:4px;
+  padding:8px 12px;
+  display:flex;
+  align-items:start;
+  gap:8px;
+  font-size:14px;
+  color:{};
+  width:100%;
+  height:100%;
+}}
+.grip{{color:{}}}
+.checkbox{{width:16px;height:16px;border:1px solid {};border-radius:3px;flex-shrink:0;margin-top:2px}}
+.content{{flex:1;white-space:pre-wrap;word-break:break-word;overflow-wrap:anywhere}}
+{}</style>
+</head>
+<body style="background:transparent!important">
+<div class="{}">
+<div class="grip">⋮⋮</div>
+{}
+<span class="content" style="{}">{}</span>
+</div>
+</body>
+</html>"#, bg_color, border_color, text_color, text_muted, text_muted, fade_style, card_class, checkbox_html, content_style, escape_html(&params.content))
+}
+
+/// Script that replaces an overlay window's document with `html`
+pub fn document_write_script(html: &str) -> String {
+    format!(r#"document.open(); document.write(`{}`); document.close();"#, escape_template_literal(html))
+}
+
+/// Render the drag card HTML without creating a window
+#[tauri::command]
+pub fn preview_overlay_html(params: OverlayParams) -> String {
+    build_overlay_html(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(content: &str, is_done: bool, color: Option<&str>) -> OverlayParams {
+        let state = if is_done { CheckboxState::Done } else { CheckboxState::Open };
+        OverlayParams::drag(content.to_string(), state, false, color.map(|c| c.to_string()))
+    }
+
+    #[test]
+    fn test_drag_window_labels_are_distinct_per_id() {
+        assert_eq!(drag_window_label("task-1").unwrap(), "drag-overlay-task-1");
+        assert_ne!(drag_window_label("a").unwrap(), drag_window_label("b").unwrap());
+        assert!(drag_window_label("").is_err());
+        assert!(drag_window_label("has space").is_err());
+    }
+
+    #[test]
+    fn test_registry_tracks_multiple_overlays() {
+        let registry = DragWindowRegistry::default();
+        registry.insert("drag-overlay-a", params("a", false, None));
+        registry.insert("drag-overlay-b", params("b", false, None));
+        assert_eq!(registry.labels(), vec!["drag-overlay-a", "drag-overlay-b"]);
+
+        // Destroying one overlay leaves the other registered
+        assert_eq!(registry.remove("drag-overlay-a").unwrap().content, "a");
+        assert_eq!(registry.labels(), vec!["drag-overlay-b"]);
+
+        assert_eq!(registry.drain(), vec!["drag-overlay-b"]);
+        assert!(registry.labels().is_empty());
+    }
+
+    #[test]
+    fn test_registry_retheme_keeps_content() {
+        let registry = DragWindowRegistry::default();
+        registry.insert("drag-overlay-a", params("Buy milk", true, Some("red")));
+
+        let updated = registry.set_theme(true);
+        assert_eq!(updated.len(), 1);
+        let (label, params) = &updated[0];
+        assert_eq!(label, "drag-overlay-a");
+        assert_eq!(params.is_dark, Some(true));
+        assert_eq!(params.content, "Buy milk");
+        assert!(build_overlay_html(params.clone()).contains("background:#18181b"));
+    }
+
+    #[test]
+    fn test_document_write_script_escapes_html() {
+        let script = document_write_script("<p>`${x}`</p>");
+        assert!(script.starts_with("document.open(); document.write(`"));
+        assert!(script.contains(r"\`\${x}\`"));
+    }
+
+    #[test]
+    fn test_drag_defaults_preserve_window_flags() {
+        let config = params("task", false, None).window_config().unwrap();
+        assert_eq!(
+            config,
+            OverlayWindowConfig {
+                always_on_top: true,
+                skip_taskbar: true,
+                ignore_cursor_events: true,
+                focused: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_pinned_note_window_flags() {
+        let mut p = params("note", false, None);
+        p.always_on_top = false;
+        p.skip_taskbar = false;
+        p.click_through = false;
+        p.interactive = true;
+        let config = p.window_config().unwrap();
+        assert!(!config.always_on_top);
+        assert!(!config.skip_taskbar);
+        assert!(!config.ignore_cursor_events);
+        assert!(config.focused);
+    }
+
+    #[test]
+    fn test_click_through_interactive_rejected() {
+        let mut p = params("note", false, None);
+        p.interactive = true;
+        assert!(p.window_config().is_err());
+    }
+
+    #[test]
+    fn test_window_flags_default_when_omitted() {
+        let p: OverlayParams = serde_json::from_str(
+            r#"{"content":"task","isDone":false,"isDark":true,"color":null}"#,
+        )
+        .unwrap();
+        assert!(p.always_on_top && p.skip_taskbar && p.click_through);
+        assert!(!p.interactive);
+    }
+
+    #[test]
+    fn test_animated_card_starts_hidden_with_transition() {
+        let mut p = params("task", false, None);
+        assert!(!build_overlay_html(p.clone()).contains("transition"));
+        assert!(build_overlay_html(p.clone()).contains("<div class=\"card\">"));
+
+        p.animate = true;
+        let html = build_overlay_html(p.clone());
+        assert!(html.contains(&format!("transition:opacity {}ms", FADE_DURATION_MS)));
+        assert!(html.contains("<div class=\"card hidden\">"));
+
+        // Re-rendering keeps the card visible but still able to fade out
+        let html = rerender_overlay_html(p.clone());
+        assert!(html.contains("transition:opacity"));
+        assert!(html.contains("<div class=\"card\">"));
+
+        // Animation never changes the window flags, so the overlay stays unfocused
+        assert!(!p.window_config().unwrap().focused);
+    }
+
+    #[test]
+    fn test_done_task_is_struck_through_with_checked_box() {
+        let html = build_overlay_html(params("Buy milk", true, None));
+        assert!(html.contains("text-decoration:line-through"));
+        assert!(html.contains("<svg class=\"checkbox\""));
+    }
+
+    #[test]
+    fn test_undone_task_has_empty_checkbox() {
+        let html = build_overlay_html(params("Buy milk", false, None));
+        assert!(!html.contains("line-through"));
+        assert!(!html.contains("<svg"));
+        assert!(html.contains("<div class=\"checkbox\" style=\"border-color:#a1a1aa\">"));
+    }
+
+    #[test]
+    fn test_partial_and_blocked_checkboxes() {
+        let mut p = params("Write report", false, Some("red"));
+        p.state = Some(CheckboxState::Partial);
+        let html = build_overlay_html(p.clone());
+        assert!(html.contains("d=\"M4 8h8\" stroke=\"#FE002D\""));
+        assert!(!html.contains("line-through"));
+
+        p.state = Some(CheckboxState::Blocked);
+        let html = build_overlay_html(p);
+        assert!(html.contains("stroke-dasharray"));
+        assert!(html.contains("<span class=\"content\" style=\"color:#a1a1aa\">"));
+    }
+
+    #[test]
+    fn test_state_resolution_and_is_done_alias() {
+        assert_eq!("partial".parse::<CheckboxState>().unwrap(), CheckboxState::Partial);
+        assert!("later".parse::<CheckboxState>().is_err());
+        assert!(serde_json::from_str::<OverlayParams>(r#"{"content":"task","state":"later"}"#).is_err());
+
+        // Frontends that still send only `isDone` keep working
+        let p: OverlayParams = serde_json::from_str(r#"{"content":"task","isDone":true,"isDark":false,"color":null}"#).unwrap();
+        assert_eq!(p.checkbox_state(), CheckboxState::Done);
+        let p: OverlayParams = serde_json::from_str(r#"{"content":"task","state":"blocked","isDark":false,"color":null}"#).unwrap();
+        assert_eq!(p.checkbox_state(), CheckboxState::Blocked);
+        // `state` wins over the deprecated flag; an unset theme is left for the OS to decide
+        let p: OverlayParams = serde_json::from_str(r#"{"content":"task","state":"partial","isDone":true}"#).unwrap();
+        assert_eq!(p.checkbox_state(), CheckboxState::Partial);
+        assert_eq!(p.is_dark, None);
+        assert!(p.click_through && p.always_on_top && !p.animate);
+    }
+
+    #[test]
+    fn test_each_color_sets_checkbox_border() {
+        let colors = [
+            ("red", "#FE002D"),
+            ("orange", "#FF8500"),
+            ("yellow", "#FEC900"),
+            ("green", "#63DA38"),
+            ("blue", "#008BFE"),
+            ("purple", "#DD11E8"),
+            ("brown", "#B47D58"),
+        ];
+        for (name, hex) in colors {
+            let html = build_overlay_html(params("task", false, Some(name)));
+            assert!(
+                html.contains(&format!("border:2px solid {}", hex)),
+                "missing border for {}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_color_uses_muted_border() {
+        let html = build_overlay_html(params("task", false, Some("default")));
+        assert!(!html.contains("border:2px solid"));
+    }
+
+    #[test]
+    fn test_dark_theme_colors() {
+        let mut p = params("task", false, None);
+        p.is_dark = Some(true);
+        let html = build_overlay_html(p);
+        assert!(html.contains("background:#18181b"));
+    }
+
+    #[test]
+    fn test_content_is_escaped() {
+        let html = build_overlay_html(params("<script>alert(\"x\")</script> & 'more'", false, None));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; &#39;more&#39;"));
+    }
+
+    #[test]
+    fn test_escape_html_angle_brackets_and_ampersands() {
+        assert_eq!(escape_html("</span><b>"), "&lt;/span&gt;&lt;b&gt;");
+        assert_eq!(escape_html("a && b &amp;"), "a &amp;&amp; b &amp;amp;");
+    }
+
+    #[test]
+    fn test_backticks_cannot_break_document_write() {
+        let html = build_overlay_html(params("`); alert(1); (`${x}", false, None));
+        assert!(!html.contains('`'));
+        assert!(html.contains("&#96;); alert(1); (&#96;${x}"));
+
+        let script = escape_template_literal(&html);
+        assert!(script.contains("\\${x}"));
+    }
+
+    const LEFT: ScreenBounds = ScreenBounds { x: 0.0, y: 0.0, width: 1920.0, height: 1080.0 };
+    const RIGHT: ScreenBounds = ScreenBounds { x: 1920.0, y: 0.0, width: 1280.0, height: 720.0 };
+
+    #[test]
+    fn test_overlay_inside_monitor_is_not_moved() {
+        let position = place_overlay(500.0, 500.0, 300.0, 40.0, Some(LEFT));
+        assert_eq!(position, OverlayPosition { x: 480.0, y: 480.0, clamped: false });
+        assert_eq!(place_overlay(500.0, 500.0, 300.0, 40.0, None), position);
+    }
+
+    #[test]
+    fn test_overlay_clamped_at_monitor_edges() {
+        // Near the right edge of the right-hand monitor
+        let position = place_overlay(3190.0, 10.0, 300.0, 40.0, Some(RIGHT));
+        assert_eq!(position, OverlayPosition { x: 2900.0, y: 0.0, clamped: true });
+
+        // Near the left edge of the right-hand monitor, it must not spill onto the left one
+        let position = place_overlay(1925.0, 710.0, 300.0, 40.0, Some(RIGHT));
+        assert_eq!(position, OverlayPosition { x: 1920.0, y: 680.0, clamped: true });
+
+        // Larger than the monitor: pinned to its top-left corner
+        let position = place_overlay(100.0, 100.0, 4000.0, 40.0, Some(LEFT));
+        assert_eq!((position.x, position.y), (0.0, 80.0));
+    }
+
+    #[test]
+    fn test_nearest_bounds_picks_monitor_under_cursor() {
+        let monitors = [LEFT, RIGHT];
+        assert_eq!(nearest_bounds(&monitors, 100.0, 100.0), Some(LEFT));
+        assert_eq!(nearest_bounds(&monitors, 2000.0, 100.0), Some(RIGHT));
+        // Below the shorter right-hand monitor, closest to it
+        assert_eq!(nearest_bounds(&monitors, 3000.0, 900.0), Some(RIGHT));
+        assert_eq!(nearest_bounds(&[], 0.0, 0.0), None);
+
+        let scaled = ScreenBounds::from_physical(3840, 0, 2560, 1440, 2.0);
+        assert_eq!(scaled, ScreenBounds { x: 1920.0, y: 0.0, width: 1280.0, height: 720.0 });
+    }
+
+    #[test]
+    fn test_escape_template_literal() {
+        assert_eq!(escape_template_literal("a`b${c}\\d"), "a\\`b\\${c}\\\\d");
+    }
+}
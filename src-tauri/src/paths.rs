@@ -0,0 +1,117 @@
+//! Where NekoTick keeps its data
+//!
+//! The base directory is resolved once at startup. `NEKOTICK_DATA_DIR`
+//! overrides Tauri's app data directory; a relative value is taken relative
+//! to the executable, so a portable build can keep its data next to it (e.g.
+//! on a USB stick). Every module gets the base from `data_dir`, and the
+//! frontend from the `get_data_dir` command, so `.nekotick` is never split
+//! across locations.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::Manager;
+
+/// Environment variable that overrides the data directory
+pub const DATA_DIR_ENV: &str = "NEKOTICK_DATA_DIR";
+
+/// Folder for cloned repositories under a portable data directory
+const REPOS_FOLDER: &str = "repos";
+
+/// Base set by `NEKOTICK_DATA_DIR`, for code that has no `AppHandle`
+static PORTABLE_BASE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Data locations shared by every module, managed as app state
+#[derive(Debug, Clone)]
+pub struct DataPaths {
+    base: PathBuf,
+    portable: bool,
+}
+
+impl DataPaths {
+    /// Resolve from the environment, falling back to the app data directory
+    pub fn resolve(app: &tauri::AppHandle) -> Result<Self, String> {
+        let exe_dir = std::env::current_exe().ok().and_then(|p| p.parent().map(Path::to_path_buf));
+        let app_data_dir = app.path().app_data_dir().ok();
+        let paths = resolve_data_paths(std::env::var(DATA_DIR_ENV).ok(), exe_dir.as_deref(), app_data_dir)
+            .ok_or("Could not determine the data directory")?;
+        if paths.portable {
+            let _ = PORTABLE_BASE.set(paths.base.clone());
+        }
+        Ok(paths)
+    }
+
+    /// Directory holding `.nekotick/` and `nekotick.md`
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+
+    /// Whether the data directory came from `NEKOTICK_DATA_DIR`
+    pub fn is_portable(&self) -> bool {
+        self.portable
+    }
+}
+
+/// Pick the data directory: a non-empty override wins over the app data dir
+pub fn resolve_data_paths(
+    override_dir: Option<String>,
+    exe_dir: Option<&Path>,
+    app_data_dir: Option<PathBuf>,
+) -> Option<DataPaths> {
+    match override_dir.filter(|d| !d.trim().is_empty()) {
+        Some(dir) => {
+            let dir = PathBuf::from(dir.trim());
+            let base = match exe_dir {
+                Some(exe_dir) if dir.is_relative() => exe_dir.join(dir),
+                _ => dir,
+            };
+            Some(DataPaths { base, portable: true })
+        }
+        None => app_data_dir.map(|base| DataPaths { base, portable: false }),
+    }
+}
+
+/// Base data directory for `app`
+pub fn data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    match app.try_state::<DataPaths>() {
+        Some(paths) => Ok(paths.base().to_path_buf()),
+        None => app.path().app_data_dir().map_err(|e| e.to_string()),
+    }
+}
+
+/// Base data directory, so the frontend reads and writes the same `.nekotick`
+#[tauri::command]
+pub fn get_data_dir(app: tauri::AppHandle) -> Result<String, String> {
+    Ok(data_dir(&app)?.to_string_lossy().into_owned())
+}
+
+/// Cloned repositories directory in portable mode, `None` otherwise
+pub fn portable_repos_dir() -> Option<PathBuf> {
+    PORTABLE_BASE.get().map(|base| base.join(REPOS_FOLDER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_data_dir_by_default() {
+        let paths = resolve_data_paths(None, None, Some(PathBuf::from("/appdata"))).unwrap();
+        assert_eq!(paths.base(), Path::new("/appdata"));
+        assert!(!paths.is_portable());
+
+        let blank = resolve_data_paths(Some("  ".to_string()), None, Some(PathBuf::from("/appdata"))).unwrap();
+        assert!(!blank.is_portable());
+    }
+
+    #[test]
+    fn test_relative_override_is_next_to_executable() {
+        let exe_dir = std::env::temp_dir().join("usb");
+        let paths = resolve_data_paths(Some("data".to_string()), Some(&exe_dir), Some(PathBuf::from("/appdata"))).unwrap();
+        assert_eq!(paths.base(), exe_dir.join("data"));
+        assert!(paths.is_portable());
+
+        let absolute = std::env::temp_dir().join("portable");
+        let paths = resolve_data_paths(Some(absolute.display().to_string()), Some(&exe_dir), None).unwrap();
+        assert_eq!(paths.base(), absolute);
+    }
+}
@@ -0,0 +1,113 @@
+//! Task priority colors
+//!
+//! Tasks carry a color name (`red`, `yellow`, ...) that doubles as their
+//! priority. This is the one place that knows the valid names, their display
+//! colors and labels.
+
+use std::str::FromStr;
+
+/// A task's priority color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+    Brown,
+    Default,
+}
+
+impl Priority {
+    /// Every color priority, highest first (`Default` excluded)
+    pub const COLORS: [Priority; 7] = [
+        Priority::Red,
+        Priority::Orange,
+        Priority::Yellow,
+        Priority::Green,
+        Priority::Blue,
+        Priority::Purple,
+        Priority::Brown,
+    ];
+
+    /// Name as stored in data.json
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Priority::Red => "red",
+            Priority::Orange => "orange",
+            Priority::Yellow => "yellow",
+            Priority::Green => "green",
+            Priority::Blue => "blue",
+            Priority::Purple => "purple",
+            Priority::Brown => "brown",
+            Priority::Default => "default",
+        }
+    }
+
+    /// Apple style hex value, or `None` for the theme's muted color
+    pub fn as_color(self) -> Option<&'static str> {
+        match self {
+            Priority::Red => Some("#FE002D"),
+            Priority::Orange => Some("#FF8500"),
+            Priority::Yellow => Some("#FEC900"),
+            Priority::Green => Some("#63DA38"),
+            Priority::Blue => Some("#008BFE"),
+            Priority::Purple => Some("#DD11E8"),
+            Priority::Brown => Some("#B47D58"),
+            Priority::Default => None,
+        }
+    }
+
+    /// Human readable name
+    pub fn as_label(self) -> &'static str {
+        match self {
+            Priority::Red => "Red",
+            Priority::Orange => "Orange",
+            Priority::Yellow => "Yellow",
+            Priority::Green => "Green",
+            Priority::Blue => "Blue",
+            Priority::Purple => "Purple",
+            Priority::Brown => "Brown",
+            Priority::Default => "No priority",
+        }
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Priority::COLORS
+            .into_iter()
+            .chain([Priority::Default])
+            .find(|p| p.as_str() == s)
+            .ok_or_else(|| format!("Unknown priority: {}", s))
+    }
+}
+
+/// Whether `priority` is a color name the app understands
+#[tauri::command]
+pub fn validate_priority(priority: String) -> bool {
+    priority.parse::<Priority>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_round_trip() {
+        for priority in Priority::COLORS.into_iter().chain([Priority::Default]) {
+            assert_eq!(priority.as_str().parse::<Priority>(), Ok(priority));
+        }
+    }
+
+    #[test]
+    fn test_unknown_priority_is_invalid() {
+        assert!(validate_priority("red".to_string()));
+        assert!(validate_priority("default".to_string()));
+        assert!(!validate_priority("Red".to_string()));
+        assert!(!validate_priority("magenta".to_string()));
+    }
+}
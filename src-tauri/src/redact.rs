@@ -0,0 +1,136 @@
+//! Masking of credentials in free-form text
+//!
+//! Log lines and error messages can carry tokens the app never meant to
+//! print: a reflected `access_token=` in an OAuth error body, a `Bearer`
+//! header echoed by a proxy, a `code=` in a callback URL. `redact` masks the
+//! value of anything that looks like one while keeping the key, so the text
+//! still says what went wrong.
+
+const MASK: &str = "[REDACTED]";
+
+/// Prefixes of GitHub tokens (classic, OAuth, user-to-server, server, refresh, fine-grained)
+const TOKEN_PREFIXES: &[&str] = &["github_pat_", "ghp_", "gho_", "ghu_", "ghs_", "ghr_"];
+
+/// Keys whose value is a secret, and whether `key: value` counts as well as `key=value`
+///
+/// `code` only matches as a query parameter; `"code": "not_connected"` is
+/// a sync error code, not an OAuth code.
+const SECRET_KEYS: &[(&str, bool)] = &[
+    ("access_token", true),
+    ("refresh_token", true),
+    ("client_secret", true),
+    ("password", true),
+    ("token", true),
+    ("code", false),
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Length of the secret value at the start of `text`
+fn value_len(text: &str) -> usize {
+    text.find(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '&' | ',' | ';' | '}' | ')'))
+        .unwrap_or(text.len())
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len()).is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+}
+
+/// Secret starting at `text`, as (bytes kept verbatim, bytes consumed)
+fn match_secret(text: &str) -> Option<(usize, usize)> {
+    for prefix in TOKEN_PREFIXES {
+        if let Some(rest) = text.strip_prefix(prefix) {
+            let len = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+            if len > 0 {
+                return Some((prefix.len(), prefix.len() + len));
+            }
+        }
+    }
+
+    let masked = |value: &str| {
+        let len = value_len(value);
+        let keep = text.len() - value.len();
+        (len > 0).then_some((keep, keep + len))
+    };
+
+    if starts_with_ignore_case(text, "bearer ") {
+        if let Some(secret) = masked(text["bearer ".len()..].trim_start()) {
+            return Some(secret);
+        }
+    }
+
+    for (key, allow_colon) in SECRET_KEYS {
+        if !starts_with_ignore_case(text, key) || text[key.len()..].starts_with(is_word_char) {
+            continue;
+        }
+        let rest = text[key.len()..].trim_start_matches('"').trim_start();
+        let Some(rest) = rest
+            .strip_prefix('=')
+            .or_else(|| rest.strip_prefix(':').filter(|_| *allow_colon))
+        else {
+            continue;
+        };
+        if let Some(secret) = masked(rest.trim_start().trim_start_matches('"')) {
+            return Some(secret);
+        }
+    }
+
+    None
+}
+
+/// An error as a user-facing message, with secrets masked
+pub fn redact_error(e: impl std::fmt::Display) -> String {
+    redact(&e.to_string())
+}
+
+/// Mask every token, bearer value and secret `key=value` in `text`
+pub fn redact(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        let at_word_start = !text[..i].chars().next_back().is_some_and(is_word_char);
+        if at_word_start {
+            if let Some((keep, end)) = match_secret(&text[i..]) {
+                out.push_str(&text[i..i + keep]);
+                out.push_str(MASK);
+                i += end;
+                continue;
+            }
+        }
+        out.push(c);
+        i += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_masks_github_tokens() {
+        assert_eq!(redact("token ghp_abc123XYZ rejected"), "token ghp_[REDACTED] rejected");
+        assert_eq!(redact("github_pat_11AB_cd"), "github_pat_[REDACTED]");
+        assert_eq!(redact("Authorization: Bearer gho_x1"), "Authorization: Bearer [REDACTED]");
+    }
+
+    #[test]
+    fn test_masks_secret_parameters() {
+        assert_eq!(
+            redact("access_token=abc&scope=gist&refresh_token=def"),
+            "access_token=[REDACTED]&scope=gist&refresh_token=[REDACTED]"
+        );
+        assert_eq!(redact("{\"access_token\": \"abc\", \"x\": 1}"), "{\"access_token\": \"[REDACTED]\", \"x\": 1}");
+        assert_eq!(redact("/callback?code=1234&state=s"), "/callback?code=[REDACTED]&state=s");
+    }
+
+    #[test]
+    fn test_leaves_ordinary_text_alone() {
+        let text = "GitHub token refresh failed: error_code=bad_verification_code";
+        assert_eq!(redact(text), text);
+        assert_eq!(redact("{\"code\":\"not_connected\"}"), "{\"code\":\"not_connected\"}");
+        assert_eq!(redact("Größe: 5"), "Größe: 5");
+    }
+}
@@ -0,0 +1,55 @@
+//! Preview of remote sync data
+//!
+//! Downloads a backend's data.json and summarizes it so the restore
+//! confirmation can show what is about to replace local data. Nothing is
+//! written locally.
+
+use serde::Serialize;
+
+use crate::sync_error::SyncError;
+use crate::tasks::{self, schema, DataSummary};
+
+/// What a backend currently holds
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemotePreview {
+    pub backend: String,
+    #[serde(flatten)]
+    pub summary: DataSummary,
+    /// Written by a newer app version; restoring needs `allow_newer_schema`
+    pub schema_too_new: bool,
+}
+
+fn preview(backend: &str, content: &str) -> Result<RemotePreview, SyncError> {
+    Ok(RemotePreview {
+        backend: backend.to_string(),
+        summary: tasks::summarize_data_json(content)?,
+        schema_too_new: schema::check_schema_version(content)?.is_too_new(),
+    })
+}
+
+/// Summarize the remote data.json of `backend` (`github` or `webdav`)
+#[tauri::command]
+pub async fn preview_remote_data(app: tauri::AppHandle, backend: String) -> Result<RemotePreview, SyncError> {
+    let content = match backend.as_str() {
+        "github" => crate::github::commands::download_github_data(&app).await?,
+        "webdav" => crate::webdav::commands::download_webdav_data(&app).await?,
+        other => return Err(SyncError::Other(format!("Unknown sync backend: {}", other))),
+    };
+    preview(&backend, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview_serializes_flat() {
+        let content = "{\"version\":1,\"data\":{\"tasks\":[{\"id\":\"a\",\"updatedAt\":5}]}}";
+        let json = serde_json::to_value(preview("github", content).unwrap()).unwrap();
+        assert_eq!(json["backend"], "github");
+        assert_eq!(json["taskCount"], 1);
+        assert_eq!(json["lastModified"], 5);
+        assert_eq!(json["schemaTooNew"], false);
+    }
+}
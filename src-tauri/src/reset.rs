@@ -0,0 +1,195 @@
+//! Factory reset
+//!
+//! Wipes all local app state under `.nekotick/store` (task data, sync
+//! metadata and credentials) plus the sync merge base and offline sync
+//! queue after archiving it, so support can have users start fresh without
+//! hand-deleting files.
+//!
+//! The archive leaves out encrypted credentials and the keys and device id
+//! they are bound to: they only open on this install, and a backup that
+//! carries both would hand out the sync tokens.
+
+use crate::github::commands::clear_github_local_state;
+use crate::github::credential_store;
+use crate::paths;
+use crate::sync_queue::clear_sync_queue;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const SYNC_BASE_FILE: &str = "sync_base.json";
+const SYNC_BASE_NOTES_FILE: &str = "sync_base.md";
+const WEBDAV_SYNC_BASE_FILE: &str = "webdav_sync_base.json";
+const BACKUPS_FOLDER: &str = "backups";
+const NOTES_FILE_NAME: &str = "nekotick.md";
+
+/// Files never copied into the backup archive
+const SECRET_FILES: [&str; 3] = [".sync_meta_key", ".credential_key", ".device_id"];
+const SECRET_EXTENSION: &str = "enc";
+
+/// Text the user must type to confirm a factory reset of the device with `device_id`
+///
+/// Tying it to the device keeps a code copied from support instructions or
+/// another machine from wiping this one.
+fn confirmation_code(device_id: &str) -> String {
+    let digest = Sha256::digest(device_id.as_bytes());
+    let prefix: String = digest.iter().take(3).map(|b| format!("{:02X}", b)).collect();
+    format!("RESET-{}", prefix)
+}
+
+/// Confirmation code for the install under `base_dir`
+pub fn factory_reset_confirmation(base_dir: &Path) -> Result<String, String> {
+    let store_dir = base_dir.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+    credential_store::device_id(&store_dir).map(|id| confirmation_code(&id))
+}
+
+/// Whether `path` holds credentials or the secrets that unlock them
+fn is_secret(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    SECRET_FILES.contains(&name) || path.extension().is_some_and(|ext| ext == SECRET_EXTENSION)
+}
+
+/// Add `path` (file or directory) to the archive under `name`, skipping secrets
+fn add_to_archive(zip: &mut ZipWriter<File>, path: &Path, name: &str) -> io::Result<()> {
+    let options = SimpleFileOptions::default();
+    if path.is_dir() {
+        zip.add_directory(format!("{}/", name), options)?;
+        let mut entries: Vec<_> = fs::read_dir(path)?.collect::<Result<_, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries.into_iter().filter(|e| !is_secret(&e.path())) {
+            let child_name = format!("{}/{}", name, entry.file_name().to_string_lossy());
+            add_to_archive(zip, &entry.path(), &child_name)?;
+        }
+    } else {
+        zip.start_file(name, options)?;
+        zip.write_all(&fs::read(path)?)?;
+    }
+    Ok(())
+}
+
+/// Zip `.nekotick/` and `nekotick.md` from `base_dir` into `backup_dir`, without credentials
+pub fn create_backup_archive(base_dir: &Path, backup_dir: &Path, timestamp: i64) -> Result<PathBuf, String> {
+    fs::create_dir_all(backup_dir).map_err(|e| e.to_string())?;
+    let backup_path = backup_dir.join(format!("nekotick-backup-{}.zip", timestamp));
+
+    let file = File::create(&backup_path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+    for name in [NEKOTICK_FOLDER, NOTES_FILE_NAME] {
+        let path = base_dir.join(name);
+        if path.exists() {
+            add_to_archive(&mut zip, &path, name).map_err(|e| format!("Failed to archive {}: {}", name, e))?;
+        }
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(backup_path)
+}
+
+/// Back up and then delete all local state under `base_dir`
+///
+/// Refuses to do anything unless `confirm` matches this device's
+/// `factory_reset_confirmation`. Returns the path of the backup archive.
+pub fn reset_local_state(base_dir: &Path, confirm: &str, timestamp: i64) -> Result<PathBuf, String> {
+    let expected = factory_reset_confirmation(base_dir)?;
+    if confirm != expected {
+        return Err(format!("Type {} to confirm the factory reset", expected));
+    }
+
+    let backup_path = create_backup_archive(base_dir, &base_dir.join(BACKUPS_FOLDER), timestamp)?;
+
+    let store_dir = base_dir.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+    clear_github_local_state(&store_dir)?;
+    if store_dir.exists() {
+        fs::remove_dir_all(&store_dir).map_err(|e| format!("Failed to remove local data: {}", e))?;
+    }
+    for name in [SYNC_BASE_FILE, SYNC_BASE_NOTES_FILE, WEBDAV_SYNC_BASE_FILE] {
+        let sync_base_path = base_dir.join(NEKOTICK_FOLDER).join(name);
+        if sync_base_path.exists() {
+            fs::remove_file(&sync_base_path).map_err(|e| format!("Failed to remove sync base: {}", e))?;
+        }
+    }
+    clear_sync_queue(&base_dir.join(NEKOTICK_FOLDER))?;
+
+    Ok(backup_path)
+}
+
+/// Code the user must type to confirm `factory_reset` on this device
+#[tauri::command]
+pub async fn get_factory_reset_confirmation(app: tauri::AppHandle) -> Result<String, String> {
+    factory_reset_confirmation(&paths::data_dir(&app)?)
+}
+
+/// Delete all local app state after archiving it, returning the backup path
+#[tauri::command]
+pub async fn factory_reset(app: tauri::AppHandle, confirm: String) -> Result<String, String> {
+    let base_dir = paths::data_dir(&app)?;
+    let timestamp = chrono::Utc::now().timestamp();
+    reset_local_state(&base_dir, &confirm, timestamp).map(|p| p.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populate(base: &Path) -> PathBuf {
+        let store_dir = base.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+        fs::create_dir_all(&store_dir).unwrap();
+        fs::write(store_dir.join("data.json"), r#"{"version":2}"#).unwrap();
+        fs::write(store_dir.join("github_credentials.json"), "{}").unwrap();
+        fs::write(base.join(NOTES_FILE_NAME), "# notes").unwrap();
+        store_dir
+    }
+
+    #[test]
+    fn test_wrong_confirmation_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_dir = populate(dir.path());
+
+        assert!(reset_local_state(dir.path(), "RESET", 1).is_err());
+        assert!(reset_local_state(dir.path(), "", 1).is_err());
+        assert!(store_dir.join("data.json").exists());
+        assert!(!dir.path().join(BACKUPS_FOLDER).exists());
+    }
+
+    #[test]
+    fn test_reset_backs_up_before_deleting() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_dir = populate(dir.path());
+
+        fs::write(store_dir.join("github_credentials.enc"), "nekotick-enc-v1:secret").unwrap();
+        fs::write(store_dir.join(".sync_meta_key"), "key").unwrap();
+        fs::write(store_dir.join(".credential_key"), "key").unwrap();
+        fs::write(dir.path().join(NEKOTICK_FOLDER).join("sync_queue.json"), "{}").unwrap();
+
+        let confirm = factory_reset_confirmation(dir.path()).unwrap();
+        let backup_path = reset_local_state(dir.path(), &confirm, 42).unwrap();
+        assert!(!store_dir.exists());
+        assert!(!dir.path().join(NEKOTICK_FOLDER).join("sync_queue.json").exists());
+        assert_eq!(backup_path.file_name().unwrap(), "nekotick-backup-42.zip");
+
+        let mut archive = zip::ZipArchive::new(File::open(&backup_path).unwrap()).unwrap();
+        let mut data = String::new();
+        io::Read::read_to_string(&mut archive.by_name(".nekotick/store/data.json").unwrap(), &mut data).unwrap();
+        assert_eq!(data, r#"{"version":2}"#);
+        assert!(archive.by_name(".nekotick/store/github_credentials.json").is_ok());
+        assert!(archive.by_name(NOTES_FILE_NAME).is_ok());
+        assert!(archive.by_name(".nekotick/store/github_credentials.enc").is_err());
+        assert!(archive.by_name(".nekotick/store/.sync_meta_key").is_err());
+        assert!(archive.by_name(".nekotick/store/.device_id").is_err());
+        assert!(archive.by_name(".nekotick/store/.credential_key").is_err());
+    }
+
+    #[test]
+    fn test_confirmation_code_is_per_device() {
+        let code = confirmation_code("machine-a");
+        assert!(code.starts_with("RESET-"));
+        assert_eq!(code.len(), "RESET-".len() + 6);
+        assert_eq!(code, confirmation_code("machine-a"));
+        assert_ne!(code, confirmation_code("machine-b"));
+    }
+}
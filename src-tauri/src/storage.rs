@@ -0,0 +1,113 @@
+//! Local storage health checks
+//!
+//! Probes whether the app data directory can be written so permission or
+//! disk-full problems surface with a clear cause instead of failing deep
+//! inside a save.
+
+use crate::paths;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const PROBE_FILE: &str = ".write_probe";
+
+/// Raw OS error codes for "no space left on device"
+#[cfg(windows)]
+const DISK_FULL_OS_ERRORS: &[i32] = &[39, 112]; // ERROR_HANDLE_DISK_FULL, ERROR_DISK_FULL
+#[cfg(not(windows))]
+const DISK_FULL_OS_ERRORS: &[i32] = &[28]; // ENOSPC
+
+/// Result of probing the storage directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum StorageWritability {
+    Writable,
+    PermissionDenied { message: String },
+    DiskFull { message: String },
+    Other { message: String },
+}
+
+/// Classify an io error from a storage operation
+pub fn classify_io_error(error: &io::Error) -> StorageWritability {
+    let message = error.to_string();
+
+    if error.kind() == io::ErrorKind::PermissionDenied {
+        return StorageWritability::PermissionDenied { message };
+    }
+
+    let disk_full = error.kind() == io::ErrorKind::StorageFull
+        || error
+            .raw_os_error()
+            .is_some_and(|code| DISK_FULL_OS_ERRORS.contains(&code));
+    if disk_full {
+        return StorageWritability::DiskFull { message };
+    }
+
+    StorageWritability::Other { message }
+}
+
+/// Create `dir`, then write and delete a probe file inside it
+pub fn probe_writable(dir: &Path) -> StorageWritability {
+    let probe = || -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(PROBE_FILE);
+        fs::write(&path, b"nekotick")?;
+        fs::remove_file(&path)
+    };
+
+    match probe() {
+        Ok(()) => StorageWritability::Writable,
+        Err(e) => classify_io_error(&e),
+    }
+}
+
+/// Check whether the credential and data store can be written
+#[tauri::command]
+pub async fn check_storage_writable(app: tauri::AppHandle) -> Result<StorageWritability, String> {
+    let base = paths::data_dir(&app)?;
+    let store_dir = base.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+
+    tokio::task::spawn_blocking(move || probe_writable(&store_dir))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_denied_classification() {
+        let error = io::Error::new(io::ErrorKind::PermissionDenied, "access denied");
+        assert!(matches!(classify_io_error(&error), StorageWritability::PermissionDenied { .. }));
+    }
+
+    #[test]
+    fn test_disk_full_classification() {
+        let error = io::Error::from_raw_os_error(DISK_FULL_OS_ERRORS[0]);
+        assert!(matches!(classify_io_error(&error), StorageWritability::DiskFull { .. }));
+
+        let error = io::Error::new(io::ErrorKind::StorageFull, "no space");
+        assert!(matches!(classify_io_error(&error), StorageWritability::DiskFull { .. }));
+    }
+
+    #[test]
+    fn test_other_classification() {
+        let error = io::Error::new(io::ErrorKind::InvalidData, "bad data");
+        assert_eq!(
+            classify_io_error(&error),
+            StorageWritability::Other { message: "bad data".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_probe_writable_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("store");
+        assert_eq!(probe_writable(&store), StorageWritability::Writable);
+        assert!(!store.join(PROBE_FILE).exists());
+    }
+}
@@ -0,0 +1,249 @@
+//! Opt-in end-to-end encryption of synced data.json
+//!
+//! With a sync passphrase set, data.json is sealed with AES-256-GCM under a
+//! key derived from the passphrase (Argon2id) before it leaves the device,
+//! so the sync provider only ever stores ciphertext. The salt travels in
+//! the payload, letting any device with the passphrase decrypt it; the
+//! local salt for new uploads is kept in `sync_encryption.json`. The
+//! passphrase itself is stored with the device-bound credential store.
+//!
+//! Setting or clearing the passphrase doesn't touch the data, so backends
+//! compare `upload_marker` with the marker of what the remote holds and
+//! push again when they differ, even if data.json itself is unchanged.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::github::credential_store;
+use crate::paths;
+use crate::sync_error::SyncError;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const SYNC_ENCRYPTION_FILE: &str = "sync_encryption.json";
+const SYNC_PASSPHRASE_FILE: &str = "sync_passphrase.enc";
+
+const PAYLOAD_PREFIX: &str = "nekotick-e2e-v1:";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Encryption settings shared by all sync backends
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncEncryptionMeta {
+    /// Base64 salt used to derive the key for new uploads
+    salt: Option<String>,
+}
+
+/// Whether `content` is an encrypted sync payload
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with(PAYLOAD_PREFIX)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; 32], String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive encryption key: {}", e))?;
+    Ok(key)
+}
+
+/// Encrypt `content` as `nekotick-e2e-v1:<base64(salt || nonce || ciphertext)>`
+pub fn encrypt_payload(content: &str, passphrase: &str, salt: &[u8]) -> Result<String, String> {
+    let key = derive_key(passphrase, salt)?;
+    let mut nonce = [0u8; NONCE_LENGTH];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let ciphertext = Aes256Gcm::new((&key).into())
+        .encrypt(Nonce::from_slice(&nonce), content.as_bytes())
+        .map_err(|_| "Failed to encrypt sync data".to_string())?;
+
+    let mut sealed = salt.to_vec();
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(format!("{}{}", PAYLOAD_PREFIX, STANDARD.encode(sealed)))
+}
+
+/// Decrypt a payload produced by `encrypt_payload`
+pub fn decrypt_payload(payload: &str, passphrase: &str) -> Result<String, SyncError> {
+    let encoded = payload
+        .trim()
+        .strip_prefix(PAYLOAD_PREFIX)
+        .ok_or("Sync data is not encrypted")?;
+    let bytes = STANDARD.decode(encoded).map_err(|e| e.to_string())?;
+    if bytes.len() < SALT_LENGTH + NONCE_LENGTH {
+        return Err("Encrypted sync data is truncated".into());
+    }
+
+    let (salt, rest) = bytes.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    let key = derive_key(passphrase, salt)?;
+    let plaintext = Aes256Gcm::new((&key).into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| SyncError::WrongPassphrase)?;
+    String::from_utf8(plaintext).map_err(|e| SyncError::Other(e.to_string()))
+}
+
+fn load_passphrase(store_dir: &Path) -> Result<Option<String>, String> {
+    let Some(bytes) = credential_store::read_encrypted(store_dir, &store_dir.join(SYNC_PASSPHRASE_FILE))? else {
+        return Ok(None);
+    };
+    String::from_utf8(bytes).map(Some).map_err(|e| e.to_string())
+}
+
+fn load_salt(store_dir: &Path) -> Option<Vec<u8>> {
+    let content = fs::read_to_string(store_dir.join(SYNC_ENCRYPTION_FILE)).ok()?;
+    let meta: SyncEncryptionMeta = serde_json::from_str(&content).ok()?;
+    STANDARD.decode(meta.salt?).ok().filter(|salt| salt.len() == SALT_LENGTH)
+}
+
+/// Generate and persist a fresh salt for new uploads
+fn write_new_salt(store_dir: &Path) -> Result<Vec<u8>, String> {
+    let mut salt = vec![0u8; SALT_LENGTH];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let meta = SyncEncryptionMeta {
+        salt: Some(STANDARD.encode(&salt)),
+    };
+    let content = serde_json::to_string_pretty(&meta).map_err(|e| e.to_string())?;
+    fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    fs::write(store_dir.join(SYNC_ENCRYPTION_FILE), content).map_err(|e| e.to_string())?;
+    Ok(salt)
+}
+
+/// How uploads are sealed right now: the local salt when a passphrase is
+/// set (empty until a salt exists), `None` for plaintext
+pub fn upload_marker(store_dir: &Path) -> Option<String> {
+    if !store_dir.join(SYNC_PASSPHRASE_FILE).exists() {
+        return None;
+    }
+    Some(load_salt(store_dir).map(|salt| STANDARD.encode(salt)).unwrap_or_default())
+}
+
+/// The `upload_marker` a remote payload was sealed with: its salt, or `None` for plaintext
+pub fn payload_marker(content: &str) -> Option<String> {
+    let encoded = content.trim().strip_prefix(PAYLOAD_PREFIX)?;
+    let salt = STANDARD
+        .decode(encoded)
+        .ok()
+        .filter(|bytes| bytes.len() >= SALT_LENGTH)
+        .map(|bytes| STANDARD.encode(&bytes[..SALT_LENGTH]));
+    Some(salt.unwrap_or_default())
+}
+
+/// Encrypt data.json for upload when a sync passphrase is set
+///
+/// A passphrase without a usable salt gets a new salt rather than falling
+/// back to plaintext; the salt travels in the payload, so nothing else
+/// depends on the old one.
+pub fn seal_for_upload(store_dir: &Path, content: &str) -> Result<String, String> {
+    let Some(passphrase) = load_passphrase(store_dir)? else {
+        return Ok(content.to_string());
+    };
+    let salt = match load_salt(store_dir) {
+        Some(salt) => salt,
+        None => write_new_salt(store_dir)?,
+    };
+    encrypt_payload(content, &passphrase, &salt)
+}
+
+/// Decrypt downloaded data.json; plaintext from unencrypted uploads passes through
+pub fn open_download(store_dir: &Path, content: String) -> Result<String, SyncError> {
+    if !is_encrypted(&content) {
+        return Ok(content);
+    }
+    let passphrase = load_passphrase(store_dir)?.ok_or(SyncError::WrongPassphrase)?;
+    decrypt_payload(&content, &passphrase)
+}
+
+fn get_store_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let base = paths::data_dir(app)?;
+    Ok(base.join(NEKOTICK_FOLDER).join(STORE_FOLDER))
+}
+
+/// Encrypt future uploads with `passphrase`
+///
+/// A fresh salt is generated each time the passphrase is set, and written
+/// before the passphrase so a failed write never leaves a passphrase that
+/// uploads with a stale salt. The next sync on each backend re-uploads the
+/// data encrypted, even when it is unchanged. Every device syncing the same
+/// data needs the same passphrase.
+#[tauri::command]
+pub async fn set_sync_passphrase(app: tauri::AppHandle, passphrase: String) -> Result<(), String> {
+    if passphrase.is_empty() {
+        return Err("Passphrase cannot be empty".to_string());
+    }
+
+    save_passphrase(&get_store_dir(&app)?, &passphrase)
+}
+
+/// Store `passphrase` under a fresh salt
+pub fn save_passphrase(store_dir: &Path, passphrase: &str) -> Result<(), String> {
+    write_new_salt(store_dir)?;
+    credential_store::write_encrypted(store_dir, &store_dir.join(SYNC_PASSPHRASE_FILE), passphrase.as_bytes())
+}
+
+/// Stop encrypting uploads and forget the passphrase
+///
+/// The next sync on each backend uploads plaintext over the encrypted copy,
+/// even when data.json is unchanged. A bidirectional sync can't read that
+/// copy without the passphrase, so use a one-way upload to replace it.
+#[tauri::command]
+pub async fn clear_sync_passphrase(app: tauri::AppHandle) -> Result<(), String> {
+    let store_dir = get_store_dir(&app)?;
+    for file_name in [SYNC_PASSPHRASE_FILE, SYNC_ENCRYPTION_FILE] {
+        let path = store_dir.join(file_name);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALT: [u8; SALT_LENGTH] = [3u8; SALT_LENGTH];
+
+    #[test]
+    fn test_round_trip() {
+        let payload = encrypt_payload("{\"version\":2}", "correct horse", &SALT).unwrap();
+        assert!(is_encrypted(&payload));
+        assert!(!payload.contains("version"));
+        assert_eq!(decrypt_payload(&payload, "correct horse").unwrap(), "{\"version\":2}");
+    }
+
+    #[test]
+    fn test_wrong_passphrase() {
+        let payload = encrypt_payload("{}", "correct horse", &SALT).unwrap();
+        assert!(matches!(decrypt_payload(&payload, "battery staple"), Err(SyncError::WrongPassphrase)));
+    }
+
+    #[test]
+    fn test_plaintext_passes_through_without_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(seal_for_upload(dir.path(), "{}").unwrap(), "{}");
+        assert_eq!(open_download(dir.path(), "{}".to_string()).unwrap(), "{}");
+
+        let payload = encrypt_payload("{}", "correct horse", &SALT).unwrap();
+        assert!(matches!(open_download(dir.path(), payload), Err(SyncError::WrongPassphrase)));
+    }
+
+    #[test]
+    fn test_passphrase_without_salt_still_encrypts() {
+        let dir = tempfile::tempdir().unwrap();
+        credential_store::write_encrypted(dir.path(), &dir.path().join(SYNC_PASSPHRASE_FILE), b"correct horse").unwrap();
+        fs::write(dir.path().join(SYNC_ENCRYPTION_FILE), "{\"salt\":\"not base64\"}").unwrap();
+
+        let sealed = seal_for_upload(dir.path(), "{\"version\":2}").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert!(load_salt(dir.path()).is_some());
+        assert_eq!(open_download(dir.path(), sealed).unwrap(), "{\"version\":2}");
+    }
+}
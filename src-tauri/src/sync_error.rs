@@ -0,0 +1,225 @@
+//! Structured errors for sync commands
+//!
+//! Serialized as `{ "code": "...", "message": "..." }` so the frontend can
+//! branch and localize on `code` instead of matching error text. Messages
+//! are passed through `redact`, since provider error bodies can echo tokens.
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::github::gist_api::GistApiError;
+use crate::redact::redact_error;
+use crate::tasks::MergeError;
+use crate::webdav::client::WebDavError;
+
+/// Error returned by sync commands
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("Not connected to {0}")]
+    NotConnected(String),
+    #[error("Sync credentials were rejected; reconnect to continue")]
+    Unauthorized,
+    #[error("Rate limited by the sync provider; try again later")]
+    RateLimited,
+    #[error("This feature requires NekoTick Pro")]
+    ProRequired,
+    #[error("System clock looks wrong; check the date and time settings")]
+    TimeTamper,
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("A sync is already in progress")]
+    AlreadyRunning,
+    #[error("Sync was cancelled")]
+    Cancelled,
+    #[error("Remote data is encrypted; enter the sync passphrase it was encrypted with")]
+    WrongPassphrase,
+    #[error("Remote data was written by a newer version of NekoTick. Update the app before syncing.")]
+    SchemaTooNew,
+    #[error("{0}")]
+    Other(String),
+}
+
+impl SyncError {
+    /// Stable identifier for the frontend
+    pub fn code(&self) -> &'static str {
+        match self {
+            SyncError::NotConnected(_) => "not_connected",
+            SyncError::Unauthorized => "unauthorized",
+            SyncError::RateLimited => "rate_limited",
+            SyncError::ProRequired => "pro_required",
+            SyncError::TimeTamper => "time_tamper",
+            SyncError::Network(_) => "network",
+            SyncError::Conflict(_) => "conflict",
+            SyncError::AlreadyRunning => "already_running",
+            SyncError::Cancelled => "cancelled",
+            SyncError::WrongPassphrase => "wrong_passphrase",
+            SyncError::SchemaTooNew => "schema_too_new",
+            SyncError::Other(_) => "other",
+        }
+    }
+}
+
+impl Serialize for SyncError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SyncError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &redact_error(self))?;
+        state.end()
+    }
+}
+
+/// Outcome of the most recent sync attempt, kept in each backend's sync metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LastSyncOutcome {
+    pub success: bool,
+    pub error_code: Option<String>,
+    pub error: Option<String>,
+    pub timestamp: i64,
+}
+
+impl LastSyncOutcome {
+    /// Outcome of a sync command's result
+    ///
+    /// `reported_failure` turns an `Ok` result that reports failure in-band
+    /// into an error. Returns `None` for `AlreadyRunning`, since the sync
+    /// that holds the lock records its own outcome.
+    pub fn from_result<T>(
+        result: &Result<T, SyncError>,
+        reported_failure: impl FnOnce(&T) -> Option<SyncError>,
+        timestamp: i64,
+    ) -> Option<Self> {
+        let failure = |e: &SyncError| LastSyncOutcome {
+            success: false,
+            error_code: Some(e.code().to_string()),
+            error: Some(redact_error(e)),
+            timestamp,
+        };
+        match result {
+            Err(SyncError::AlreadyRunning) => None,
+            Err(e) => Some(failure(e)),
+            Ok(value) => Some(match reported_failure(value) {
+                Some(e) => failure(&e),
+                None => LastSyncOutcome {
+                    success: true,
+                    error_code: None,
+                    error: None,
+                    timestamp,
+                },
+            }),
+        }
+    }
+}
+
+/// GitHub reports an exhausted quota as 403 (or 429) with this text in the body
+fn is_rate_limit_message(message: &str) -> bool {
+    message.to_ascii_lowercase().contains("rate limit")
+}
+
+impl From<String> for SyncError {
+    fn from(message: String) -> Self {
+        SyncError::Other(message)
+    }
+}
+
+impl From<&str> for SyncError {
+    fn from(message: &str) -> Self {
+        SyncError::Other(message.to_string())
+    }
+}
+
+impl From<GistApiError> for SyncError {
+    fn from(e: GistApiError) -> Self {
+        match e {
+            GistApiError::NetworkError(message) => SyncError::Network(message),
+            e @ GistApiError::Timeout => SyncError::Network(e.to_string()),
+            GistApiError::Unauthorized => SyncError::Unauthorized,
+            GistApiError::RateLimited { .. } => SyncError::RateLimited,
+            GistApiError::ApiError(message) if is_rate_limit_message(&message) => SyncError::RateLimited,
+            e @ GistApiError::RemoteChanged { .. } => SyncError::Conflict(e.to_string()),
+            e => SyncError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<WebDavError> for SyncError {
+    fn from(e: WebDavError) -> Self {
+        match e {
+            WebDavError::NetworkError(message) => SyncError::Network(message),
+            WebDavError::Unauthorized => SyncError::Unauthorized,
+            e @ WebDavError::RemoteChanged => SyncError::Conflict(e.to_string()),
+            e => SyncError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<MergeError> for SyncError {
+    fn from(e: MergeError) -> Self {
+        SyncError::Other(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let json = serde_json::to_value(SyncError::NotConnected("GitHub".to_string())).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "not_connected", "message": "Not connected to GitHub" }));
+    }
+
+    #[test]
+    fn test_message_is_redacted() {
+        let error = SyncError::Other("Bad credentials for token ghp_abc123".to_string());
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["message"], "Bad credentials for token ghp_[REDACTED]");
+    }
+
+    #[test]
+    fn test_gist_errors_map_to_codes() {
+        let rate_limited = GistApiError::ApiError("{\"message\":\"API rate limit exceeded for user ID 1.\"}".to_string());
+        assert_eq!(SyncError::from(rate_limited).code(), "rate_limited");
+        assert_eq!(SyncError::from(GistApiError::NetworkError("timeout".to_string())).code(), "network");
+        assert_eq!(SyncError::from(GistApiError::Timeout).to_string(), "Network error: GitHub timed out");
+        assert_eq!(SyncError::from(GistApiError::Unauthorized).code(), "unauthorized");
+
+        let changed = GistApiError::RemoteChanged { expected: "a".to_string(), actual: "b".to_string() };
+        assert_eq!(SyncError::from(changed).code(), "conflict");
+        assert_eq!(SyncError::from(GistApiError::ApiError("Server Error".to_string())).code(), "other");
+    }
+
+    #[test]
+    fn test_plain_messages_become_other() {
+        let error = SyncError::from("Failed to read data.json".to_string());
+        assert_eq!(error.code(), "other");
+        assert_eq!(error.to_string(), "Failed to read data.json");
+    }
+
+    #[test]
+    fn test_outcome_records_errors_and_reported_failures() {
+        let ok: Result<bool, SyncError> = Ok(true);
+        let outcome = LastSyncOutcome::from_result(&ok, |_| None, 100).unwrap();
+        assert!(outcome.success);
+        assert_eq!(outcome.error_code, None);
+
+        let reported: Result<bool, SyncError> = Ok(false);
+        let outcome = LastSyncOutcome::from_result(&reported, |_| Some(SyncError::Conflict("Remote changed".to_string())), 100).unwrap();
+        assert!(!outcome.success);
+        assert_eq!(outcome.error_code.as_deref(), Some("conflict"));
+        assert_eq!(outcome.error.as_deref(), Some("Remote changed"));
+
+        let failed: Result<bool, SyncError> = Err(SyncError::Network("timeout".to_string()));
+        let outcome = LastSyncOutcome::from_result(&failed, |_| None, 100).unwrap();
+        assert_eq!(outcome.error_code.as_deref(), Some("network"));
+        assert_eq!(outcome.timestamp, 100);
+    }
+
+    #[test]
+    fn test_already_running_is_not_recorded() {
+        let busy: Result<bool, SyncError> = Err(SyncError::AlreadyRunning);
+        assert_eq!(LastSyncOutcome::from_result(&busy, |_| None, 100), None);
+    }
+}
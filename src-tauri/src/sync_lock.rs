@@ -0,0 +1,231 @@
+//! Per-backend sync locks
+//!
+//! A timer-driven sync and a user-initiated one must not interleave their
+//! uploads, so every sync command takes its backend's lock first and fails
+//! fast with `SyncError::AlreadyRunning` instead of queueing.
+//!
+//! Each acquired lock also carries a fresh `CancellationToken`. The
+//! `cancel_sync` command trips the tokens of running syncs, which check them
+//! between steps and stop before their next write.
+
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_util::sync::CancellationToken;
+
+use crate::sync_error::SyncError;
+
+/// Sync backend a lock belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncBackend {
+    GitHub,
+    WebDav,
+}
+
+/// Process-wide sync locks, held in Tauri managed state
+#[derive(Default)]
+pub struct SyncLocks {
+    github: Mutex<()>,
+    webdav: Mutex<()>,
+    github_cancel: std::sync::Mutex<CancellationToken>,
+    webdav_cancel: std::sync::Mutex<CancellationToken>,
+}
+
+/// A held sync lock and the token that cancels this sync
+pub struct SyncGuard<'a> {
+    _lock: MutexGuard<'a, ()>,
+    pub cancel: CancellationToken,
+}
+
+impl SyncLocks {
+    fn cancel_slot(&self, backend: SyncBackend) -> &std::sync::Mutex<CancellationToken> {
+        match backend {
+            SyncBackend::GitHub => &self.github_cancel,
+            SyncBackend::WebDav => &self.webdav_cancel,
+        }
+    }
+
+    /// Take `backend`'s lock, or fail if a sync on it is already running
+    ///
+    /// The lock is released when the returned guard is dropped.
+    pub fn try_acquire(&self, backend: SyncBackend) -> Result<SyncGuard<'_>, SyncError> {
+        let lock = match backend {
+            SyncBackend::GitHub => &self.github,
+            SyncBackend::WebDav => &self.webdav,
+        };
+        let lock = lock.try_lock().map_err(|_| SyncError::AlreadyRunning)?;
+
+        // A cancel aimed at an earlier sync must not stop this one
+        let cancel = CancellationToken::new();
+        if let Ok(mut slot) = self.cancel_slot(backend).lock() {
+            *slot = cancel.clone();
+        }
+        Ok(SyncGuard { _lock: lock, cancel })
+    }
+
+    /// Cancel the running sync on every backend
+    pub fn cancel_all(&self) {
+        for backend in [SyncBackend::GitHub, SyncBackend::WebDav] {
+            if let Ok(slot) = self.cancel_slot(backend).lock() {
+                slot.cancel();
+            }
+        }
+    }
+}
+
+/// Fail with `SyncError::Cancelled` once `cancel` has been tripped
+pub fn check_cancelled(cancel: &CancellationToken) -> Result<(), SyncError> {
+    if cancel.is_cancelled() {
+        Err(SyncError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Result of a push step that checks the remote before uploading
+#[derive(Debug)]
+pub enum PushOutcome<T> {
+    Pushed(T),
+    /// Another device pushed in the meantime; nothing was uploaded
+    RemoteChanged(String),
+}
+
+/// Final steps of a bidirectional sync: write the merge locally, then push
+/// it when `needs_push` says the remote is behind
+///
+/// `cancel` is checked before each step, so a sync cancelled before the
+/// local write leaves data.json untouched and one cancelled before the
+/// push uploads nothing. Returns what `write_local` returned and the
+/// push's result, or `None` when nothing needed pushing.
+pub async fn write_then_push<L, T, Fut>(
+    cancel: &CancellationToken,
+    write_local: impl FnOnce() -> Result<L, SyncError>,
+    needs_push: bool,
+    push: impl FnOnce() -> Fut,
+) -> Result<(L, Option<T>), SyncError>
+where
+    Fut: std::future::Future<Output = Result<T, SyncError>>,
+{
+    check_cancelled(cancel)?;
+    let local = write_local()?;
+    if !needs_push {
+        return Ok((local, None));
+    }
+
+    check_cancelled(cancel)?;
+    Ok((local, Some(push().await?)))
+}
+
+/// Ask running syncs to stop before their next write
+#[tauri::command]
+pub async fn cancel_sync(locks: tauri::State<'_, SyncLocks>) -> Result<(), String> {
+    locks.cancel_all();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_sync_is_rejected_until_first_finishes() {
+        let locks = SyncLocks::default();
+
+        let guard = locks.try_acquire(SyncBackend::GitHub).unwrap();
+        assert!(matches!(locks.try_acquire(SyncBackend::GitHub), Err(SyncError::AlreadyRunning)));
+
+        drop(guard);
+        assert!(locks.try_acquire(SyncBackend::GitHub).is_ok());
+    }
+
+    /// Run `write_then_push` with a local step that writes `merged` to
+    /// `data_json` and a push step that counts its calls
+    async fn run_final_steps(
+        cancel: &CancellationToken,
+        data_json: &std::path::Path,
+        merged: &str,
+        cancel_after_write: bool,
+        uploads: &mut usize,
+    ) -> Result<(bool, Option<PushOutcome<()>>), SyncError> {
+        write_then_push(
+            cancel,
+            || {
+                std::fs::write(data_json, merged).map_err(|e| e.to_string())?;
+                if cancel_after_write {
+                    cancel.cancel();
+                }
+                Ok(true)
+            },
+            true,
+            || async {
+                *uploads += 1;
+                Ok(PushOutcome::Pushed(()))
+            },
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_cancel_before_push_skips_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_json = dir.path().join("data.json");
+        std::fs::write(&data_json, "local").unwrap();
+
+        let locks = SyncLocks::default();
+        let guard = locks.try_acquire(SyncBackend::GitHub).unwrap();
+        locks.cancel_all();
+
+        let mut uploads = 0;
+        let result = run_final_steps(&guard.cancel, &data_json, "merged", false, &mut uploads).await;
+        assert!(matches!(result, Err(SyncError::Cancelled)));
+        assert_eq!(uploads, 0);
+        assert_eq!(std::fs::read_to_string(&data_json).unwrap(), "local");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_after_local_write_skips_upload() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_json = dir.path().join("data.json");
+
+        let mut uploads = 0;
+        let result = run_final_steps(&CancellationToken::new(), &data_json, "merged", true, &mut uploads).await;
+        assert!(matches!(result, Err(SyncError::Cancelled)));
+        assert_eq!(uploads, 0);
+        assert_eq!(std::fs::read_to_string(&data_json).unwrap(), "merged");
+    }
+
+    #[tokio::test]
+    async fn test_push_runs_only_when_needed() {
+        let cancel = CancellationToken::new();
+        let mut uploads = 0;
+
+        let (_, pushed) = write_then_push(&cancel, || Ok(()), false, || async {
+            uploads += 1;
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert!(pushed.is_none());
+        assert_eq!(uploads, 0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let result = run_final_steps(&cancel, &dir.path().join("data.json"), "merged", false, &mut uploads).await;
+        assert!(matches!(result, Ok((true, Some(PushOutcome::Pushed(()))))));
+        assert_eq!(uploads, 1);
+    }
+
+    #[test]
+    fn test_next_sync_gets_fresh_token() {
+        let locks = SyncLocks::default();
+        drop(locks.try_acquire(SyncBackend::GitHub).unwrap());
+        locks.cancel_all();
+
+        let guard = locks.try_acquire(SyncBackend::GitHub).unwrap();
+        assert!(check_cancelled(&guard.cancel).is_ok());
+    }
+
+    #[test]
+    fn test_backends_lock_independently() {
+        let locks = SyncLocks::default();
+        let _github = locks.try_acquire(SyncBackend::GitHub).unwrap();
+        assert!(locks.try_acquire(SyncBackend::WebDav).is_ok());
+    }
+}
@@ -0,0 +1,355 @@
+//! Offline sync queue
+//!
+//! A push that fails with a network error is remembered in
+//! `.nekotick/sync_queue.json`, one entry per backend with the hash of the
+//! local data.json it tried to upload. The queue is flushed at startup, on
+//! every auto-sync tick and by `flush_sync_queue`, and an entry is dropped
+//! as soon as a push or two-way sync with its backend succeeds or the
+//! backend is disconnected.
+
+use crate::github::commands::sync_to_github;
+use crate::paths;
+use crate::sync_error::SyncError;
+use crate::sync_lock::SyncLocks;
+use crate::webdav::commands::sync_to_webdav;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const DATA_FILE_NAME: &str = "data.json";
+const SYNC_QUEUE_FILE: &str = "sync_queue.json";
+
+/// A push that is waiting for its backend to come back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingPush {
+    /// `github` or `webdav`
+    pub backend: String,
+    /// SHA-256 (hex) of data.json when the push failed
+    pub local_hash: Option<String>,
+    pub queued_at: i64,
+}
+
+/// Persisted queue of pending pushes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncQueue {
+    pub pending: Vec<PendingPush>,
+}
+
+impl SyncQueue {
+    /// Queue a push for `backend`, replacing any older entry for it
+    pub fn enqueue(&mut self, backend: &str, local_hash: Option<String>, now: i64) {
+        self.remove(backend);
+        self.pending.push(PendingPush {
+            backend: backend.to_string(),
+            local_hash,
+            queued_at: now,
+        });
+    }
+
+    /// Drop `backend`'s entry; returns whether there was one
+    pub fn remove(&mut self, backend: &str) -> bool {
+        let before = self.pending.len();
+        self.pending.retain(|entry| entry.backend != backend);
+        self.pending.len() != before
+    }
+
+    pub fn contains(&self, backend: &str) -> bool {
+        self.pending.iter().any(|entry| entry.backend == backend)
+    }
+}
+
+/// A queued push as reported by `get_sync_queue`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingPushStatus {
+    #[serde(flatten)]
+    pub entry: PendingPush,
+    /// data.json changed after the push was queued, so the retry uploads newer data
+    pub local_changed_since: bool,
+}
+
+/// Result of `flush_sync_queue`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncQueueFlush {
+    /// Backends whose pending push went through
+    pub pushed: Vec<String>,
+    /// Backends still waiting, e.g. because they are still unreachable
+    pub still_pending: Vec<String>,
+}
+
+fn queue_path(nekotick_dir: &Path) -> PathBuf {
+    nekotick_dir.join(SYNC_QUEUE_FILE)
+}
+
+/// Load the queue from `.nekotick`, treating a missing or corrupt file as empty
+pub fn load_sync_queue(nekotick_dir: &Path) -> SyncQueue {
+    fs::read_to_string(queue_path(nekotick_dir))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_sync_queue(nekotick_dir: &Path, queue: &SyncQueue) -> Result<(), String> {
+    fs::create_dir_all(nekotick_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(queue).map_err(|e| e.to_string())?;
+    fs::write(queue_path(nekotick_dir), content).map_err(|e| e.to_string())
+}
+
+/// Delete the queue file along with every pending push
+pub fn clear_sync_queue(nekotick_dir: &Path) -> Result<(), String> {
+    let path = queue_path(nekotick_dir);
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", SYNC_QUEUE_FILE, e))?;
+    }
+    Ok(())
+}
+
+/// Drop `backend`'s pending push, deleting the queue file once nothing is left
+pub fn forget_backend(nekotick_dir: &Path, backend: &str) -> Result<(), String> {
+    let mut queue = load_sync_queue(nekotick_dir);
+    if !queue.remove(backend) {
+        return Ok(());
+    }
+    if queue.pending.is_empty() {
+        clear_sync_queue(nekotick_dir)
+    } else {
+        save_sync_queue(nekotick_dir, &queue)
+    }
+}
+
+/// Pair each queued push with whether data.json moved on since it was queued
+fn pending_status(queue: SyncQueue, current_hash: Option<&str>) -> Vec<PendingPushStatus> {
+    queue
+        .pending
+        .into_iter()
+        .map(|entry| {
+            let local_changed_since = entry.local_hash.as_deref() != current_hash;
+            PendingPushStatus { entry, local_changed_since }
+        })
+        .collect()
+}
+
+/// SHA-256 (hex) of the local data.json, `None` if it can't be read
+fn local_data_hash(nekotick_dir: &Path) -> Option<String> {
+    let content = fs::read(nekotick_dir.join(STORE_FOLDER).join(DATA_FILE_NAME)).ok()?;
+    Some(Sha256::digest(&content).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn get_nekotick_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(paths::data_dir(app)?.join(NEKOTICK_FOLDER))
+}
+
+/// Queue change a push result calls for: `Some(true)` to queue, `Some(false)` to clear
+fn queue_action<T>(result: &Result<T, SyncError>, pushed: impl FnOnce(&T) -> bool) -> Option<bool> {
+    match result {
+        Err(SyncError::Network(_)) => Some(true),
+        Ok(value) if pushed(value) => Some(false),
+        _ => None,
+    }
+}
+
+/// Queue `backend` after a push that failed offline, or clear it after one that succeeded
+pub fn record_push_result<T>(
+    app: &tauri::AppHandle,
+    backend: &str,
+    result: &Result<T, SyncError>,
+    pushed: impl FnOnce(&T) -> bool,
+) {
+    let Some(offline) = queue_action(result, pushed) else {
+        return;
+    };
+    let nekotick_dir = match get_nekotick_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("Failed to update the sync queue: {}", e);
+            return;
+        }
+    };
+
+    let updated = if offline {
+        let mut queue = load_sync_queue(&nekotick_dir);
+        queue.enqueue(backend, local_data_hash(&nekotick_dir), chrono::Utc::now().timestamp());
+        save_sync_queue(&nekotick_dir, &queue)
+    } else {
+        forget_backend(&nekotick_dir, backend)
+    };
+    if let Err(e) = updated {
+        log::warn!("Failed to update the sync queue: {}", e);
+    }
+}
+
+/// Clear `backend`'s pending push after a two-way sync that brought both sides in line
+pub fn record_sync_result<T>(
+    app: &tauri::AppHandle,
+    backend: &str,
+    result: &Result<T, SyncError>,
+    synced: impl FnOnce(&T) -> bool,
+) {
+    if !matches!(result, Ok(value) if synced(value)) {
+        return;
+    }
+    if let Err(e) = get_nekotick_dir(app).and_then(|dir| forget_backend(&dir, backend)) {
+        log::warn!("Failed to update the sync queue: {}", e);
+    }
+}
+
+/// Retry every queued push and report which backends went through
+async fn flush(app: &tauri::AppHandle) -> Result<SyncQueueFlush, String> {
+    let nekotick_dir = get_nekotick_dir(app)?;
+    let queue = load_sync_queue(&nekotick_dir);
+
+    let current_hash = local_data_hash(&nekotick_dir);
+    for entry in &queue.pending {
+        if entry.local_hash != current_hash {
+            log::info!("Local data changed since the {} push was queued; pushing the newer copy", entry.backend);
+        }
+        // Each push updates the queue itself through `record_push_result`
+        let outcome = match entry.backend.as_str() {
+            "github" => sync_to_github(app.clone(), app.state::<SyncLocks>(), None).await.map(|_| ()),
+            "webdav" => sync_to_webdav(app.clone(), app.state::<SyncLocks>()).await.map(|_| ()),
+            other => {
+                log::warn!("Dropping queued push for unknown backend {}", other);
+                let mut queue = load_sync_queue(&nekotick_dir);
+                queue.remove(other);
+                save_sync_queue(&nekotick_dir, &queue)?;
+                continue;
+            }
+        };
+        if let Err(e) = outcome {
+            log::info!("Queued {} push not flushed: {}", entry.backend, e);
+        }
+    }
+
+    let remaining = load_sync_queue(&nekotick_dir);
+    let (still_pending, pushed) = queue
+        .pending
+        .into_iter()
+        .map(|entry| entry.backend)
+        .filter(|backend| matches!(backend.as_str(), "github" | "webdav"))
+        .partition(|backend| remaining.contains(backend));
+    Ok(SyncQueueFlush { pushed, still_pending })
+}
+
+/// Retry queued pushes, if there are any, logging instead of returning the outcome
+pub async fn flush_pending(app: &tauri::AppHandle) {
+    let has_pending = get_nekotick_dir(app)
+        .map(|dir| !load_sync_queue(&dir).pending.is_empty())
+        .unwrap_or(false);
+    if !has_pending {
+        return;
+    }
+    match flush(app).await {
+        Ok(result) if !result.pushed.is_empty() => {
+            log::info!("Flushed queued sync pushes: {}", result.pushed.join(", "));
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to flush the sync queue: {}", e),
+    }
+}
+
+/// Flush pushes left over from an earlier offline session in the background
+pub fn start(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { flush_pending(&app).await });
+}
+
+/// Push every change queued while offline
+#[tauri::command]
+pub async fn flush_sync_queue(app: tauri::AppHandle) -> Result<SyncQueueFlush, String> {
+    flush(&app).await
+}
+
+/// Pushes currently waiting for a backend
+#[tauri::command]
+pub async fn get_sync_queue(app: tauri::AppHandle) -> Result<Vec<PendingPushStatus>, String> {
+    let nekotick_dir = get_nekotick_dir(&app)?;
+    let current_hash = local_data_hash(&nekotick_dir);
+    Ok(pending_status(load_sync_queue(&nekotick_dir), current_hash.as_deref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enqueue_keeps_one_entry_per_backend() {
+        let mut queue = SyncQueue::default();
+        queue.enqueue("github", Some("a".to_string()), 1);
+        queue.enqueue("webdav", None, 2);
+        queue.enqueue("github", Some("b".to_string()), 3);
+
+        assert_eq!(queue.pending.len(), 2);
+        let github = queue.pending.iter().find(|e| e.backend == "github").unwrap();
+        assert_eq!(github.local_hash.as_deref(), Some("b"));
+        assert!(queue.remove("github"));
+        assert!(!queue.remove("github"));
+    }
+
+    #[test]
+    fn test_only_network_errors_queue_and_only_pushes_clear() {
+        let offline: Result<bool, SyncError> = Err(SyncError::Network("offline".to_string()));
+        assert_eq!(queue_action(&offline, |pushed| *pushed), Some(true));
+
+        let pushed: Result<bool, SyncError> = Ok(true);
+        assert_eq!(queue_action(&pushed, |pushed| *pushed), Some(false));
+
+        let refused: Result<bool, SyncError> = Ok(false);
+        assert_eq!(queue_action(&refused, |pushed| *pushed), None);
+        let unauthorized: Result<bool, SyncError> = Err(SyncError::Unauthorized);
+        assert_eq!(queue_action(&unauthorized, |pushed| *pushed), None);
+    }
+
+    #[test]
+    fn test_queue_round_trips_with_local_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_sync_queue(dir.path()).pending.is_empty());
+
+        fs::create_dir_all(dir.path().join(STORE_FOLDER)).unwrap();
+        fs::write(dir.path().join(STORE_FOLDER).join(DATA_FILE_NAME), "{}").unwrap();
+        let hash = local_data_hash(dir.path()).unwrap();
+        assert_eq!(hash.len(), 64);
+
+        let mut queue = SyncQueue::default();
+        queue.enqueue("webdav", Some(hash.clone()), 10);
+        save_sync_queue(dir.path(), &queue).unwrap();
+        assert_eq!(load_sync_queue(dir.path()).pending, queue.pending);
+    }
+
+    #[test]
+    fn test_status_flags_local_changes_after_queueing() {
+        let mut queue = SyncQueue::default();
+        queue.enqueue("github", Some("a".to_string()), 1);
+        queue.enqueue("webdav", Some("b".to_string()), 2);
+
+        let status = pending_status(queue, Some("a"));
+        assert!(!status[0].local_changed_since);
+        assert!(status[1].local_changed_since);
+    }
+
+    #[test]
+    fn test_forgetting_last_backend_deletes_queue_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut queue = SyncQueue::default();
+        queue.enqueue("github", None, 1);
+        queue.enqueue("webdav", None, 2);
+        save_sync_queue(dir.path(), &queue).unwrap();
+
+        forget_backend(dir.path(), "github").unwrap();
+        assert!(!load_sync_queue(dir.path()).contains("github"));
+        assert!(queue_path(dir.path()).exists());
+
+        forget_backend(dir.path(), "webdav").unwrap();
+        assert!(!queue_path(dir.path()).exists());
+
+        // Nothing queued is not an error
+        forget_backend(dir.path(), "webdav").unwrap();
+        clear_sync_queue(dir.path()).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+//! Tauri commands for inspecting local NekoTick data
+//!
+//! These commands are exposed to the frontend via Tauri's IPC.
+
+use crate::backup;
+use crate::paths;
+use crate::sync_error::SyncError;
+use crate::tasks::export::{self, ExportOptions};
+use crate::tasks::schema::{self, SchemaCompatibility};
+use crate::tasks::validate::{self, DataProblem};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DATA_FILE_NAME: &str = "data.json";
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+const IMPORT_TEMP_FILE: &str = "data.json.import.tmp";
+
+/// Result of importing a data file
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub tasks_imported: usize,
+    pub groups_imported: usize,
+    /// Backup of the data.json that was replaced, if there was one
+    pub backup_path: Option<String>,
+}
+
+/// Get the local data.json path
+fn get_data_json_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    let base = paths::data_dir(app)?;
+    Ok(base.join(NEKOTICK_FOLDER).join(STORE_FOLDER).join(DATA_FILE_NAME))
+}
+
+/// Check whether data.json (local by default, or the given content) matches
+/// the schema versions this app supports
+#[tauri::command]
+pub async fn check_data_schema_version(
+    app: tauri::AppHandle,
+    content: Option<String>,
+) -> Result<SchemaCompatibility, String> {
+    let content = match content {
+        Some(c) => c,
+        None => {
+            let path = get_data_json_path(&app)?;
+            fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read data.json: {}", e))?
+        }
+    };
+
+    schema::check_schema_version(&content)
+}
+
+/// Validate data.json (local by default, or the given content) against the
+/// NekoTick data structure, listing every problem found
+#[tauri::command]
+pub async fn validate_data_against_schema(
+    app: tauri::AppHandle,
+    content: Option<String>,
+) -> Result<Vec<DataProblem>, String> {
+    let content = match content {
+        Some(c) => c,
+        None => {
+            let path = get_data_json_path(&app)?;
+            fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read data.json: {}", e))?
+        }
+    };
+
+    Ok(validate::validate_data_json(&content))
+}
+
+/// Render local tasks as Markdown for copying or saving
+#[tauri::command]
+pub async fn export_markdown(app: tauri::AppHandle, options: ExportOptions) -> Result<String, String> {
+    let path = get_data_json_path(&app)?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read data.json: {}", e))?;
+    let data: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid data.json: {}", e))?;
+
+    Ok(export::render_markdown(&data, &options))
+}
+
+/// Ensure `content` is a complete NekoTick data file this version can read
+pub fn check_importable(content: &str) -> Result<(), SyncError> {
+    let problems = validate::validate_data_json(content);
+    if let Some(problem) = problems.first() {
+        return Err(SyncError::Other(format!(
+            "Not a NekoTick data file ({} problem(s), first at {}: {})",
+            problems.len(),
+            problem.path,
+            problem.message
+        )));
+    }
+    if schema::check_schema_version(content)?.is_too_new() {
+        return Err(SyncError::SchemaTooNew);
+    }
+    Ok(())
+}
+
+/// Replace `store_dir/data.json` with `content` after validating it
+///
+/// Content that fails `check_importable` is rejected before anything is
+/// touched. The current file is backed up first, and the new one is
+/// written to a temp file and renamed into place.
+pub fn import_data_into(store_dir: &Path, content: &str) -> Result<ImportResult, SyncError> {
+    check_importable(content)?;
+
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let count = |key: &str| value["data"][key].as_array().map_or(0, Vec::len);
+
+    fs::create_dir_all(store_dir).map_err(|e| e.to_string())?;
+    let backup_path = backup::rotate_backup(store_dir, DATA_FILE_NAME, backup::DEFAULT_BACKUP_KEEP)?;
+
+    let temp_path = store_dir.join(IMPORT_TEMP_FILE);
+    fs::write(&temp_path, content).map_err(|e| format!("Failed to write imported data: {}", e))?;
+    fs::rename(&temp_path, store_dir.join(DATA_FILE_NAME))
+        .map_err(|e| format!("Failed to replace data.json: {}", e))?;
+
+    Ok(ImportResult {
+        tasks_imported: count("tasks"),
+        groups_imported: count("groups"),
+        backup_path: backup_path.map(|p| p.display().to_string()),
+    })
+}
+
+/// Import a data.json picked by the user, replacing local data
+#[tauri::command]
+pub async fn import_data_file(app: tauri::AppHandle, source_path: String) -> Result<ImportResult, SyncError> {
+    let content = fs::read_to_string(&source_path)
+        .map_err(|e| format!("Failed to read {}: {}", source_path, e))?;
+    let data_json_path = get_data_json_path(&app)?;
+    let store_dir = data_json_path.parent().ok_or("Invalid data directory")?;
+    import_data_into(store_dir, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMPORTED: &str = r#"{"version":2,"lastModified":1,"data":{
+        "groups":[{"id":"g1","title":"Inbox"}],
+        "tasks":[{"id":"t1","content":"Imported","groupId":"g1","createdAt":1},{"id":"t2","content":"Also","groupId":"g1","createdAt":2}],
+        "progress":[],
+        "archive":[],
+        "settings":{"timezone":8,"viewMode":"week","dayCount":7}
+    }}"#;
+
+    #[test]
+    fn test_import_backs_up_and_replaces() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(DATA_FILE_NAME), "old").unwrap();
+
+        let result = import_data_into(dir.path(), IMPORTED).unwrap();
+        assert_eq!(result.tasks_imported, 2);
+        assert_eq!(result.groups_imported, 1);
+        assert_eq!(fs::read_to_string(result.backup_path.unwrap()).unwrap(), "old");
+        assert_eq!(fs::read_to_string(dir.path().join(DATA_FILE_NAME)).unwrap(), IMPORTED);
+        assert!(!dir.path().join(IMPORT_TEMP_FILE).exists());
+    }
+
+    #[test]
+    fn test_import_rejects_non_nekotick_json() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(DATA_FILE_NAME), "old").unwrap();
+
+        assert!(import_data_into(dir.path(), r#"{"todos":[]}"#).is_err());
+        assert!(import_data_into(dir.path(), "not json").is_err());
+        assert_eq!(fs::read_to_string(dir.path().join(DATA_FILE_NAME)).unwrap(), "old");
+        assert!(backup::list_backup_timestamps(dir.path(), DATA_FILE_NAME).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+//! Semantic diff between two versions of the task list
+//!
+//! Compares tasks by `id` rather than by text, so reordering or reformatting
+//! data.json does not show up as a change. Tombstones count as absent.
+
+use crate::tasks::merge::{is_tombstone, parse_data_file, task_id, tasks_of, MergeError};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// A task that exists on only one side
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskSummary {
+    pub id: String,
+    pub content: Option<String>,
+}
+
+/// A task present on both sides with different fields
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskModification {
+    pub id: String,
+    pub content: Option<String>,
+    pub changed_fields: Vec<String>,
+}
+
+/// Tasks added, removed and modified going from `old` to `new`
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskDiff {
+    pub added: Vec<TaskSummary>,
+    pub removed: Vec<TaskSummary>,
+    pub modified: Vec<TaskModification>,
+}
+
+impl TaskDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+fn content_of(task: &Value) -> Option<String> {
+    task.get("content").and_then(Value::as_str).map(|s| s.to_string())
+}
+
+fn summary(id: &str, task: &Value) -> TaskSummary {
+    TaskSummary {
+        id: id.to_string(),
+        content: content_of(task),
+    }
+}
+
+/// Fields whose values differ, ignoring the `updatedAt` bookkeeping field
+fn changed_fields(old: &Value, new: &Value) -> Vec<String> {
+    let (Some(old), Some(new)) = (old.as_object(), new.as_object()) else {
+        return Vec::new();
+    };
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter(|k| k.as_str() != "updatedAt" && old.get(*k) != new.get(*k))
+        .cloned()
+        .collect()
+}
+
+fn live_tasks_by_id(tasks: &[Value]) -> HashMap<&str, &Value> {
+    tasks
+        .iter()
+        .filter(|t| !is_tombstone(t))
+        .filter_map(|t| task_id(t).map(|id| (id, t)))
+        .collect()
+}
+
+/// Diff two task lists, reporting results in `new` order (removed in `old` order)
+pub fn diff_tasks(old: &[Value], new: &[Value]) -> TaskDiff {
+    let old_by_id = live_tasks_by_id(old);
+    let new_by_id = live_tasks_by_id(new);
+    let mut diff = TaskDiff::default();
+
+    for task in new.iter().filter(|t| !is_tombstone(t)) {
+        let Some(id) = task_id(task) else { continue };
+        match old_by_id.get(id) {
+            None => diff.added.push(summary(id, task)),
+            Some(old_task) => {
+                let fields = changed_fields(old_task, task);
+                if !fields.is_empty() {
+                    diff.modified.push(TaskModification {
+                        id: id.to_string(),
+                        content: content_of(task),
+                        changed_fields: fields,
+                    });
+                }
+            }
+        }
+    }
+
+    for task in old.iter().filter(|t| !is_tombstone(t)) {
+        let Some(id) = task_id(task) else { continue };
+        if !new_by_id.contains_key(id) {
+            diff.removed.push(summary(id, task));
+        }
+    }
+
+    diff
+}
+
+/// Diff the task lists of two data.json documents
+pub fn diff_task_json(old: &str, new: &str) -> Result<TaskDiff, MergeError> {
+    let old_file = parse_data_file(old, "Old")?;
+    let new_file = parse_data_file(new, "New")?;
+    Ok(diff_tasks(&tasks_of(&old_file), &tasks_of(&new_file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_added_removed_and_modified() {
+        let old = vec![
+            json!({ "id": "a", "content": "same", "updatedAt": 1 }),
+            json!({ "id": "b", "content": "gone", "updatedAt": 1 }),
+            json!({ "id": "c", "content": "draft", "completed": false, "updatedAt": 1 }),
+        ];
+        let new = vec![
+            json!({ "id": "a", "content": "same", "updatedAt": 5 }),
+            json!({ "id": "c", "content": "final", "completed": true, "updatedAt": 5 }),
+            json!({ "id": "d", "content": "fresh", "updatedAt": 5 }),
+        ];
+
+        let diff = diff_tasks(&old, &new);
+        assert_eq!(diff.added, vec![TaskSummary { id: "d".into(), content: Some("fresh".into()) }]);
+        assert_eq!(diff.removed, vec![TaskSummary { id: "b".into(), content: Some("gone".into()) }]);
+        assert_eq!(diff.modified.len(), 1);
+        assert_eq!(diff.modified[0].id, "c");
+        assert_eq!(diff.modified[0].changed_fields, vec!["completed", "content"]);
+    }
+
+    #[test]
+    fn test_tombstone_counts_as_removed() {
+        let old = vec![json!({ "id": "a", "content": "task" })];
+        let new = vec![json!({ "id": "a", "deleted": true, "deletedAt": 10 })];
+        let diff = diff_tasks(&old, &new);
+        assert_eq!(diff.removed.len(), 1);
+        assert!(diff.added.is_empty() && diff.modified.is_empty());
+    }
+
+    #[test]
+    fn test_reordering_is_not_a_change() {
+        let old = vec![json!({ "id": "a" }), json!({ "id": "b" })];
+        let new = vec![json!({ "id": "b" }), json!({ "id": "a" })];
+        assert!(diff_tasks(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn test_diff_task_json_rejects_invalid() {
+        assert!(diff_task_json("not json", "{}").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Render data.json tasks as Markdown
+//!
+//! Separate from the synced `nekotick.md`: this is an on-demand export whose
+//! output the frontend copies to the clipboard or saves. Task colors are
+//! written as textual priority tags (`#red`, `#yellow`, ...).
+
+use crate::priority::Priority;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What to include in a Markdown export
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExportOptions {
+    pub include_completed: bool,
+    /// Group tasks by color instead of by task group
+    pub group_by_priority: bool,
+    pub include_timestamps: bool,
+}
+
+/// A task's color, if it is one of the known priority colors
+fn task_priority(task: &Value) -> Option<Priority> {
+    task.get("color")
+        .and_then(Value::as_str)
+        .and_then(|color| color.parse().ok())
+        .filter(|priority| *priority != Priority::Default)
+}
+
+fn format_timestamp(ms: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp_millis(ms).map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+fn render_task(task: &Value, options: &ExportOptions) -> String {
+    let completed = task.get("completed").and_then(Value::as_bool).unwrap_or(false);
+    let content = task.get("content").and_then(Value::as_str).unwrap_or("").replace('\n', " ");
+    let mut line = format!("- [{}] {}", if completed { "x" } else { " " }, content.trim());
+
+    if !options.group_by_priority {
+        if let Some(priority) = task_priority(task) {
+            line.push_str(&format!(" #{}", priority.as_str()));
+        }
+    }
+
+    if options.include_timestamps {
+        let stamps: Vec<String> = [("created", "createdAt"), ("completed", "completedAt")]
+            .iter()
+            .filter_map(|(label, field)| {
+                let ms = task.get(*field).and_then(Value::as_i64)?;
+                Some(format!("{} {}", label, format_timestamp(ms)?))
+            })
+            .collect();
+        if !stamps.is_empty() {
+            line.push_str(&format!(" ({})", stamps.join(", ")));
+        }
+    }
+
+    line
+}
+
+fn push_section(out: &mut String, title: &str, tasks: &[&Value], options: &ExportOptions) {
+    if tasks.is_empty() {
+        return;
+    }
+    out.push_str(&format!("\n## {}\n\n", title));
+    for task in tasks {
+        out.push_str(&render_task(task, options));
+        out.push('\n');
+    }
+}
+
+/// Render the tasks of a parsed data.json as Markdown
+pub fn render_markdown(data: &Value, options: &ExportOptions) -> String {
+    let empty = Vec::new();
+    let tasks: Vec<&Value> = data["data"]["tasks"]
+        .as_array()
+        .unwrap_or(&empty)
+        .iter()
+        .filter(|t| t.get("deleted").and_then(Value::as_bool) != Some(true))
+        .filter(|t| options.include_completed || t.get("completed").and_then(Value::as_bool) != Some(true))
+        .collect();
+
+    let mut out = String::from("# NekoTick Tasks\n");
+
+    if options.group_by_priority {
+        for priority in Priority::COLORS {
+            let section: Vec<&Value> = tasks.iter().copied().filter(|t| task_priority(t) == Some(priority)).collect();
+            push_section(&mut out, priority.as_label(), &section, options);
+        }
+        let section: Vec<&Value> = tasks.iter().copied().filter(|t| task_priority(t).is_none()).collect();
+        push_section(&mut out, Priority::Default.as_label(), &section, options);
+        return out;
+    }
+
+    let groups = data["data"]["groups"].as_array().unwrap_or(&empty);
+    let group_id = |task: &Value| task.get("groupId").and_then(Value::as_str).map(str::to_string);
+    for group in groups {
+        let id = group.get("id").and_then(Value::as_str);
+        let title = group.get("title").and_then(Value::as_str).unwrap_or("Untitled");
+        let section: Vec<&Value> = tasks.iter().copied().filter(|t| group_id(t).as_deref() == id).collect();
+        push_section(&mut out, title, &section, options);
+    }
+
+    let known: Vec<Option<&str>> = groups.iter().map(|g| g.get("id").and_then(Value::as_str)).collect();
+    let section: Vec<&Value> = tasks
+        .iter()
+        .copied()
+        .filter(|t| !known.contains(&group_id(t).as_deref()))
+        .collect();
+    push_section(&mut out, "Ungrouped", &section, options);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data() -> Value {
+        json!({
+            "version": 2,
+            "lastModified": 1,
+            "data": {
+                "groups": [{ "id": "g1", "title": "Inbox" }, { "id": "g2", "title": "Work" }],
+                "tasks": [
+                    { "id": "t1", "content": "Pay rent", "groupId": "g1", "color": "red", "createdAt": 0 },
+                    { "id": "t2", "content": "Write report", "groupId": "g2", "completed": true, "completedAt": 60000 },
+                    { "id": "t3", "content": "Gone", "groupId": "g1", "deleted": true },
+                    { "id": "t4", "content": "Stray", "color": "green" }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_groups_and_priority_tags() {
+        let md = render_markdown(&data(), &ExportOptions::default());
+        assert_eq!(md, "# NekoTick Tasks\n\n## Inbox\n\n- [ ] Pay rent #red\n\n## Ungrouped\n\n- [ ] Stray #green\n");
+    }
+
+    #[test]
+    fn test_completed_and_timestamps() {
+        let options = ExportOptions { include_completed: true, include_timestamps: true, ..Default::default() };
+        let md = render_markdown(&data(), &options);
+        assert!(md.contains("## Work\n\n- [x] Write report (completed 1970-01-01 00:01 UTC)\n"));
+        assert!(md.contains("- [ ] Pay rent #red (created 1970-01-01 00:00 UTC)"));
+        assert!(!md.contains("Gone"));
+    }
+
+    #[test]
+    fn test_group_by_priority() {
+        let options = ExportOptions { group_by_priority: true, include_completed: true, ..Default::default() };
+        let md = render_markdown(&data(), &options);
+        let red = md.find("## Red").unwrap();
+        let green = md.find("## Green").unwrap();
+        let none = md.find("## No priority").unwrap();
+        assert!(red < green && green < none);
+        assert!(md.contains("- [ ] Pay rent\n"));
+        assert!(md.contains("- [x] Write report\n"));
+    }
+}
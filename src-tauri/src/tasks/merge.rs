@@ -0,0 +1,641 @@
+//! Item-level merge of data.json collections
+//!
+//! Every list in `data` whose items carry a stable `id` (tasks, groups,
+//! progress, custom icons) is merged item by item, archive sections by their
+//! `timestamp`, and `settings` key by key. Anything else is taken from the
+//! side with the newer `lastModified`.
+//!
+//! Items are matched by their stable `id`. Deletions are derived from the
+//! last synced payload (the "base"): an item in the base that one side
+//! removed and the other left unchanged is dropped, so the frontend can
+//! simply remove items. Without a base (the first sync) a removed item can't
+//! be told apart from a new one and is kept. Tombstones (`deleted: true`
+//! plus `deletedAt`) are honored too, and dropped after the retention window.
+//!
+//! With a base, live items are also merged field by field, so edits to
+//! different fields on two devices both survive. Text edited differently on
+//! both sides is reported as a conflict instead of silently dropping one
+//! version.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// How long tombstones are kept before being garbage-collected (30 days)
+pub const TOMBSTONE_RETENTION_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+
+/// Error types for merge operations
+#[derive(Debug, thiserror::Error)]
+pub enum MergeError {
+    #[error("Invalid JSON: {0}")]
+    Parse(String),
+    #[error("Unexpected data.json structure: {0}")]
+    Structure(String),
+}
+
+/// A task field edited differently on both sides since the base
+///
+/// The merged output keeps the local value; the remote value is carried here
+/// so the user can pick it instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    pub task_id: String,
+    pub field: String,
+    pub local: Value,
+    pub remote: Value,
+    pub base: Option<Value>,
+}
+
+/// Merged data.json plus any conflicts found while merging
+#[derive(Debug, Clone)]
+pub struct MergeOutcome {
+    pub merged: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// How a bidirectional sync reconciles local and remote data.json
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictStrategy {
+    /// Keep local data and overwrite the remote
+    PreferLocal,
+    /// Keep remote data and overwrite local
+    PreferRemote,
+    /// Keep whichever file has the newer `lastModified` as a whole
+    PreferNewer,
+    /// Merge task by task (see `merge_task_json`)
+    #[default]
+    Merge,
+}
+
+/// Fields whose concurrent edits are conflicts rather than newest-wins
+const CONFLICT_FIELDS: &[&str] = &["content"];
+
+/// `data` lists merged item by item, keyed by `id`
+const ID_COLLECTIONS: &[&str] = &["tasks", "groups", "progress", "customIcons"];
+
+/// `data` list of archive sections, keyed by their `timestamp`
+const ARCHIVE_COLLECTION: &str = "archive";
+
+/// `data` object merged key by key
+const SETTINGS_OBJECT: &str = "settings";
+
+/// Whether a task is a deletion tombstone
+pub fn is_tombstone(task: &Value) -> bool {
+    task.get("deleted").and_then(Value::as_bool).unwrap_or(false)
+}
+
+pub(crate) fn task_id(task: &Value) -> Option<&str> {
+    task.get("id").and_then(Value::as_str)
+}
+
+fn id_key(item: &Value) -> Option<String> {
+    task_id(item).map(str::to_string)
+}
+
+fn archive_key(section: &Value) -> Option<String> {
+    section.get("timestamp").and_then(Value::as_i64).map(|t| t.to_string())
+}
+
+fn timestamp(task: &Value, field: &str) -> Option<i64> {
+    task.get(field).and_then(Value::as_i64)
+}
+
+/// Last time a task was changed, falling back to its creation time
+pub(crate) fn updated_at(task: &Value) -> i64 {
+    timestamp(task, "updatedAt")
+        .or_else(|| timestamp(task, "createdAt"))
+        .unwrap_or(0)
+}
+
+/// When a tombstone was created
+fn deleted_at(task: &Value) -> i64 {
+    timestamp(task, "deletedAt").unwrap_or_else(|| updated_at(task))
+}
+
+/// Pick the surviving version of a task present on both sides
+///
+/// A deletion wins unless the other side edited the task after it was deleted.
+fn resolve_task(local: &Value, remote: &Value) -> Value {
+    match (is_tombstone(local), is_tombstone(remote)) {
+        (true, true) => {
+            if deleted_at(remote) > deleted_at(local) { remote.clone() } else { local.clone() }
+        }
+        (true, false) => {
+            if updated_at(remote) > deleted_at(local) { remote.clone() } else { local.clone() }
+        }
+        (false, true) => {
+            if updated_at(local) > deleted_at(remote) { local.clone() } else { remote.clone() }
+        }
+        (false, false) => {
+            if updated_at(remote) > updated_at(local) { remote.clone() } else { local.clone() }
+        }
+    }
+}
+
+/// Three-way merge of a task present live on both sides with a base version
+fn merge_task_fields(id: &str, local: &Value, remote: &Value, base: &Value, conflicts: &mut Vec<MergeConflict>) -> Value {
+    let (Some(local_obj), Some(remote_obj)) = (local.as_object(), remote.as_object()) else {
+        return resolve_task(local, remote);
+    };
+    let base_obj = base.as_object();
+    let remote_newer = updated_at(remote) > updated_at(local);
+
+    let keys: BTreeSet<&String> = local_obj.keys().chain(remote_obj.keys()).collect();
+    let mut merged = Map::new();
+    for key in keys {
+        let l = local_obj.get(key.as_str());
+        let r = remote_obj.get(key.as_str());
+        let b = base_obj.and_then(|o| o.get(key.as_str()));
+
+        let value = if l == r || r == b {
+            l
+        } else if l == b {
+            r
+        } else if CONFLICT_FIELDS.contains(&key.as_str()) {
+            conflicts.push(MergeConflict {
+                task_id: id.to_string(),
+                field: key.to_string(),
+                local: l.cloned().unwrap_or(Value::Null),
+                remote: r.cloned().unwrap_or(Value::Null),
+                base: b.cloned(),
+            });
+            l
+        } else if remote_newer {
+            r
+        } else {
+            l
+        };
+
+        if let Some(v) = value {
+            merged.insert(key.to_string(), v.clone());
+        }
+    }
+
+    // Items without `updatedAt` (progress, groups) don't get one from `createdAt`
+    let latest = updated_at(local).max(updated_at(remote));
+    if latest > 0 && (local_obj.contains_key("updatedAt") || remote_obj.contains_key("updatedAt")) {
+        merged.insert("updatedAt".to_string(), Value::from(latest));
+    }
+    Value::Object(merged)
+}
+
+/// Merge a task present on both sides, field by field when a live base exists
+fn merge_task(id: &str, local: &Value, remote: &Value, base: Option<&Value>, conflicts: &mut Vec<MergeConflict>) -> Value {
+    match base {
+        Some(base) if !is_tombstone(local) && !is_tombstone(remote) && !is_tombstone(base) => {
+            merge_task_fields(id, local, remote, base, conflicts)
+        }
+        _ => resolve_task(local, remote),
+    }
+}
+
+/// Keep a task that exists on only one side unless the other side removed it
+///
+/// A task unchanged since the base and missing from the other side was
+/// removed there (without a tombstone), so it is dropped.
+fn keep_one_sided(task: &Value, base: Option<&Value>) -> bool {
+    match base {
+        Some(base) => task != base,
+        None => true,
+    }
+}
+
+/// Merge two task lists by id, keeping local order and appending remote-only tasks
+///
+/// `base` is the task list from the last successful sync, if known.
+pub fn merge_task_lists(
+    local: &[Value],
+    remote: &[Value],
+    base: Option<&[Value]>,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<Value> {
+    merge_lists_by(local, remote, base, id_key, conflicts)
+}
+
+/// Merge two lists by the key `key_of` gives each item
+///
+/// Local order is kept and remote-only items are appended. Items without a
+/// key are kept from the local side only.
+fn merge_lists_by(
+    local: &[Value],
+    remote: &[Value],
+    base: Option<&[Value]>,
+    key_of: fn(&Value) -> Option<String>,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Vec<Value> {
+    let by_key = |items: &'_ [Value]| -> HashMap<String, Value> {
+        items
+            .iter()
+            .filter_map(|item| key_of(item).map(|key| (key, item.clone())))
+            .collect()
+    };
+    let remote_by_key = by_key(remote);
+    let base_by_key = base.map(by_key).unwrap_or_default();
+
+    let mut merged = Vec::with_capacity(local.len().max(remote.len()));
+    let mut seen = HashSet::new();
+
+    for item in local {
+        match key_of(item) {
+            Some(key) => {
+                let base_item = base_by_key.get(&key);
+                match remote_by_key.get(&key) {
+                    Some(remote_item) => merged.push(merge_task(&key, item, remote_item, base_item, conflicts)),
+                    None if keep_one_sided(item, base_item) => merged.push(item.clone()),
+                    None => {}
+                }
+                seen.insert(key);
+            }
+            None => merged.push(item.clone()),
+        }
+    }
+
+    for item in remote {
+        if let Some(key) = key_of(item) {
+            if !seen.contains(&key) && keep_one_sided(item, base_by_key.get(&key)) {
+                merged.push(item.clone());
+            }
+        }
+    }
+
+    merged
+}
+
+/// Three-way merge of two objects key by key; keys changed on both sides take the newer side
+fn merge_object(local: &Map<String, Value>, remote: &Map<String, Value>, base: Option<&Map<String, Value>>, remote_newer: bool) -> Map<String, Value> {
+    let keys: BTreeSet<&String> = local.keys().chain(remote.keys()).collect();
+    let mut merged = Map::new();
+    for key in keys {
+        let l = local.get(key.as_str());
+        let r = remote.get(key.as_str());
+        let b = base.and_then(|o| o.get(key.as_str()));
+
+        let value = if l == r || r == b {
+            l
+        } else if l == b || remote_newer {
+            r
+        } else {
+            l
+        };
+        if let Some(v) = value {
+            merged.insert(key.to_string(), v.clone());
+        }
+    }
+    merged
+}
+
+/// Drop tombstones deleted more than `retention_ms` before `now_ms`
+pub fn gc_tombstones(tasks: Vec<Value>, now_ms: i64, retention_ms: i64) -> Vec<Value> {
+    tasks
+        .into_iter()
+        .filter(|t| !is_tombstone(t) || now_ms - deleted_at(t) <= retention_ms)
+        .collect()
+}
+
+pub(crate) fn parse_data_file(content: &str, side: &str) -> Result<Map<String, Value>, MergeError> {
+    match serde_json::from_str::<Value>(content) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err(MergeError::Structure(format!("{} data is not an object", side))),
+        Err(e) => Err(MergeError::Parse(format!("{} data: {}", side, e))),
+    }
+}
+
+pub(crate) fn tasks_of(file: &Map<String, Value>) -> Vec<Value> {
+    list_of(file, "tasks").unwrap_or_default()
+}
+
+fn data_field<'a>(file: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    file.get("data").and_then(|d| d.get(key))
+}
+
+fn list_of(file: &Map<String, Value>, key: &str) -> Option<Vec<Value>> {
+    data_field(file, key).and_then(Value::as_array).cloned()
+}
+
+/// Merge local and remote data.json content
+///
+/// Collections are merged item by item with deletions propagated, three-way
+/// against `base` (the last synced payload) when it is given; anything the
+/// merge doesn't know is taken from whichever side has the newer
+/// `lastModified`.
+pub fn merge_task_json(local: &str, remote: &str, base: Option<&str>, now_ms: i64) -> Result<MergeOutcome, MergeError> {
+    let local_file = parse_data_file(local, "Local")?;
+    let remote_file = parse_data_file(remote, "Remote")?;
+    // An unreadable base only loses three-way precision, so fall back to two-way
+    let base_file = base.and_then(|b| parse_data_file(b, "Base").ok());
+
+    let last_modified = |f: &Map<String, Value>| f.get("lastModified").and_then(Value::as_i64).unwrap_or(0);
+    let local_modified = last_modified(&local_file);
+    let remote_modified = last_modified(&remote_file);
+    let remote_newer = remote_modified > local_modified;
+
+    let mut conflicts = Vec::new();
+    let mut merged_data = Map::new();
+    let keyed_lists = ID_COLLECTIONS
+        .iter()
+        .map(|key| (*key, id_key as fn(&Value) -> Option<String>))
+        .chain([(ARCHIVE_COLLECTION, archive_key as fn(&Value) -> Option<String>)]);
+    for (key, key_of) in keyed_lists {
+        let (local_list, remote_list) = (list_of(&local_file, key), list_of(&remote_file, key));
+        // Tasks are always written; other lists only if either side has them
+        if local_list.is_none() && remote_list.is_none() && key != "tasks" {
+            continue;
+        }
+        let base_list = base_file.as_ref().and_then(|f| list_of(f, key));
+        let items = merge_lists_by(
+            &local_list.unwrap_or_default(),
+            &remote_list.unwrap_or_default(),
+            base_list.as_deref(),
+            key_of,
+            &mut conflicts,
+        );
+        merged_data.insert(key.to_string(), Value::Array(gc_tombstones(items, now_ms, TOMBSTONE_RETENTION_MS)));
+    }
+
+    let object_of = |f: &Map<String, Value>| data_field(f, SETTINGS_OBJECT).and_then(Value::as_object).cloned();
+    if let (Some(local_settings), Some(remote_settings)) = (object_of(&local_file), object_of(&remote_file)) {
+        let base_settings = base_file.as_ref().and_then(object_of);
+        let settings = merge_object(&local_settings, &remote_settings, base_settings.as_ref(), remote_newer);
+        merged_data.insert(SETTINGS_OBJECT.to_string(), Value::Object(settings));
+    }
+
+    let mut merged = if remote_newer { remote_file } else { local_file };
+    match merged.get_mut("data") {
+        Some(Value::Object(data)) => data.extend(merged_data),
+        _ => {
+            merged.insert("data".to_string(), Value::Object(merged_data));
+        }
+    }
+    merged.insert("lastModified".to_string(), Value::from(local_modified.max(remote_modified)));
+
+    let merged = serde_json::to_string_pretty(&Value::Object(merged)).map_err(|e| MergeError::Parse(e.to_string()))?;
+    Ok(MergeOutcome { merged, conflicts })
+}
+
+/// Reconcile local and remote data.json using `strategy`
+///
+/// Only `Merge` can report conflicts; the other strategies take one side
+/// wholesale. Ties under `PreferNewer` keep local data.
+pub fn resolve_task_json(
+    strategy: ConflictStrategy,
+    local: &str,
+    remote: &str,
+    base: Option<&str>,
+    now_ms: i64,
+) -> Result<MergeOutcome, MergeError> {
+    let take = |content: &str| MergeOutcome {
+        merged: content.to_string(),
+        conflicts: Vec::new(),
+    };
+
+    match strategy {
+        ConflictStrategy::PreferLocal => Ok(take(local)),
+        ConflictStrategy::PreferRemote => Ok(take(remote)),
+        ConflictStrategy::PreferNewer => {
+            let last_modified = |f: &Map<String, Value>| f.get("lastModified").and_then(Value::as_i64).unwrap_or(0);
+            let local_modified = last_modified(&parse_data_file(local, "Local")?);
+            let remote_modified = last_modified(&parse_data_file(remote, "Remote")?);
+            Ok(take(if remote_modified > local_modified { remote } else { local }))
+        }
+        ConflictStrategy::Merge => merge_task_json(local, remote, base, now_ms),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: i64 = 1_700_000_000_000;
+
+    fn data_file(tasks: Value, last_modified: i64) -> String {
+        json!({ "version": 2, "lastModified": last_modified, "data": { "tasks": tasks } }).to_string()
+    }
+
+    fn merged_tasks(merged: &str) -> Vec<Value> {
+        let value: Value = serde_json::from_str(merged).unwrap();
+        value["data"]["tasks"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn test_deletion_propagates_and_does_not_resurrect() {
+        let local = data_file(json!([
+            { "id": "a", "content": "keep", "updatedAt": NOW - 5000 },
+            { "id": "b", "deleted": true, "deletedAt": NOW - 1000 }
+        ]), NOW - 1000);
+        let remote = data_file(json!([
+            { "id": "a", "content": "keep", "updatedAt": NOW - 5000 },
+            { "id": "b", "content": "stale copy", "updatedAt": NOW - 4000 }
+        ]), NOW - 4000);
+
+        // Merge from both directions: the tombstone must win either way
+        for merged in [merge_task_json(&local, &remote, None, NOW).unwrap().merged, merge_task_json(&remote, &local, None, NOW).unwrap().merged] {
+            let tasks = merged_tasks(&merged);
+            let b = tasks.iter().find(|t| t["id"] == "b").unwrap();
+            assert!(is_tombstone(b));
+            assert_eq!(tasks.iter().filter(|t| !is_tombstone(t)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_edit_after_delete_wins() {
+        let local = data_file(json!([{ "id": "b", "deleted": true, "deletedAt": NOW - 5000 }]), NOW - 5000);
+        let remote = data_file(json!([{ "id": "b", "content": "edited", "updatedAt": NOW - 1000 }]), NOW - 1000);
+
+        let tasks = merged_tasks(&merge_task_json(&local, &remote, None, NOW).unwrap().merged);
+        assert!(!is_tombstone(&tasks[0]));
+        assert_eq!(tasks[0]["content"], "edited");
+    }
+
+    #[test]
+    fn test_newer_edit_wins_and_remote_only_tasks_kept() {
+        let local = data_file(json!([{ "id": "a", "content": "old", "updatedAt": NOW - 5000 }]), NOW - 5000);
+        let remote = data_file(json!([
+            { "id": "a", "content": "new", "updatedAt": NOW - 1000 },
+            { "id": "c", "content": "from remote", "updatedAt": NOW - 1000 }
+        ]), NOW - 1000);
+
+        let tasks = merged_tasks(&merge_task_json(&local, &remote, None, NOW).unwrap().merged);
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0]["content"], "new");
+        assert_eq!(tasks[1]["id"], "c");
+    }
+
+    #[test]
+    fn test_old_tombstones_are_collected() {
+        let tasks = vec![
+            json!({ "id": "old", "deleted": true, "deletedAt": NOW - TOMBSTONE_RETENTION_MS - 1 }),
+            json!({ "id": "recent", "deleted": true, "deletedAt": NOW - 1000 }),
+            json!({ "id": "live", "createdAt": 0 }),
+        ];
+        let kept: Vec<String> = gc_tombstones(tasks, NOW, TOMBSTONE_RETENTION_MS)
+            .iter()
+            .map(|t| t["id"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(kept, vec!["recent", "live"]);
+    }
+
+    #[test]
+    fn test_three_way_keeps_edits_to_different_fields() {
+        let base = data_file(json!([{ "id": "a", "content": "draft", "completed": false, "updatedAt": NOW - 9000 }]), NOW - 9000);
+        let local = data_file(json!([{ "id": "a", "content": "draft", "completed": true, "updatedAt": NOW - 2000 }]), NOW - 2000);
+        let remote = data_file(json!([{ "id": "a", "content": "final", "completed": false, "updatedAt": NOW - 1000 }]), NOW - 1000);
+
+        let outcome = merge_task_json(&local, &remote, Some(&base), NOW).unwrap();
+        let tasks = merged_tasks(&outcome.merged);
+        assert_eq!(tasks[0]["content"], "final");
+        assert_eq!(tasks[0]["completed"], true);
+        assert_eq!(tasks[0]["updatedAt"], NOW - 1000);
+        assert!(outcome.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_text_edited_on_both_sides_is_conflict() {
+        let base = data_file(json!([{ "id": "a", "content": "draft", "updatedAt": NOW - 9000 }]), NOW - 9000);
+        let local = data_file(json!([{ "id": "a", "content": "local text", "updatedAt": NOW - 2000 }]), NOW - 2000);
+        let remote = data_file(json!([{ "id": "a", "content": "remote text", "updatedAt": NOW - 1000 }]), NOW - 1000);
+
+        let outcome = merge_task_json(&local, &remote, Some(&base), NOW).unwrap();
+        assert_eq!(merged_tasks(&outcome.merged)[0]["content"], "local text");
+        assert_eq!(
+            outcome.conflicts,
+            vec![MergeConflict {
+                task_id: "a".to_string(),
+                field: "content".to_string(),
+                local: json!("local text"),
+                remote: json!("remote text"),
+                base: Some(json!("draft")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_removed_on_one_side_since_base_is_dropped() {
+        let base = data_file(json!([
+            { "id": "a", "content": "removed remotely", "updatedAt": NOW - 9000 },
+            { "id": "b", "content": "edited locally", "updatedAt": NOW - 9000 }
+        ]), NOW - 9000);
+        let local = data_file(json!([
+            { "id": "a", "content": "removed remotely", "updatedAt": NOW - 9000 },
+            { "id": "b", "content": "edited locally!", "updatedAt": NOW - 2000 }
+        ]), NOW - 2000);
+        let remote = data_file(json!([]), NOW - 1000);
+
+        let tasks = merged_tasks(&merge_task_json(&local, &remote, Some(&base), NOW).unwrap().merged);
+        // Unchanged task follows the remote removal; edited task survives it
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0]["id"], "b");
+    }
+
+    #[test]
+    fn test_edits_to_different_progress_and_groups_entries_both_survive() {
+        let file = |progress: Value, groups: Value, settings: Value, last_modified: i64| {
+            json!({
+                "version": 2,
+                "lastModified": last_modified,
+                "data": { "tasks": [], "groups": groups, "progress": progress, "archive": [], "settings": settings }
+            })
+            .to_string()
+        };
+        let base = file(
+            json!([{ "id": "p1", "title": "Read", "current": 1 }, { "id": "p2", "title": "Run", "current": 0 }]),
+            json!([{ "id": "g1", "name": "Inbox" }, { "id": "g2", "name": "Work" }]),
+            json!({ "timezone": 8, "dayCount": 3 }),
+            NOW - 9000,
+        );
+        // Device A bumps p1, renames g1 and changes the day count
+        let local = file(
+            json!([{ "id": "p1", "title": "Read", "current": 2 }, { "id": "p2", "title": "Run", "current": 0 }]),
+            json!([{ "id": "g1", "name": "Home" }, { "id": "g2", "name": "Work" }]),
+            json!({ "timezone": 8, "dayCount": 5 }),
+            NOW - 2000,
+        );
+        // Device B bumps p2, renames g2, adds p3 and changes the timezone
+        let remote = file(
+            json!([{ "id": "p1", "title": "Read", "current": 1 }, { "id": "p2", "title": "Run", "current": 4 }, { "id": "p3", "title": "Swim", "current": 0 }]),
+            json!([{ "id": "g1", "name": "Inbox" }, { "id": "g2", "name": "Office" }]),
+            json!({ "timezone": 9, "dayCount": 3 }),
+            NOW - 1000,
+        );
+
+        let merged: Value = serde_json::from_str(&merge_task_json(&local, &remote, Some(&base), NOW).unwrap().merged).unwrap();
+        let data = &merged["data"];
+        assert_eq!(data["progress"][0], json!({ "id": "p1", "title": "Read", "current": 2 }));
+        assert_eq!(data["progress"][1]["current"], 4);
+        assert_eq!(data["progress"][2]["id"], "p3");
+        assert_eq!(data["groups"][0]["name"], "Home");
+        assert_eq!(data["groups"][1]["name"], "Office");
+        assert_eq!(data["settings"], json!({ "timezone": 9, "dayCount": 5 }));
+    }
+
+    #[test]
+    fn test_archive_sections_merged_by_timestamp() {
+        let file = |archive: Value| json!({ "lastModified": 1, "data": { "tasks": [], "archive": archive } }).to_string();
+        let local = file(json!([{ "timestamp": 1, "tasks": [] }, { "timestamp": 2, "tasks": [{ "content": "a" }] }]));
+        let remote = file(json!([{ "timestamp": 1, "tasks": [] }, { "timestamp": 3, "tasks": [{ "content": "b" }] }]));
+
+        let merged: Value = serde_json::from_str(&merge_task_json(&local, &remote, None, NOW).unwrap().merged).unwrap();
+        let timestamps: Vec<i64> = merged["data"]["archive"].as_array().unwrap().iter().map(|s| s["timestamp"].as_i64().unwrap()).collect();
+        assert_eq!(timestamps, vec![1, 2, 3]);
+        // Lists neither side has are not added
+        assert!(merged["data"].get("progress").is_none());
+    }
+
+    #[test]
+    fn test_plain_removal_propagates_from_either_side() {
+        let task = json!({ "id": "a", "content": "buy milk", "updatedAt": NOW - 9000 });
+        let base = data_file(json!([task]), NOW - 9000);
+        let kept = data_file(json!([task]), NOW - 9000);
+        let removed = data_file(json!([]), NOW - 1000);
+
+        // No tombstone: the task simply disappeared on one device
+        for (local, remote) in [(&kept, &removed), (&removed, &kept)] {
+            let tasks = merged_tasks(&merge_task_json(local, remote, Some(&base), NOW).unwrap().merged);
+            assert!(tasks.is_empty());
+        }
+
+        // Without a base the removal can't be detected and the task is kept
+        assert_eq!(merged_tasks(&merge_task_json(&kept, &removed, None, NOW).unwrap().merged).len(), 1);
+    }
+
+    #[test]
+    fn test_unreadable_base_falls_back_to_two_way() {
+        let local = data_file(json!([{ "id": "a", "content": "old", "updatedAt": NOW - 5000 }]), NOW - 5000);
+        let remote = data_file(json!([{ "id": "a", "content": "new", "updatedAt": NOW - 1000 }]), NOW - 1000);
+
+        let outcome = merge_task_json(&local, &remote, Some("garbage"), NOW).unwrap();
+        assert_eq!(merged_tasks(&outcome.merged)[0]["content"], "new");
+        assert!(outcome.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_strategies_pick_a_side() {
+        let local = data_file(json!([{ "id": "a", "content": "local", "lastModified": 10 }]), 10);
+        let remote = data_file(json!([{ "id": "b", "content": "remote", "lastModified": 20 }]), 20);
+
+        let resolve = |strategy| resolve_task_json(strategy, &local, &remote, None, NOW).unwrap().merged;
+        assert_eq!(resolve(ConflictStrategy::PreferLocal), local);
+        assert_eq!(resolve(ConflictStrategy::PreferRemote), remote);
+        assert_eq!(resolve(ConflictStrategy::PreferNewer), remote);
+        assert_eq!(merged_tasks(&resolve(ConflictStrategy::Merge)).len(), 2);
+
+        // Ties keep the local side
+        let same_age = data_file(json!([]), 10);
+        assert_eq!(resolve_task_json(ConflictStrategy::PreferNewer, &local, &same_age, None, NOW).unwrap().merged, local);
+    }
+
+    #[test]
+    fn test_strategy_serialization() {
+        assert_eq!(serde_json::to_value(ConflictStrategy::PreferNewer).unwrap(), json!("preferNewer"));
+        assert_eq!(serde_json::from_value::<ConflictStrategy>(json!("preferLocal")).unwrap(), ConflictStrategy::PreferLocal);
+        assert_eq!(ConflictStrategy::default(), ConflictStrategy::Merge);
+    }
+
+    #[test]
+    fn test_invalid_input_is_error() {
+        assert!(matches!(merge_task_json("nope", "{}", None, NOW), Err(MergeError::Parse(_))));
+        assert!(matches!(merge_task_json("[]", "{}", None, NOW), Err(MergeError::Structure(_))));
+    }
+}
@@ -0,0 +1,22 @@
+//! NekoTick data.json helpers
+//!
+//! This module understands the structure of `.nekotick/store/data.json`
+//! (`{ version, lastModified, data: { tasks, groups, ... } }`) so sync and
+//! import paths can validate content before it replaces local data.
+
+pub mod schema;
+pub mod merge;
+pub mod diff;
+pub mod validate;
+pub mod export;
+pub mod summary;
+pub mod commands;
+
+// Re-export commonly used types
+pub use schema::SchemaCompatibility;
+pub use merge::{merge_task_json, resolve_task_json, ConflictStrategy, MergeConflict, MergeError, MergeOutcome};
+pub use diff::{diff_task_json, diff_tasks, TaskDiff};
+pub use validate::{validate_data_json, DataProblem};
+pub use export::{render_markdown, ExportOptions};
+pub use summary::{summarize_data_json, DataSummary};
+pub use commands::*;
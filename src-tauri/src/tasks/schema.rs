@@ -0,0 +1,95 @@
+//! Schema version checks for data.json
+//!
+//! The frontend writes a top-level `version` field; newer files may carry
+//! `schemaVersion` instead. Both are accepted, with `schemaVersion` preferred.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Schema version written by this app
+pub const CURRENT_SCHEMA_VERSION: u64 = 2;
+/// Oldest schema version the app reads; there are no migrations from older ones
+pub const MIN_SUPPORTED_SCHEMA_VERSION: u64 = 2;
+
+/// Result of comparing a data file's schema version with this app
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum SchemaCompatibility {
+    Compatible { version: u64 },
+    TooNew { version: u64 },
+    TooOld { version: u64 },
+}
+
+impl SchemaCompatibility {
+    /// Whether the data was written by a newer app version
+    pub fn is_too_new(&self) -> bool {
+        matches!(self, SchemaCompatibility::TooNew { .. })
+    }
+}
+
+/// Read the schema version of a parsed data file (0 when absent)
+pub fn read_schema_version(value: &Value) -> u64 {
+    value
+        .get("schemaVersion")
+        .or_else(|| value.get("version"))
+        .and_then(Value::as_u64)
+        .unwrap_or(0)
+}
+
+/// Classify a schema version against the supported range
+pub fn classify_version(version: u64) -> SchemaCompatibility {
+    if version > CURRENT_SCHEMA_VERSION {
+        SchemaCompatibility::TooNew { version }
+    } else if version < MIN_SUPPORTED_SCHEMA_VERSION {
+        SchemaCompatibility::TooOld { version }
+    } else {
+        SchemaCompatibility::Compatible { version }
+    }
+}
+
+/// Check the schema compatibility of raw data.json content
+pub fn check_schema_version(content: &str) -> Result<SchemaCompatibility, String> {
+    let value: Value = serde_json::from_str(content)
+        .map_err(|e| format!("Invalid data.json: {}", e))?;
+    Ok(classify_version(read_schema_version(&value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_version_is_compatible() {
+        let result = check_schema_version(r#"{"version":2,"data":{}}"#).unwrap();
+        assert_eq!(result, SchemaCompatibility::Compatible { version: 2 });
+    }
+
+    #[test]
+    fn test_newer_version_is_too_new() {
+        let result = check_schema_version(r#"{"schemaVersion":3,"data":{}}"#).unwrap();
+        assert!(result.is_too_new());
+    }
+
+    #[test]
+    fn test_schema_version_preferred_over_version() {
+        let result = check_schema_version(r#"{"schemaVersion":2,"version":9}"#).unwrap();
+        assert_eq!(result, SchemaCompatibility::Compatible { version: 2 });
+    }
+
+    #[test]
+    fn test_older_version_is_too_old() {
+        let result = check_schema_version(r#"{"version":1}"#).unwrap();
+        assert_eq!(result, SchemaCompatibility::TooOld { version: 1 });
+    }
+
+    #[test]
+    fn test_missing_version_is_too_old() {
+        let result = check_schema_version(r#"{"data":{}}"#).unwrap();
+        assert_eq!(result, SchemaCompatibility::TooOld { version: 0 });
+    }
+
+    #[test]
+    fn test_invalid_json_is_error() {
+        assert!(check_schema_version("not json").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Structural validation of data.json
+//!
+//! A small hand-written validator mirroring the frontend's `DataFile` type in
+//! `src/lib/storage/unifiedStorage.ts`. Unlike the schema version check it
+//! reports every problem it finds, each with a JSON path, so corrupt imports
+//! can be diagnosed precisely.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// A single problem found in data.json
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataProblem {
+    /// JSON path of the offending value, e.g. `data.tasks[3].id`
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    Number,
+    Bool,
+    Array,
+    Object,
+}
+
+impl Kind {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            Kind::String => value.is_string(),
+            Kind::Number => value.is_number(),
+            Kind::Bool => value.is_boolean(),
+            Kind::Array => value.is_array(),
+            Kind::Object => value.is_object(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::String => "a string",
+            Kind::Number => "a number",
+            Kind::Bool => "a boolean",
+            Kind::Array => "an array",
+            Kind::Object => "an object",
+        }
+    }
+}
+
+struct Validator {
+    problems: Vec<DataProblem>,
+}
+
+impl Validator {
+    fn report(&mut self, path: &str, message: impl Into<String>) {
+        self.problems.push(DataProblem {
+            path: path.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// Check a required field's type, returning it when valid
+    fn required<'a>(&mut self, obj: &'a Map<String, Value>, path: &str, field: &str, kind: Kind) -> Option<&'a Value> {
+        let field_path = format!("{}.{}", path, field);
+        match obj.get(field) {
+            None => {
+                self.report(&field_path, "missing required field");
+                None
+            }
+            Some(value) => self.typed(value, &field_path, kind),
+        }
+    }
+
+    /// Check an optional field's type when present (`null` counts as absent)
+    fn optional<'a>(&mut self, obj: &'a Map<String, Value>, path: &str, field: &str, kind: Kind) -> Option<&'a Value> {
+        match obj.get(field) {
+            None | Some(Value::Null) => None,
+            Some(value) => self.typed(value, &format!("{}.{}", path, field), kind),
+        }
+    }
+
+    fn typed<'a>(&mut self, value: &'a Value, path: &str, kind: Kind) -> Option<&'a Value> {
+        if kind.matches(value) {
+            Some(value)
+        } else {
+            self.report(path, format!("expected {}", kind.name()));
+            None
+        }
+    }
+
+    /// Validate an array of objects, each checked by `check`, with unique `id`s
+    fn id_list(&mut self, data: &Map<String, Value>, field: &str, required: bool, check: fn(&mut Self, &Map<String, Value>, &str)) {
+        let list = if required {
+            self.required(data, "data", field, Kind::Array)
+        } else {
+            self.optional(data, "data", field, Kind::Array)
+        };
+        let Some(items) = list.and_then(Value::as_array) else { return };
+
+        let mut seen = HashSet::new();
+        for (i, item) in items.iter().enumerate() {
+            let path = format!("data.{}[{}]", field, i);
+            let Some(obj) = self.typed(item, &path, Kind::Object).and_then(Value::as_object) else { continue };
+            if let Some(id) = self.required(obj, &path, "id", Kind::String).and_then(Value::as_str) {
+                if !seen.insert(id) {
+                    self.report(&format!("{}.id", path), format!("duplicate id \"{}\"", id));
+                }
+            }
+            check(self, obj, &path);
+        }
+    }
+
+    fn task(&mut self, task: &Map<String, Value>, path: &str) {
+        self.optional(task, path, "deleted", Kind::Bool);
+        if task.get("deleted").and_then(Value::as_bool) != Some(true) {
+            self.optional(task, path, "content", Kind::String);
+        }
+        for field in ["createdAt", "updatedAt", "deletedAt", "completedAt"] {
+            self.optional(task, path, field, Kind::Number);
+        }
+        self.optional(task, path, "groupId", Kind::String);
+        self.optional(task, path, "completed", Kind::Bool);
+    }
+
+    fn group(&mut self, group: &Map<String, Value>, path: &str) {
+        self.optional(group, path, "title", Kind::String);
+    }
+
+    fn progress(&mut self, progress: &Map<String, Value>, path: &str) {
+        if let Some(kind) = self.required(progress, path, "type", Kind::String).and_then(Value::as_str) {
+            if kind != "progress" && kind != "counter" {
+                self.report(&format!("{}.type", path), format!("unknown progress type \"{}\"", kind));
+            }
+        }
+        self.required(progress, path, "title", Kind::String);
+        self.required(progress, path, "unit", Kind::String);
+        for field in ["step", "current", "todayCount", "createdAt"] {
+            self.required(progress, path, field, Kind::Number);
+        }
+        self.optional(progress, path, "total", Kind::Number);
+        self.optional(progress, path, "history", Kind::Object);
+    }
+
+    fn custom_icon(&mut self, icon: &Map<String, Value>, path: &str) {
+        self.required(icon, path, "url", Kind::String);
+        self.required(icon, path, "name", Kind::String);
+        self.required(icon, path, "createdAt", Kind::Number);
+    }
+
+    fn archive(&mut self, data: &Map<String, Value>) {
+        let Some(sections) = self.required(data, "data", "archive", Kind::Array).and_then(Value::as_array) else { return };
+        for (i, section) in sections.iter().enumerate() {
+            let path = format!("data.archive[{}]", i);
+            let Some(section) = self.typed(section, &path, Kind::Object).and_then(Value::as_object) else { continue };
+            self.required(section, &path, "timestamp", Kind::Number);
+            let Some(entries) = self.required(section, &path, "tasks", Kind::Array).and_then(Value::as_array) else { continue };
+            for (j, entry) in entries.iter().enumerate() {
+                let entry_path = format!("{}.tasks[{}]", path, j);
+                let Some(entry) = self.typed(entry, &entry_path, Kind::Object).and_then(Value::as_object) else { continue };
+                self.required(entry, &entry_path, "content", Kind::String);
+                self.required(entry, &entry_path, "groupId", Kind::String);
+            }
+        }
+    }
+
+    fn settings(&mut self, data: &Map<String, Value>) {
+        let Some(settings) = self.required(data, "data", "settings", Kind::Object).and_then(Value::as_object) else { return };
+        self.required(settings, "data.settings", "timezone", Kind::Number);
+        self.required(settings, "data.settings", "viewMode", Kind::String);
+        self.required(settings, "data.settings", "dayCount", Kind::Number);
+    }
+}
+
+/// Validate a parsed data.json, returning every problem found
+pub fn validate_data(value: &Value) -> Vec<DataProblem> {
+    let mut v = Validator { problems: Vec::new() };
+
+    let Some(root) = v.typed(value, "$", Kind::Object).and_then(Value::as_object) else {
+        return v.problems;
+    };
+    v.required(root, "$", "version", Kind::Number);
+    v.required(root, "$", "lastModified", Kind::Number);
+
+    if let Some(data) = v.required(root, "$", "data", Kind::Object).and_then(Value::as_object) {
+        v.id_list(data, "tasks", true, Validator::task);
+        v.id_list(data, "groups", true, Validator::group);
+        v.id_list(data, "progress", true, Validator::progress);
+        v.id_list(data, "customIcons", false, Validator::custom_icon);
+        v.archive(data);
+        v.settings(data);
+    }
+
+    v.problems
+}
+
+/// Validate raw data.json content; unparseable JSON is reported as a single problem
+pub fn validate_data_json(content: &str) -> Vec<DataProblem> {
+    match serde_json::from_str::<Value>(content) {
+        Ok(value) => validate_data(&value),
+        Err(e) => vec![DataProblem {
+            path: "$".to_string(),
+            message: format!("invalid JSON: {}", e),
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn valid_file() -> Value {
+        json!({
+            "version": 2,
+            "lastModified": 1_700_000_000_000i64,
+            "data": {
+                "groups": [{ "id": "g1", "title": "Inbox" }],
+                "tasks": [
+                    { "id": "t1", "content": "Write report", "groupId": "g1", "createdAt": 1 },
+                    { "id": "t2", "deleted": true, "deletedAt": 5 }
+                ],
+                "progress": [{
+                    "id": "p1", "type": "counter", "title": "Water", "step": 1,
+                    "unit": "cups", "current": 3, "todayCount": 3, "createdAt": 1
+                }],
+                "archive": [{ "timestamp": 1, "tasks": [{ "content": "Done", "groupId": "g1" }] }],
+                "settings": { "timezone": 8, "viewMode": "week", "dayCount": 7 },
+                "customIcons": []
+            }
+        })
+    }
+
+    fn paths(problems: &[DataProblem]) -> Vec<&str> {
+        problems.iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_file_has_no_problems() {
+        assert!(validate_data(&valid_file()).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let problems = validate_data_json("{ not json");
+        assert_eq!(paths(&problems), vec!["$"]);
+        assert!(problems[0].message.starts_with("invalid JSON"));
+    }
+
+    #[test]
+    fn test_missing_top_level_fields() {
+        let problems = validate_data(&json!({ "version": 2 }));
+        assert_eq!(paths(&problems), vec!["$.lastModified", "$.data"]);
+    }
+
+    #[test]
+    fn test_task_problems() {
+        let mut file = valid_file();
+        file["data"]["tasks"] = json!([
+            { "content": "no id" },
+            { "id": 7, "content": "numeric id" },
+            { "id": "t1", "content": 42, "completed": "yes" },
+            { "id": "t1", "content": "duplicate" },
+            "not an object"
+        ]);
+        let problems = validate_data(&file);
+        assert_eq!(
+            problems,
+            vec![
+                DataProblem { path: "data.tasks[0].id".into(), message: "missing required field".into() },
+                DataProblem { path: "data.tasks[1].id".into(), message: "expected a string".into() },
+                DataProblem { path: "data.tasks[2].content".into(), message: "expected a string".into() },
+                DataProblem { path: "data.tasks[2].completed".into(), message: "expected a boolean".into() },
+                DataProblem { path: "data.tasks[3].id".into(), message: "duplicate id \"t1\"".into() },
+                DataProblem { path: "data.tasks[4]".into(), message: "expected an object".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_progress_archive_and_settings_problems() {
+        let mut file = valid_file();
+        file["data"]["progress"][0]["type"] = json!("timer");
+        file["data"]["progress"][0].as_object_mut().unwrap().remove("unit");
+        file["data"]["archive"][0]["tasks"][0] = json!({ "content": "Done" });
+        file["data"]["settings"]["dayCount"] = json!("7");
+        let problems = validate_data(&file);
+        assert_eq!(
+            paths(&problems),
+            vec![
+                "data.progress[0].type",
+                "data.progress[0].unit",
+                "data.archive[0].tasks[0].groupId",
+                "data.settings.dayCount",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrong_container_types() {
+        let mut file = valid_file();
+        file["data"]["tasks"] = json!({});
+        file["data"]["groups"] = Value::Null;
+        let problems = validate_data(&file);
+        assert_eq!(paths(&problems), vec!["data.tasks", "data.groups"]);
+    }
+}
@@ -0,0 +1,309 @@
+//! Minimal WebDAV client
+//!
+//! Supports the PUT/GET/DELETE/PROPFIND/MKCOL calls needed to keep single files in
+//! one collection, authenticating with HTTP basic auth.
+
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+/// PROPFIND body asking only for the modification time
+const PROPFIND_LAST_MODIFIED: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getlastmodified/></d:prop></d:propfind>"#;
+
+/// Error types for WebDAV operations
+#[derive(Debug, thiserror::Error)]
+pub enum WebDavError {
+    #[error("Network error: {0}")]
+    NetworkError(String),
+    #[error("Server error: {0}")]
+    ApiError(String),
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("Invalid WebDAV URL: {0}")]
+    InvalidUrl(String),
+    #[error("Remote data changed on another device since it was last synced")]
+    RemoteChanged,
+}
+
+/// What a conditional upload expects to find on the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpectedRemote {
+    /// The file doesn't exist yet
+    Missing,
+    /// The file exists with this modification time (Unix seconds)
+    ModifiedAt(i64),
+    /// The file exists but the server reports no modification time, so
+    /// there is nothing to check against
+    Untracked,
+}
+
+impl ExpectedRemote {
+    /// Expectation for a file last seen as `exists` with `modified`
+    pub fn from_last_seen(exists: bool, modified: Option<i64>) -> Self {
+        match (exists, modified) {
+            (false, _) => ExpectedRemote::Missing,
+            (true, Some(modified)) => ExpectedRemote::ModifiedAt(modified),
+            (true, None) => ExpectedRemote::Untracked,
+        }
+    }
+}
+
+/// WebDAV client rooted at one collection
+pub struct WebDavClient {
+    base_url: Url,
+    username: String,
+    password: String,
+    client: reqwest::Client,
+}
+
+/// Parse `url` as a collection URL (always ending in `/`)
+pub fn collection_url(url: &str) -> Result<Url, WebDavError> {
+    let mut base = Url::parse(url.trim()).map_err(|e| WebDavError::InvalidUrl(e.to_string()))?;
+    if !matches!(base.scheme(), "http" | "https") {
+        return Err(WebDavError::InvalidUrl(format!("unsupported scheme {}", base.scheme())));
+    }
+    if !base.path().ends_with('/') {
+        let path = format!("{}/", base.path());
+        base.set_path(&path);
+    }
+    Ok(base)
+}
+
+/// Extract `getlastmodified` from a PROPFIND response as Unix seconds
+///
+/// Servers differ in namespace prefixes (`d:`, `D:`, `lp1:`), so the tag is
+/// matched by local name rather than with a full XML parser.
+pub fn parse_last_modified(xml: &str) -> Option<i64> {
+    let lower = xml.to_ascii_lowercase();
+    let tag = lower.find("getlastmodified")?;
+    let start = tag + lower[tag..].find('>')? + 1;
+    let end = start + lower[start..].find('<')?;
+    chrono::DateTime::parse_from_rfc2822(xml[start..end].trim())
+        .ok()
+        .map(|dt| dt.timestamp())
+}
+
+impl WebDavClient {
+    /// Create a client for the collection at `base_url`
+    pub fn new(base_url: &str, username: String, password: String) -> Result<Self, WebDavError> {
+        Ok(Self {
+            base_url: collection_url(base_url)?,
+            username,
+            password,
+            client: crate::http::build_http_client(),
+        })
+    }
+
+    fn file_url(&self, name: &str) -> Result<Url, WebDavError> {
+        self.base_url.join(name).map_err(|e| WebDavError::InvalidUrl(e.to_string()))
+    }
+
+    fn request(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        self.client
+            .request(method, url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("User-Agent", "NekoTick")
+    }
+
+    async fn propfind(&self, url: Url) -> Result<Option<String>, WebDavError> {
+        let method = Method::from_bytes(b"PROPFIND").expect("PROPFIND is a valid method");
+        let response = self
+            .request(method, url)
+            .header("Depth", "0")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(PROPFIND_LAST_MODIFIED)
+            .send()
+            .await
+            .map_err(|e| WebDavError::NetworkError(e.to_string()))?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(WebDavError::Unauthorized),
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .text()
+                .await
+                .map(Some)
+                .map_err(|e| WebDavError::NetworkError(e.to_string())),
+            _ => Err(WebDavError::ApiError(response.text().await.unwrap_or_default())),
+        }
+    }
+
+    /// Make sure the base collection exists, creating it if needed
+    ///
+    /// Doubles as a credentials check when connecting.
+    pub async fn ensure_collection(&self) -> Result<(), WebDavError> {
+        if self.propfind(self.base_url.clone()).await?.is_some() {
+            return Ok(());
+        }
+
+        let method = Method::from_bytes(b"MKCOL").expect("MKCOL is a valid method");
+        let response = self
+            .request(method, self.base_url.clone())
+            .send()
+            .await
+            .map_err(|e| WebDavError::NetworkError(e.to_string()))?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(WebDavError::Unauthorized),
+            status if status.is_success() => Ok(()),
+            _ => Err(WebDavError::ApiError(response.text().await.unwrap_or_default())),
+        }
+    }
+
+    /// Modification time of `name` (Unix seconds), or `None` if it doesn't exist
+    pub async fn file_modified_time(&self, name: &str) -> Result<Option<i64>, WebDavError> {
+        let body = self.propfind(self.file_url(name)?).await?;
+        Ok(body.as_deref().and_then(parse_last_modified))
+    }
+
+    /// Current state of `name`, as an expectation for `put_file_if_unmodified`
+    pub async fn current_state(&self, name: &str) -> Result<ExpectedRemote, WebDavError> {
+        Ok(match self.propfind(self.file_url(name)?).await? {
+            Some(body) => ExpectedRemote::from_last_seen(true, parse_last_modified(&body)),
+            None => ExpectedRemote::Missing,
+        })
+    }
+
+    /// Whether `name` exists in the collection
+    pub async fn file_exists(&self, name: &str) -> Result<bool, WebDavError> {
+        Ok(self.propfind(self.file_url(name)?).await?.is_some())
+    }
+
+    /// Download `name`, or `None` if it doesn't exist
+    pub async fn get_file(&self, name: &str) -> Result<Option<String>, WebDavError> {
+        let response = self
+            .request(Method::GET, self.file_url(name)?)
+            .send()
+            .await
+            .map_err(|e| WebDavError::NetworkError(e.to_string()))?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(WebDavError::Unauthorized),
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .text()
+                .await
+                .map(Some)
+                .map_err(|e| WebDavError::NetworkError(e.to_string())),
+            _ => Err(WebDavError::ApiError(response.text().await.unwrap_or_default())),
+        }
+    }
+
+    /// Upload `content` as `name`, replacing any existing file
+    pub async fn put_file(&self, name: &str, content: &str) -> Result<(), WebDavError> {
+        self.send_put(self.request(Method::PUT, self.file_url(name)?), content).await
+    }
+
+    /// Upload `content` as `name` only if the file is still as `expected`
+    ///
+    /// The file is checked first and the expectation is then sent as
+    /// `If-Unmodified-Since` (or `If-None-Match: *` for a new file), so
+    /// servers that honour preconditions also catch a push that lands in
+    /// between. Fails with `RemoteChanged` either way.
+    pub async fn put_file_if_unmodified(
+        &self,
+        name: &str,
+        content: &str,
+        expected: ExpectedRemote,
+    ) -> Result<(), WebDavError> {
+        let url = self.file_url(name)?;
+        let request = self.request(Method::PUT, url.clone());
+        let request = match expected {
+            ExpectedRemote::Missing => {
+                if self.propfind(url).await?.is_some() {
+                    return Err(WebDavError::RemoteChanged);
+                }
+                request.header("If-None-Match", "*")
+            }
+            ExpectedRemote::ModifiedAt(modified) => {
+                let current = self.propfind(url).await?.as_deref().and_then(parse_last_modified);
+                if current != Some(modified) {
+                    return Err(WebDavError::RemoteChanged);
+                }
+                match chrono::DateTime::from_timestamp(modified, 0) {
+                    Some(at) => request.header("If-Unmodified-Since", at.format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+                    None => request,
+                }
+            }
+            ExpectedRemote::Untracked => request,
+        };
+        self.send_put(request, content).await
+    }
+
+    async fn send_put(&self, request: reqwest::RequestBuilder, content: &str) -> Result<(), WebDavError> {
+        let response = request
+            .header("Content-Type", "application/json; charset=utf-8")
+            .body(content.to_string())
+            .send()
+            .await
+            .map_err(|e| WebDavError::NetworkError(e.to_string()))?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(WebDavError::Unauthorized),
+            StatusCode::PRECONDITION_FAILED => Err(WebDavError::RemoteChanged),
+            status if status.is_success() => Ok(()),
+            _ => Err(WebDavError::ApiError(response.text().await.unwrap_or_default())),
+        }
+    }
+
+    /// Delete `name`; a file that doesn't exist counts as deleted
+    pub async fn delete_file(&self, name: &str) -> Result<(), WebDavError> {
+        let response = self
+            .request(Method::DELETE, self.file_url(name)?)
+            .send()
+            .await
+            .map_err(|e| WebDavError::NetworkError(e.to_string()))?;
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED => Err(WebDavError::Unauthorized),
+            StatusCode::NOT_FOUND => Ok(()),
+            status if status.is_success() => Ok(()),
+            _ => Err(WebDavError::ApiError(response.text().await.unwrap_or_default())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_url_gets_trailing_slash() {
+        let url = collection_url("https://cloud.example.com/remote.php/dav/files/neko/NekoTick").unwrap();
+        assert_eq!(url.as_str(), "https://cloud.example.com/remote.php/dav/files/neko/NekoTick/");
+        assert_eq!(url.join("data.json").unwrap().path(), "/remote.php/dav/files/neko/NekoTick/data.json");
+    }
+
+    #[test]
+    fn test_expected_remote_from_last_seen() {
+        assert_eq!(ExpectedRemote::from_last_seen(false, Some(100)), ExpectedRemote::Missing);
+        assert_eq!(ExpectedRemote::from_last_seen(true, Some(100)), ExpectedRemote::ModifiedAt(100));
+        assert_eq!(ExpectedRemote::from_last_seen(true, None), ExpectedRemote::Untracked);
+    }
+
+    #[test]
+    fn test_collection_url_rejects_other_schemes() {
+        assert!(collection_url("ftp://example.com/dav").is_err());
+        assert!(collection_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_parse_last_modified_any_prefix() {
+        let nextcloud = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:"><d:response><d:href>/dav/data.json</d:href><d:propstat>
+<d:prop><d:getlastmodified>Tue, 05 Mar 2024 10:15:00 GMT</d:getlastmodified></d:prop>
+<d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response></d:multistatus>"#;
+        assert_eq!(parse_last_modified(nextcloud), Some(1_709_633_700));
+
+        let apache = r#"<D:multistatus xmlns:D="DAV:"><D:response><D:propstat><D:prop>
+<lp1:getlastmodified xmlns:lp1="DAV:">Tue, 05 Mar 2024 10:15:00 GMT</lp1:getlastmodified>
+</D:prop></D:propstat></D:response></D:multistatus>"#;
+        assert_eq!(parse_last_modified(apache), Some(1_709_633_700));
+    }
+
+    #[test]
+    fn test_parse_last_modified_missing() {
+        assert_eq!(parse_last_modified("<d:multistatus/>"), None);
+        assert_eq!(parse_last_modified("<d:getlastmodified>yesterday</d:getlastmodified>"), None);
+    }
+}
@@ -0,0 +1,776 @@
+//! Tauri commands for WebDAV sync
+//!
+//! Mirrors the GitHub gist commands: data.json is kept as a single file in
+//! the configured collection, and the server's `getlastmodified` stands in
+//! for the gist's `updated_at` when deciding whether the remote changed.
+
+use crate::backup;
+use crate::diagnostics::{self, BackendDiagnostics};
+use crate::github::commands::ProviderDisconnectReport;
+use crate::github::credential_store;
+use crate::paths;
+use crate::redact::redact_error;
+use crate::sync_crypto;
+use crate::sync_error::{LastSyncOutcome, SyncError};
+use crate::sync_lock::{write_then_push, PushOutcome, SyncBackend, SyncLocks};
+use crate::sync_queue;
+use crate::tasks::{self, schema, ConflictStrategy};
+use crate::webdav::client::{ExpectedRemote, WebDavClient, WebDavError};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio_util::sync::CancellationToken;
+
+const DATA_FILE_NAME: &str = "data.json";
+const NEKOTICK_FOLDER: &str = ".nekotick";
+const STORE_FOLDER: &str = "store";
+
+const WEBDAV_CREDS_FILE: &str = "webdav_credentials.enc";
+const WEBDAV_SYNC_META_FILE: &str = "webdav_sync_meta.json";
+/// Kept apart from the gist sync base so the two backends merge independently
+const WEBDAV_SYNC_BASE_FILE: &str = "webdav_sync_base.json";
+
+/// WebDAV sync status returned to frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavSyncStatus {
+    pub connected: bool,
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub last_sync_time: Option<i64>,
+    pub has_remote_data: bool,
+    pub remote_modified_time: Option<i64>,
+    /// False when the most recent sync attempt failed
+    pub last_sync_success: bool,
+    pub last_sync_error: Option<String>,
+    /// `SyncError` code of the most recent failure
+    pub last_sync_error_code: Option<String>,
+}
+
+/// WebDAV connect result returned to frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavAuthResult {
+    pub success: bool,
+    pub username: Option<String>,
+    pub error: Option<String>,
+}
+
+/// WebDAV sync result returned to frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavSyncResult {
+    pub success: bool,
+    pub timestamp: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// WebDAV bidirectional sync result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavBidirectionalSyncResult {
+    pub success: bool,
+    pub timestamp: Option<i64>,
+    pub pulled_from_cloud: bool,
+    pub pushed_to_cloud: bool,
+    /// Task text edited on both devices; the local version was kept
+    pub conflicts: Vec<tasks::MergeConflict>,
+    /// data.json changed on another device mid-sync; nothing was pushed
+    #[serde(default)]
+    pub remote_changed: bool,
+    pub error: Option<String>,
+}
+
+/// Stored WebDAV connection (encrypted at rest)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WebDavCredentials {
+    url: String,
+    username: String,
+    password: String,
+}
+
+impl WebDavCredentials {
+    fn client(&self) -> Result<WebDavClient, String> {
+        WebDavClient::new(&self.url, self.username.clone(), self.password.clone())
+            .map_err(redact_error)
+    }
+}
+
+/// WebDAV sync metadata
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct WebDavSyncMeta {
+    last_sync_time: Option<i64>,
+    /// Remote `getlastmodified` (Unix seconds) as of the last sync
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote_modified_time: Option<i64>,
+    /// Result of the most recent sync attempt, successful or not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_outcome: Option<LastSyncOutcome>,
+    /// Saved conflict strategy for bidirectional sync; `None` means `Merge`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    conflict_strategy: Option<ConflictStrategy>,
+    /// `sync_crypto::payload_marker` of the remote data.json as of the last sync
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encryption_marker: Option<String>,
+}
+
+/// Whether the remote file changed since the last sync
+///
+/// Without a recorded modification time (first sync, or a server that
+/// doesn't report one) the remote is assumed to have changed.
+fn remote_changed_since(remote_modified: Option<i64>, last_seen: Option<i64>) -> bool {
+    match (remote_modified, last_seen) {
+        (Some(remote), Some(seen)) => remote != seen,
+        _ => true,
+    }
+}
+
+/// Get the data directory path
+fn get_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    paths::data_dir(app)
+}
+
+/// Get the local store directory (.nekotick/store)
+fn get_store_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(get_data_dir(app)?.join(NEKOTICK_FOLDER).join(STORE_FOLDER))
+}
+
+/// Load WebDAV credentials
+fn load_webdav_credentials(app: &tauri::AppHandle) -> Option<WebDavCredentials> {
+    let store_dir = get_store_dir(app).ok()?;
+    match credential_store::read_encrypted(&store_dir, &store_dir.join(WEBDAV_CREDS_FILE)) {
+        Ok(content) => serde_json::from_slice(&content?).ok(),
+        Err(e) => {
+            log::warn!("Failed to read WebDAV credentials: {}", e);
+            None
+        }
+    }
+}
+
+/// Save WebDAV credentials
+fn save_webdav_credentials(app: &tauri::AppHandle, creds: &WebDavCredentials) -> Result<(), String> {
+    let store_dir = get_store_dir(app)?;
+    let content = serde_json::to_vec(creds).map_err(|e| e.to_string())?;
+    credential_store::write_encrypted(&store_dir, &store_dir.join(WEBDAV_CREDS_FILE), &content)
+}
+
+/// Load WebDAV sync metadata
+fn load_webdav_sync_meta(app: &tauri::AppHandle) -> WebDavSyncMeta {
+    get_store_dir(app)
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(WEBDAV_SYNC_META_FILE)).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Save WebDAV sync metadata
+fn save_webdav_sync_meta(app: &tauri::AppHandle, meta: &WebDavSyncMeta) -> Result<(), String> {
+    let store_dir = get_store_dir(app)?;
+    fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(meta).map_err(|e| e.to_string())?;
+    fs::write(store_dir.join(WEBDAV_SYNC_META_FILE), content).map_err(|e| e.to_string())
+}
+
+/// Connect to a WebDAV folder, creating it if needed
+///
+/// Credentials are only saved once the server accepts them.
+#[tauri::command]
+pub async fn webdav_connect(
+    app: tauri::AppHandle,
+    url: String,
+    username: String,
+    password: String,
+) -> Result<WebDavAuthResult, String> {
+    let creds = WebDavCredentials { url, username, password };
+    let client = creds.client()?;
+
+    if let Err(e) = client.ensure_collection().await {
+        let error = match e {
+            WebDavError::Unauthorized => "WebDAV server rejected the username or password".to_string(),
+            e => redact_error(e),
+        };
+        return Ok(WebDavAuthResult {
+            success: false,
+            username: None,
+            error: Some(error),
+        });
+    }
+
+    save_webdav_credentials(&app, &creds)?;
+
+    Ok(WebDavAuthResult {
+        success: true,
+        username: Some(creds.username),
+        error: None,
+    })
+}
+
+/// Remove WebDAV credentials, sync metadata, merge base and queued push under `data_dir`
+fn clear_webdav_local_state(data_dir: &Path) -> Result<(), String> {
+    let nekotick_dir = data_dir.join(NEKOTICK_FOLDER);
+    let store_dir = nekotick_dir.join(STORE_FOLDER);
+    for path in [
+        store_dir.join(WEBDAV_CREDS_FILE),
+        store_dir.join(WEBDAV_SYNC_META_FILE),
+        nekotick_dir.join(WEBDAV_SYNC_BASE_FILE),
+    ] {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+    sync_queue::forget_backend(&nekotick_dir, "webdav")
+}
+
+/// Forget the WebDAV connection and its sync state
+#[tauri::command]
+pub async fn webdav_disconnect(app: tauri::AppHandle) -> Result<(), String> {
+    clear_webdav_local_state(&get_data_dir(&app)?)
+}
+
+/// Disconnect WebDAV for `disconnect_all`, optionally deleting the remote data.json first
+pub async fn disconnect_webdav_provider(app: &tauri::AppHandle, purge_remote: bool) -> ProviderDisconnectReport {
+    let mut report = ProviderDisconnectReport {
+        provider: "webdav".to_string(),
+        was_connected: false,
+        local_cleared: false,
+        remote_purged: false,
+        error: None,
+    };
+
+    let creds = load_webdav_credentials(app);
+    report.was_connected = creds.is_some();
+
+    // Purge remote data first; keep local credentials on failure so the user can retry
+    if purge_remote {
+        if let Some(creds) = &creds {
+            let purged = match creds.client() {
+                Ok(client) => client.delete_file(DATA_FILE_NAME).await.map_err(redact_error),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = purged {
+                report.error = Some(format!("Failed to delete remote data: {}", e));
+                return report;
+            }
+            report.remote_purged = true;
+        }
+    }
+
+    match get_data_dir(app).and_then(|dir| clear_webdav_local_state(&dir)) {
+        Ok(()) => report.local_cleared = true,
+        Err(e) => report.error = Some(e),
+    }
+
+    report
+}
+
+/// Probe the WebDAV backend for `run_sync_diagnostics`, or `None` when not connected
+pub async fn webdav_diagnostics(app: &tauri::AppHandle) -> Option<BackendDiagnostics> {
+    if !get_store_dir(app).ok()?.join(WEBDAV_CREDS_FILE).exists() {
+        return None;
+    }
+
+    let mut report = BackendDiagnostics {
+        backend: "webdav".to_string(),
+        credentials_present: true,
+        ..Default::default()
+    };
+
+    let Some(creds) = load_webdav_credentials(app) else {
+        report.errors.push("Stored credentials could not be read on this device".to_string());
+        return Some(report);
+    };
+
+    let secrets = [creds.password.as_str()];
+    let client = match creds.client() {
+        Ok(client) => client,
+        Err(e) => {
+            report.errors.push(diagnostics::redact(&e, &secrets));
+            return Some(report);
+        }
+    };
+    report.credentials_valid = true;
+
+    // PROPFIND on data.json is authenticated and doubles as the existence check
+    match client.file_exists(DATA_FILE_NAME).await {
+        Ok(exists) => {
+            report.api_reachable = true;
+            report.remote_data_exists = exists;
+        }
+        Err(e) => report.errors.push(diagnostics::redact(&format!("WebDAV: {}", e), &secrets)),
+    }
+
+    Some(report)
+}
+
+/// Get WebDAV sync status
+#[tauri::command]
+pub async fn get_webdav_sync_status(app: tauri::AppHandle) -> Result<WebDavSyncStatus, String> {
+    let meta = load_webdav_sync_meta(&app);
+    let last_outcome = meta.last_outcome.clone();
+    let last_sync_success = last_outcome.as_ref().is_none_or(|o| o.success);
+    let last_sync_error = last_outcome.as_ref().and_then(|o| o.error.clone());
+    let last_sync_error_code = last_outcome.and_then(|o| o.error_code);
+
+    let Some(creds) = load_webdav_credentials(&app) else {
+        return Ok(WebDavSyncStatus {
+            connected: false,
+            url: None,
+            username: None,
+            last_sync_time: None,
+            has_remote_data: false,
+            remote_modified_time: None,
+            last_sync_success,
+            last_sync_error,
+            last_sync_error_code,
+        });
+    };
+
+    // An unreachable server still counts as connected; it just has no remote info
+    let client = creds.client()?;
+    let (has_remote_data, remote_modified_time) = match client.file_exists(DATA_FILE_NAME).await {
+        Ok(true) => (true, client.file_modified_time(DATA_FILE_NAME).await.ok().flatten()),
+        _ => (false, None),
+    };
+
+    Ok(WebDavSyncStatus {
+        connected: true,
+        url: Some(creds.url),
+        username: Some(creds.username),
+        last_sync_time: meta.last_sync_time,
+        has_remote_data,
+        remote_modified_time,
+        last_sync_success,
+        last_sync_error,
+        last_sync_error_code,
+    })
+}
+
+/// In-band failure reported by a sync or restore result
+fn sync_result_failure(result: &WebDavSyncResult) -> Option<SyncError> {
+    (!result.success).then(|| SyncError::Other(result.error.clone().unwrap_or_default()))
+}
+
+/// In-band failure reported by a bidirectional sync result
+fn bidirectional_result_failure(result: &WebDavBidirectionalSyncResult) -> Option<SyncError> {
+    (!result.success).then(|| SyncError::Conflict(result.error.clone().unwrap_or_default()))
+}
+
+/// Record the outcome of a sync attempt in the sync metadata
+fn record_webdav_outcome<T>(
+    app: &tauri::AppHandle,
+    result: &Result<T, SyncError>,
+    reported_failure: impl FnOnce(&T) -> Option<SyncError>,
+) {
+    let Some(outcome) = LastSyncOutcome::from_result(result, reported_failure, chrono::Utc::now().timestamp()) else {
+        return;
+    };
+    let mut meta = load_webdav_sync_meta(app);
+    meta.last_outcome = Some(outcome);
+    if let Err(e) = save_webdav_sync_meta(app, &meta) {
+        log::warn!("Failed to record WebDAV sync outcome: {}", e);
+    }
+}
+
+/// Record the synced data.json as the base for the next merge
+///
+/// Without a base, items deleted on one device can't be told apart from
+/// items added on the other, so every one-way sync records it too.
+fn save_sync_base(base_path: &std::path::Path, content: &str) -> Result<(), String> {
+    let nekotick_dir = base_path.join(NEKOTICK_FOLDER);
+    fs::create_dir_all(&nekotick_dir).map_err(|e| e.to_string())?;
+    fs::write(nekotick_dir.join(WEBDAV_SYNC_BASE_FILE), content)
+        .map_err(|e| format!("Failed to write sync base: {}", e))
+}
+
+/// Upload local data.json to WebDAV
+///
+/// Refuses to overwrite a remote file that changed since the last sync.
+#[tauri::command]
+pub async fn sync_to_webdav(app: tauri::AppHandle, locks: tauri::State<'_, SyncLocks>) -> Result<WebDavSyncResult, SyncError> {
+    let _sync_guard = locks.try_acquire(SyncBackend::WebDav)?;
+
+    let result = run_sync_to_webdav(app.clone()).await;
+    record_webdav_outcome(&app, &result, sync_result_failure);
+    sync_queue::record_push_result(&app, "webdav", &result, |result| result.success);
+    result
+}
+
+async fn run_sync_to_webdav(app: tauri::AppHandle) -> Result<WebDavSyncResult, SyncError> {
+    let creds = load_webdav_credentials(&app).ok_or(SyncError::NotConnected("WebDAV".to_string()))?;
+    let client = creds.client()?;
+
+    let store_dir = get_store_dir(&app)?;
+    let data_json_path = store_dir.join(DATA_FILE_NAME);
+    if !data_json_path.exists() {
+        return Ok(WebDavSyncResult {
+            success: false,
+            timestamp: None,
+            error: Some("No local data to sync".to_string()),
+        });
+    }
+    let content = fs::read_to_string(&data_json_path)
+        .map_err(|e| format!("Failed to read data.json: {}", e))?;
+
+    client.ensure_collection().await?;
+    let mut meta = load_webdav_sync_meta(&app);
+
+    // Don't overwrite edits another device pushed since our last sync
+    let current = client.current_state(DATA_FILE_NAME).await?;
+    let expected = match (current, meta.remote_modified_time) {
+        (ExpectedRemote::Missing, _) => Some(current),
+        (ExpectedRemote::ModifiedAt(modified), Some(last_synced)) => (modified == last_synced).then_some(current),
+        // No recorded version (first sync here, or a server without modification
+        // times): only overwrite a remote that still holds the last synced data
+        _ => {
+            let remote = sync_crypto::open_download(&store_dir, fetch_remote_payload(&client).await?)?;
+            let base = fs::read_to_string(get_data_dir(&app)?.join(NEKOTICK_FOLDER).join(WEBDAV_SYNC_BASE_FILE)).ok();
+            (base.as_deref() == Some(remote.as_str())).then_some(current)
+        }
+    };
+    let Some(expected) = expected else {
+        return Ok(WebDavSyncResult {
+            success: false,
+            timestamp: None,
+            error: Some("Remote data changed on another device. Sync both ways to merge before uploading.".to_string()),
+        });
+    };
+
+    let payload = sync_crypto::seal_for_upload(&store_dir, &content)?;
+    match client.put_file_if_unmodified(DATA_FILE_NAME, &payload, expected).await {
+        Ok(()) => {}
+        Err(e @ WebDavError::RemoteChanged) => {
+            return Ok(WebDavSyncResult {
+                success: false,
+                timestamp: None,
+                error: Some(e.to_string()),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    meta.last_sync_time = Some(now);
+    meta.remote_modified_time = client.file_modified_time(DATA_FILE_NAME).await.ok().flatten();
+    meta.encryption_marker = sync_crypto::payload_marker(&payload);
+    save_webdav_sync_meta(&app, &meta)?;
+    save_sync_base(&get_data_dir(&app)?, &content)?;
+
+    Ok(WebDavSyncResult {
+        success: true,
+        timestamp: Some(now),
+        error: None,
+    })
+}
+
+/// Replace local data.json with the copy on WebDAV
+#[tauri::command]
+pub async fn restore_from_webdav(
+    app: tauri::AppHandle,
+    locks: tauri::State<'_, SyncLocks>,
+    allow_newer_schema: Option<bool>,
+) -> Result<WebDavSyncResult, SyncError> {
+    let _sync_guard = locks.try_acquire(SyncBackend::WebDav)?;
+
+    let result = run_restore_from_webdav(app.clone(), allow_newer_schema).await;
+    record_webdav_outcome(&app, &result, sync_result_failure);
+    result
+}
+
+/// Download the remote data.json as stored, possibly encrypted
+async fn fetch_remote_payload(client: &WebDavClient) -> Result<String, SyncError> {
+    Ok(client
+        .get_file(DATA_FILE_NAME)
+        .await?
+        .ok_or("No remote data found")?)
+}
+
+/// Download and decrypt the remote data.json
+async fn fetch_remote_data(app: &tauri::AppHandle, client: &WebDavClient) -> Result<String, SyncError> {
+    sync_crypto::open_download(&get_store_dir(app)?, fetch_remote_payload(client).await?)
+}
+
+/// Remote data.json for `preview_remote_data`
+pub async fn download_webdav_data(app: &tauri::AppHandle) -> Result<String, SyncError> {
+    let creds = load_webdav_credentials(app).ok_or(SyncError::NotConnected("WebDAV".to_string()))?;
+    fetch_remote_data(app, &creds.client()?).await
+}
+
+async fn run_restore_from_webdav(
+    app: tauri::AppHandle,
+    allow_newer_schema: Option<bool>,
+) -> Result<WebDavSyncResult, SyncError> {
+    let creds = load_webdav_credentials(&app).ok_or(SyncError::NotConnected("WebDAV".to_string()))?;
+    let client = creds.client()?;
+    let payload = fetch_remote_payload(&client).await?;
+    let remote_marker = sync_crypto::payload_marker(&payload);
+    let content = sync_crypto::open_download(&get_store_dir(&app)?, payload)?;
+
+    // Warn instead of silently restoring data this version may not understand
+    let compatibility = schema::check_schema_version(&content)?;
+    if compatibility.is_too_new() && !allow_newer_schema.unwrap_or(false) {
+        return Ok(WebDavSyncResult {
+            success: false,
+            timestamp: None,
+            error: Some("Remote data was written by a newer version of NekoTick. Update the app before restoring.".to_string()),
+        });
+    }
+
+    let store_dir = get_store_dir(&app)?;
+    fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
+    let data_json_path = store_dir.join(DATA_FILE_NAME);
+
+    // Backup existing local data
+    let backup_path = backup::rotate_backup(&store_dir, DATA_FILE_NAME, backup::DEFAULT_BACKUP_KEEP)?;
+
+    if let Err(e) = fs::write(&data_json_path, &content) {
+        // Restore from backup on failure
+        if let Some(backup_path) = &backup_path {
+            let _ = fs::copy(backup_path, &data_json_path);
+        }
+        return Err(format!("Failed to write data.json: {}", e).into());
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    let mut meta = load_webdav_sync_meta(&app);
+    meta.last_sync_time = Some(now);
+    meta.remote_modified_time = client.file_modified_time(DATA_FILE_NAME).await.ok().flatten();
+    meta.encryption_marker = remote_marker;
+    save_webdav_sync_meta(&app, &meta)?;
+    save_sync_base(&get_data_dir(&app)?, &content)?;
+
+    Ok(WebDavSyncResult {
+        success: true,
+        timestamp: Some(now),
+        error: None,
+    })
+}
+
+/// Save the conflict strategy used by bidirectional WebDAV syncs (`None` resets to `Merge`)
+#[tauri::command]
+pub async fn set_webdav_conflict_strategy(
+    app: tauri::AppHandle,
+    strategy: Option<ConflictStrategy>,
+) -> Result<(), String> {
+    let mut meta = load_webdav_sync_meta(&app);
+    meta.conflict_strategy = strategy;
+    save_webdav_sync_meta(&app, &meta)
+}
+
+/// Bidirectional sync with WebDAV
+///
+/// When PROPFIND reports the remote file unchanged since the last sync, the
+/// download is skipped and local data is pushed if it moved on. Otherwise
+/// both sides are reconciled with the conflict strategy, by default a
+/// task-by-task merge against the last synced payload. `strategy`
+/// overrides the saved strategy for this sync only. If another device
+/// pushes while the merge runs, nothing is pushed and the result reports
+/// `remote_changed`.
+#[tauri::command]
+pub async fn sync_webdav_bidirectional(
+    app: tauri::AppHandle,
+    locks: tauri::State<'_, SyncLocks>,
+    strategy: Option<ConflictStrategy>,
+) -> Result<WebDavBidirectionalSyncResult, SyncError> {
+    let sync_guard = locks.try_acquire(SyncBackend::WebDav)?;
+
+    let result = run_sync_webdav_bidirectional(app.clone(), strategy, &sync_guard.cancel).await;
+    record_webdav_outcome(&app, &result, bidirectional_result_failure);
+    sync_queue::record_sync_result(&app, "webdav", &result, |result| result.success);
+    result
+}
+
+async fn run_sync_webdav_bidirectional(
+    app: tauri::AppHandle,
+    strategy: Option<ConflictStrategy>,
+    cancel: &CancellationToken,
+) -> Result<WebDavBidirectionalSyncResult, SyncError> {
+    let creds = load_webdav_credentials(&app).ok_or(SyncError::NotConnected("WebDAV".to_string()))?;
+    let client = creds.client()?;
+    client.ensure_collection().await?;
+
+    let base_path = get_data_dir(&app)?;
+    let store_dir = base_path.join(NEKOTICK_FOLDER).join(STORE_FOLDER);
+    let data_json_path = store_dir.join(DATA_FILE_NAME);
+    let sync_base_path = base_path.join(NEKOTICK_FOLDER).join(WEBDAV_SYNC_BASE_FILE);
+
+    let local_content = if data_json_path.exists() {
+        Some(fs::read_to_string(&data_json_path)
+            .map_err(|e| format!("Failed to read data.json: {}", e))?)
+    } else {
+        None
+    };
+    let base = fs::read_to_string(&sync_base_path).ok();
+
+    let mut meta = load_webdav_sync_meta(&app);
+    let strategy = strategy.or(meta.conflict_strategy).unwrap_or_default();
+    let remote_modified = client.file_modified_time(DATA_FILE_NAME).await?;
+
+    // Unchanged since the last sync: the base is what the server holds
+    let mut remote_marker = meta.encryption_marker.clone();
+    let remote_content = if base.is_some() && !remote_changed_since(remote_modified, meta.remote_modified_time) {
+        base.clone()
+    } else {
+        let content = match client.get_file(DATA_FILE_NAME).await? {
+            Some(content) => {
+                remote_marker = sync_crypto::payload_marker(&content);
+                Some(sync_crypto::open_download(&store_dir, content)?)
+            }
+            None => None,
+        };
+        if let Some(content) = &content {
+            // Stop before pulling (and then pushing back) data from a newer app version
+            if schema::check_schema_version(content)?.is_too_new() {
+                return Err(SyncError::SchemaTooNew);
+            }
+        }
+        content
+    };
+
+    let mut conflicts = Vec::new();
+    let synced_content = match (&local_content, &remote_content) {
+        (Some(local), Some(remote)) => {
+            let outcome = tasks::resolve_task_json(strategy, local, remote, base.as_deref(), chrono::Utc::now().timestamp_millis())?;
+            conflicts = outcome.conflicts;
+            Some(outcome.merged)
+        }
+        (Some(local), None) => Some(local.clone()),
+        (None, Some(remote)) => Some(remote.clone()),
+        (None, None) => None,
+    };
+
+    let mut pulled_from_cloud = false;
+    let mut pushed_to_cloud = false;
+    let mut remote_modified_time = remote_modified;
+
+    if let Some(content) = &synced_content {
+        // Push when the remote is behind the merge, or sealed differently than
+        // uploads are now (the sync passphrase was set or cleared)
+        let resealed = remote_content.is_some() && remote_marker != sync_crypto::upload_marker(&store_dir);
+        let needs_push = remote_content.as_deref() != Some(content.as_str()) || resealed;
+
+        let (pulled, pushed) = write_then_push(
+            cancel,
+            || {
+                if local_content.as_deref() == Some(content.as_str()) {
+                    return Ok(false);
+                }
+                fs::create_dir_all(&store_dir).map_err(|e| e.to_string())?;
+                let _ = backup::rotate_backup(&store_dir, DATA_FILE_NAME, backup::DEFAULT_BACKUP_KEEP);
+                fs::write(&data_json_path, content)
+                    .map_err(|e| format!("Failed to write local data: {}", e))?;
+                Ok(true)
+            },
+            needs_push,
+            || async {
+                // Another device may have pushed while we merged; don't overwrite it
+                let payload = sync_crypto::seal_for_upload(&store_dir, content)?;
+                let expected = ExpectedRemote::from_last_seen(remote_content.is_some(), remote_modified);
+                match client.put_file_if_unmodified(DATA_FILE_NAME, &payload, expected).await {
+                    Ok(()) => Ok(PushOutcome::Pushed(sync_crypto::payload_marker(&payload))),
+                    Err(e @ WebDavError::RemoteChanged) => Ok(PushOutcome::RemoteChanged(e.to_string())),
+                    Err(e) => Err(e.into()),
+                }
+            },
+        )
+        .await?;
+        pulled_from_cloud = pulled;
+
+        match pushed {
+            None => {}
+            Some(PushOutcome::RemoteChanged(error)) => {
+                return Ok(WebDavBidirectionalSyncResult {
+                    success: false,
+                    timestamp: None,
+                    pulled_from_cloud,
+                    pushed_to_cloud: false,
+                    conflicts,
+                    remote_changed: true,
+                    error: Some(error),
+                });
+            }
+            Some(PushOutcome::Pushed(marker)) => {
+                remote_marker = marker;
+                remote_modified_time = client.file_modified_time(DATA_FILE_NAME).await.ok().flatten();
+                pushed_to_cloud = true;
+            }
+        }
+
+        // Both sides now hold this payload; it is the base for the next merge
+        save_sync_base(&base_path, content)?;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    meta.last_sync_time = Some(now);
+    meta.remote_modified_time = remote_modified_time;
+    meta.encryption_marker = remote_marker;
+    save_webdav_sync_meta(&app, &meta)?;
+
+    Ok(WebDavBidirectionalSyncResult {
+        success: true,
+        timestamp: Some(now),
+        pulled_from_cloud,
+        pushed_to_cloud,
+        conflicts,
+        remote_changed: false,
+        error: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remote_changed_since() {
+        assert!(!remote_changed_since(Some(1_700_000_000), Some(1_700_000_000)));
+        assert!(remote_changed_since(Some(1_700_000_060), Some(1_700_000_000)));
+        // Clock skew between servers can move the timestamp backwards too
+        assert!(remote_changed_since(Some(1_699_999_000), Some(1_700_000_000)));
+    }
+
+    #[test]
+    fn test_unknown_modified_time_counts_as_changed() {
+        assert!(remote_changed_since(None, Some(1_700_000_000)));
+        assert!(remote_changed_since(Some(1_700_000_000), None));
+        assert!(remote_changed_since(None, None));
+    }
+
+    #[test]
+    fn test_credentials_round_trip() {
+        let creds = WebDavCredentials {
+            url: "https://cloud.example.com/remote.php/dav/files/neko/NekoTick".to_string(),
+            username: "neko".to_string(),
+            password: "app-password".to_string(),
+        };
+        let json = serde_json::to_vec(&creds).unwrap();
+        let parsed: WebDavCredentials = serde_json::from_slice(&json).unwrap();
+        assert_eq!(parsed.url, creds.url);
+        assert!(parsed.client().is_ok());
+    }
+
+    #[test]
+    fn test_clear_local_state_removes_credentials_and_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let nekotick_dir = dir.path().join(NEKOTICK_FOLDER);
+        let store_dir = nekotick_dir.join(STORE_FOLDER);
+        fs::create_dir_all(&store_dir).unwrap();
+        for path in [
+            store_dir.join(WEBDAV_CREDS_FILE),
+            store_dir.join(WEBDAV_SYNC_META_FILE),
+            nekotick_dir.join(WEBDAV_SYNC_BASE_FILE),
+        ] {
+            fs::write(path, "{}").unwrap();
+        }
+        fs::write(store_dir.join("github_credentials.enc"), "{}").unwrap();
+
+        clear_webdav_local_state(dir.path()).unwrap();
+        assert!(!store_dir.join(WEBDAV_CREDS_FILE).exists());
+        assert!(!store_dir.join(WEBDAV_SYNC_META_FILE).exists());
+        assert!(!nekotick_dir.join(WEBDAV_SYNC_BASE_FILE).exists());
+        assert!(store_dir.join("github_credentials.enc").exists());
+
+        // Nothing left to clear is not an error
+        clear_webdav_local_state(dir.path()).unwrap();
+    }
+}
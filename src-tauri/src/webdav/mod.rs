@@ -0,0 +1,11 @@
+//! WebDAV sync module
+//!
+//! Syncs data.json with a user-supplied WebDAV folder, for Nextcloud,
+//! ownCloud and other self-hosted servers.
+
+pub mod client;
+pub mod commands;
+
+// Re-export commonly used types
+pub use client::WebDavClient;
+pub use commands::*;
@@ -19,6 +19,7 @@ import {
   stat as fsStat,
 } from '@tauri-apps/plugin-fs';
 import { appDataDir } from '@tauri-apps/api/path';
+import { invoke } from '@tauri-apps/api/core';
 import type { StorageAdapter, FileInfo, WriteOptions, ListOptions } from './types';
 
 export class TauriAdapter implements StorageAdapter {
@@ -161,7 +162,8 @@ export class TauriAdapter implements StorageAdapter {
 
   async getBasePath(): Promise<string> {
     if (this.basePath === null) {
-      const appData = await appDataDir();
+      // Ask the backend so a NEKOTICK_DATA_DIR override applies here too
+      const appData = await invoke<string>('get_data_dir').catch(() => appDataDir());
       // Remove trailing slash
       this.basePath = appData.endsWith('\\') || appData.endsWith('/')
         ? appData.slice(0, -1)
@@ -6,6 +6,7 @@
  *   - Windows: C:\Users\{user}\AppData\Roaming\NekoTick
  *   - macOS:   ~/Library/Application Support/NekoTick
  *   - Linux:   ~/.local/share/NekoTick
+ *   - NEKOTICK_DATA_DIR overrides this (portable mode); the backend resolves it
  * - Web: Uses virtual path /nekotick
  */
 